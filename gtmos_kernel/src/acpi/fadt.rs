@@ -0,0 +1,113 @@
+use super::{expect_signature, read_u16, read_u32, read_u64, table_at, AcpiError, GenericAddress};
+
+/// Set in [`Fadt::boot_architecture_flags`] when the system has an 8042 (PS/2) controller.
+pub const BOOT_ARCHITECTURE_8042: u16 = 1 << 1;
+
+/// Set in [`Fadt::flags`] when [`Fadt::reset_register`] can be used to reset the system.
+pub const RESET_REGISTER_SUPPORTED: u32 = 1 << 10;
+
+/// The FADT (Fixed ACPI Description Table), with the signature `"FACP"`. It describes the fixed
+/// power management hardware, such as the registers used to reset and power off the system.
+#[derive(Debug, Clone, Copy)]
+pub struct Fadt<'a> {
+    /// The physical address of the DSDT, which holds the AML code of the system.
+    pub dsdt_address: u64,
+    /// The ISA IRQ of the SCI (System Control Interrupt).
+    pub sci_interrupt: u16,
+    /// The I/O port used to switch the system into ACPI mode, 0 if it is always in ACPI mode.
+    pub smi_command_port: u32,
+    /// The value written to [`Fadt::smi_command_port`] to enable ACPI mode.
+    pub acpi_enable: u8,
+    /// The value written to [`Fadt::smi_command_port`] to disable ACPI mode.
+    pub acpi_disable: u8,
+    /// The PM1a control block, which is written to put the system to sleep or power it off.
+    pub pm1a_control_block: Option<GenericAddress>,
+    pub pm1b_control_block: Option<GenericAddress>,
+    /// The ACPI power management timer, which counts at 3.579545 MHz.
+    pub pm_timer_block: Option<GenericAddress>,
+    /// The index of the century in the CMOS RTC, 0 if there is none.
+    pub century: u8,
+    pub boot_architecture_flags: u16,
+    pub flags: u32,
+    pub reset_register: Option<GenericAddress>,
+    /// The value written to [`Fadt::reset_register`] to reset the system.
+    pub reset_value: u8,
+    table: &'a [u8],
+}
+
+impl<'a> Fadt<'a> {
+    /// Parses a FADT. The checksum of `table` must already be checked.
+    ///
+    /// Older (ACPI 1.0) tables are shorter, fields which they do not have are left empty. When a
+    /// table has both the 32 and the 64 bit version of an address, the 64 bit one is used.
+    pub fn parse(table: &'a [u8]) -> Result<Self, AcpiError> {
+        expect_signature(table, b"FACP")?;
+        let length = (read_u32(table, 4) as usize).min(table.len());
+        if length < 116 {
+            return Err(AcpiError::InvalidLength(*b"FACP"));
+        }
+        let table = &table[..length];
+
+        let dsdt_address = match extended_u64(table, 140) {
+            Some(address) => address,
+            None => read_u32(table, 40) as u64,
+        };
+
+        let reset_register = if length >= 129 && read_u32(table, 112) & RESET_REGISTER_SUPPORTED != 0 {
+            GenericAddress::parse(table, 116)
+        } else {
+            None
+        };
+
+        Ok(Fadt {
+            dsdt_address,
+            sci_interrupt: read_u16(table, 46),
+            smi_command_port: read_u32(table, 48),
+            acpi_enable: table[52],
+            acpi_disable: table[53],
+            pm1a_control_block: extended_address(table, 172)
+                .or_else(|| GenericAddress::io_port(read_u32(table, 64), table[89])),
+            pm1b_control_block: extended_address(table, 184)
+                .or_else(|| GenericAddress::io_port(read_u32(table, 68), table[89])),
+            pm_timer_block: extended_address(table, 208)
+                .or_else(|| GenericAddress::io_port(read_u32(table, 76), table[91])),
+            century: table[108],
+            boot_architecture_flags: read_u16(table, 109),
+            flags: read_u32(table, 112),
+            reset_register,
+            reset_value: if length >= 129 { table[128] } else { 0 },
+            table,
+        })
+    }
+
+    /// Returns `true` if the system has an 8042 (PS/2) controller. ACPI 1.0 tables do not report
+    /// this, so they are assumed to have one.
+    pub fn has_8042(&self) -> bool {
+        self.table[8] < 2 || self.boot_architecture_flags & BOOT_ARCHITECTURE_8042 != 0
+    }
+
+    /// Returns the DSDT (Differentiated System Description Table), which holds the AML code of the
+    /// system.
+    pub fn dsdt(&self) -> Result<&'static [u8], AcpiError> {
+        // SAFETY: the address comes from a table with a valid checksum.
+        let dsdt = unsafe { table_at(self.dsdt_address) }?;
+        expect_signature(dsdt, b"DSDT")?;
+        Ok(dsdt)
+    }
+}
+
+/// Reads the 64 bit address at `offset`, if the table is long enough and the address is set.
+fn extended_u64(table: &[u8], offset: usize) -> Option<u64> {
+    if table.len() < offset + 8 {
+        return None;
+    }
+    Some(read_u64(table, offset)).filter(|address| *address != 0)
+}
+
+/// Reads the generic address structure at `offset`, if the table is long enough and it is set.
+fn extended_address(table: &[u8], offset: usize) -> Option<GenericAddress> {
+    if table.len() < offset + 12 {
+        return None;
+    }
+    GenericAddress::parse(table, offset)
+}
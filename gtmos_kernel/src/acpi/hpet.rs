@@ -0,0 +1,44 @@
+use super::{expect_signature, read_u16, read_u32, AcpiError, GenericAddress, SDT_HEADER_SIZE};
+
+/// The HPET table, with the signature `"HPET"`. It describes a High Precision Event Timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hpet {
+    pub hardware_revision: u8,
+    /// The number of comparators (timers) of the HPET.
+    pub comparator_count: u8,
+    /// `true` if the main counter is 64 bits wide, otherwise it is 32 bits wide.
+    pub counter_64_bit: bool,
+    /// `true` if the HPET can replace the PIT and RTC interrupts (legacy replacement routing).
+    pub legacy_replacement: bool,
+    pub pci_vendor_id: u16,
+    /// The address of the HPET's registers, which is always in system memory.
+    pub base_address: GenericAddress,
+    /// The sequence number of this HPET, when there is more than one.
+    pub hpet_number: u8,
+    /// The minimum number of ticks which must be used for a periodic timer.
+    pub minimum_tick: u16,
+}
+
+impl Hpet {
+    /// Parses an HPET table. The checksum of `table` must already be checked.
+    pub fn parse(table: &[u8]) -> Result<Self, AcpiError> {
+        expect_signature(table, b"HPET")?;
+        let length = (read_u32(table, 4) as usize).min(table.len());
+        if length < SDT_HEADER_SIZE + 20 {
+            return Err(AcpiError::InvalidLength(*b"HPET"));
+        }
+
+        let block_id = read_u32(table, SDT_HEADER_SIZE);
+        Ok(Hpet {
+            hardware_revision: block_id as u8,
+            comparator_count: ((block_id >> 8) & 0b1_1111) as u8 + 1,
+            counter_64_bit: block_id & (1 << 13) != 0,
+            legacy_replacement: block_id & (1 << 15) != 0,
+            pci_vendor_id: (block_id >> 16) as u16,
+            base_address: GenericAddress::parse(table, SDT_HEADER_SIZE + 4)
+                .ok_or(AcpiError::InvalidLength(*b"HPET"))?,
+            hpet_number: table[SDT_HEADER_SIZE + 16],
+            minimum_tick: read_u16(table, SDT_HEADER_SIZE + 17),
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use super::{expect_signature, read_u16, read_u32, read_u64, AcpiError, SDT_HEADER_SIZE};
+
+/// Set in [`Madt::flags`] when the system also has the legacy dual 8259 PICs, which must be masked
+/// before the APICs are used.
+pub const PCAT_COMPAT: u32 = 1;
+
+/// An entry of the MADT, describing one interrupt controller or interrupt routing quirk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MadtEntry {
+    /// A processor and its local APIC.
+    LocalApic { processor_id: u8, apic_id: u8, flags: u32 },
+    /// An I/O APIC, which handles the global system interrupts (GSIs) from `gsi_base` onwards.
+    IoApic { id: u8, address: u32, gsi_base: u32 },
+    /// An ISA interrupt (`source`) which is not connected to the GSI with the same number.
+    InterruptSourceOverride { bus: u8, source: u8, gsi: u32, flags: u16 },
+    /// A GSI which should be configured as a non-maskable interrupt.
+    NmiSource { flags: u16, gsi: u32 },
+    /// A local APIC pin which is connected to the NMI line. A `processor_id` of `0xFF` means all
+    /// processors.
+    LocalApicNmi { processor_id: u8, flags: u16, lint: u8 },
+    /// A 64 bit address of the local APICs which replaces [`Madt::local_apic_address`].
+    LocalApicAddressOverride { address: u64 },
+    /// A processor whose local APIC is in x2APIC mode.
+    LocalX2Apic { x2apic_id: u32, flags: u32, processor_uid: u32 },
+    /// An entry which this parser does not understand.
+    Unknown { entry_type: u8 },
+}
+
+/// The MADT (Multiple APIC Description Table), with the signature `"APIC"`.
+#[derive(Debug, Clone, Copy)]
+pub struct Madt<'a> {
+    /// The 32 bit physical address of the local APIC of each processor.
+    pub local_apic_address: u32,
+    pub flags: u32,
+    entries: &'a [u8],
+}
+
+impl<'a> Madt<'a> {
+    /// Parses a MADT. The checksum of `table` must already be checked.
+    pub fn parse(table: &'a [u8]) -> Result<Self, AcpiError> {
+        expect_signature(table, b"APIC")?;
+        let length = (read_u32(table, 4) as usize).min(table.len());
+        if length < SDT_HEADER_SIZE + 8 {
+            return Err(AcpiError::InvalidLength(*b"APIC"));
+        }
+
+        Ok(Madt {
+            local_apic_address: read_u32(table, SDT_HEADER_SIZE),
+            flags: read_u32(table, SDT_HEADER_SIZE + 4),
+            entries: &table[SDT_HEADER_SIZE + 8..length],
+        })
+    }
+
+    /// Returns `true` if the legacy 8259 PICs are present.
+    pub fn has_8259(&self) -> bool {
+        self.flags & PCAT_COMPAT != 0
+    }
+
+    /// Returns the physical address of the local APICs, taking an address override entry into
+    /// account.
+    pub fn local_apic_physical_address(&self) -> u64 {
+        self.entries()
+            .find_map(|entry| match entry {
+                MadtEntry::LocalApicAddressOverride { address } => Some(address),
+                _ => None,
+            })
+            .unwrap_or(self.local_apic_address as u64)
+    }
+
+    /// Returns an iterator over the entries of the MADT.
+    pub fn entries(&self) -> MadtEntries<'a> {
+        MadtEntries { bytes: self.entries }
+    }
+}
+
+/// An iterator over the entries of a [`Madt`].
+pub struct MadtEntries<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for MadtEntries<'a> {
+    type Item = MadtEntry;
+
+    fn next(&mut self) -> Option<MadtEntry> {
+        if self.bytes.len() < 2 {
+            return None;
+        }
+        let entry_type = self.bytes[0];
+        let length = self.bytes[1] as usize;
+        if length < 2 || length > self.bytes.len() {
+            // A broken entry, the rest of the table can not be trusted.
+            self.bytes = &[];
+            return None;
+        }
+
+        let entry = &self.bytes[..length];
+        self.bytes = &self.bytes[length..];
+
+        Some(match (entry_type, length) {
+            (0, 8..) => MadtEntry::LocalApic {
+                processor_id: entry[2],
+                apic_id: entry[3],
+                flags: read_u32(entry, 4),
+            },
+            (1, 12..) => MadtEntry::IoApic {
+                id: entry[2],
+                address: read_u32(entry, 4),
+                gsi_base: read_u32(entry, 8),
+            },
+            (2, 10..) => MadtEntry::InterruptSourceOverride {
+                bus: entry[2],
+                source: entry[3],
+                gsi: read_u32(entry, 4),
+                flags: read_u16(entry, 8),
+            },
+            (3, 8..) => MadtEntry::NmiSource {
+                flags: read_u16(entry, 2),
+                gsi: read_u32(entry, 4),
+            },
+            (4, 6..) => MadtEntry::LocalApicNmi {
+                processor_id: entry[2],
+                flags: read_u16(entry, 3),
+                lint: entry[5],
+            },
+            (5, 12..) => MadtEntry::LocalApicAddressOverride {
+                address: read_u64(entry, 4),
+            },
+            (9, 16..) => MadtEntry::LocalX2Apic {
+                x2apic_id: read_u32(entry, 4),
+                flags: read_u32(entry, 8),
+                processor_uid: read_u32(entry, 12),
+            },
+            _ => MadtEntry::Unknown { entry_type },
+        })
+    }
+}
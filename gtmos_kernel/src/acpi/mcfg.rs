@@ -0,0 +1,72 @@
+use super::{expect_signature, read_u16, read_u32, read_u64, AcpiError, SDT_HEADER_SIZE};
+
+/// The size of one entry of the MCFG.
+const ENTRY_SIZE: usize = 16;
+
+/// The MCFG table, with the signature `"MCFG"`. It lists where the PCI Express configuration space
+/// of each PCI segment group is memory mapped.
+#[derive(Debug, Clone, Copy)]
+pub struct Mcfg<'a> {
+    entries: &'a [u8],
+}
+
+/// The memory mapped configuration space of the buses `start_bus..=end_bus` in one PCI segment
+/// group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McfgEntry {
+    /// The physical address of the configuration space of bus 0 (even if `start_bus` is not 0).
+    pub base_address: u64,
+    pub segment_group: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+impl McfgEntry {
+    /// Returns the physical address of the 4 KiB configuration space of a PCI function, or `None`
+    /// if the bus is not handled by this entry.
+    pub fn configuration_address(&self, bus: u8, device: u8, function: u8) -> Option<u64> {
+        if bus < self.start_bus || bus > self.end_bus || device >= 32 || function >= 8 {
+            return None;
+        }
+        Some(
+            self.base_address
+                + ((bus as u64) << 20)
+                + ((device as u64) << 15)
+                + ((function as u64) << 12),
+        )
+    }
+}
+
+impl<'a> Mcfg<'a> {
+    /// Parses an MCFG table. The checksum of `table` must already be checked.
+    pub fn parse(table: &'a [u8]) -> Result<Self, AcpiError> {
+        expect_signature(table, b"MCFG")?;
+        let length = (read_u32(table, 4) as usize).min(table.len());
+        // The header is followed by 8 reserved bytes.
+        if length < SDT_HEADER_SIZE + 8 {
+            return Err(AcpiError::InvalidLength(*b"MCFG"));
+        }
+        Ok(Mcfg { entries: &table[SDT_HEADER_SIZE + 8..length] })
+    }
+
+    /// Returns an iterator over the entries of the MCFG.
+    pub fn entries(&self) -> impl Iterator<Item = McfgEntry> + 'a {
+        let entries = self.entries;
+        (0..entries.len() / ENTRY_SIZE).map(move |index| {
+            let offset = index * ENTRY_SIZE;
+            McfgEntry {
+                base_address: read_u64(entries, offset),
+                segment_group: read_u16(entries, offset + 8),
+                start_bus: entries[offset + 10],
+                end_bus: entries[offset + 11],
+            }
+        })
+    }
+
+    /// Returns the entry which handles `bus` in the PCI segment group `segment_group`.
+    pub fn find(&self, segment_group: u16, bus: u8) -> Option<McfgEntry> {
+        self.entries().find(|entry| {
+            entry.segment_group == segment_group && (entry.start_bus..=entry.end_bus).contains(&bus)
+        })
+    }
+}
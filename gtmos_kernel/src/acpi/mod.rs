@@ -0,0 +1,312 @@
+//! Module containing a parser for the ACPI tables provided by the firmware.
+//!
+//! The firmware describes the hardware which can not be probed, such as interrupt controllers,
+//! timers and PCI Express configuration space, in tables found through the RSDP (Root System
+//! Description Pointer). The SubSystem gets the address of the RSDP from the bootloader and hands
+//! it to [`initialise`], after which the tables can be looked up with [`tables`].
+//!
+//! Every table is checked against its checksum before it is returned. Typed views are provided for
+//! the [MADT](madt) (interrupt controllers), [FADT](fadt) (power management), [HPET](hpet) (timer)
+//! and [MCFG](mcfg) (PCI Express configuration space).
+//!
+//! ## See also:
+//! * [RSDP (OsDev.org)](https://wiki.osdev.org/RSDP)
+//! * [RSDT (OsDev.org)](https://wiki.osdev.org/RSDT)
+//! * [XSDT (OsDev.org)](https://wiki.osdev.org/XSDT)
+//! * [MADT (OsDev.org)](https://wiki.osdev.org/MADT)
+//! * [FADT (OsDev.org)](https://wiki.osdev.org/FADT)
+//! * [HPET (OsDev.org)](https://wiki.osdev.org/HPET)
+//! * [PCI Express (OsDev.org)](https://wiki.osdev.org/PCI_Express)
+
+pub mod fadt;
+pub mod hpet;
+pub mod madt;
+pub mod mcfg;
+
+use core::slice;
+
+use spin::Once;
+
+use crate::memory::physical_to_virtual;
+use fadt::Fadt;
+use hpet::Hpet;
+use madt::Madt;
+use mcfg::Mcfg;
+
+/// The size of the header which starts every system description table.
+pub const SDT_HEADER_SIZE: usize = 36;
+
+/// Errors returned while parsing ACPI tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    /// The RSDP does not start with the `"RSD PTR "` signature.
+    InvalidRsdp,
+    /// The checksum of the table with this signature is wrong.
+    InvalidChecksum([u8; 4]),
+    /// The table with this signature is too short to hold its fields.
+    InvalidLength([u8; 4]),
+    /// The table does not have the expected signature.
+    InvalidSignature([u8; 4]),
+    /// There is no table with this signature.
+    NotFound([u8; 4]),
+}
+
+/// The header which starts every system description table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    /// The length of the whole table, including the header.
+    pub length: u32,
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+}
+
+impl SdtHeader {
+    /// Reads the header at the start of `table`.
+    pub fn parse(table: &[u8]) -> Result<Self, AcpiError> {
+        let signature = signature_of(table);
+        if table.len() < SDT_HEADER_SIZE {
+            return Err(AcpiError::InvalidLength(signature));
+        }
+        Ok(SdtHeader {
+            signature,
+            length: read_u32(table, 4),
+            revision: table[8],
+            oem_id: table[10..16].try_into().unwrap(),
+            oem_table_id: table[16..24].try_into().unwrap(),
+            oem_revision: read_u32(table, 24),
+        })
+    }
+}
+
+/// The address space a [`GenericAddress`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    SystemMemory,
+    SystemIo,
+    PciConfiguration,
+    Other(u8),
+}
+
+/// A register described by an ACPI table (the Generic Address Structure).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericAddress {
+    pub address_space: AddressSpace,
+    /// The size of the register in bits.
+    pub bit_width: u8,
+    /// The bit at which the register starts.
+    pub bit_offset: u8,
+    /// The size of the accesses to the register, 1 for bytes up to 4 for 64 bit accesses, or 0 if
+    /// any size can be used.
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl GenericAddress {
+    /// Reads the 12 byte generic address structure at `offset` of `table`. Returns `None` if the
+    /// address is 0, which means the register does not exist.
+    pub fn parse(table: &[u8], offset: usize) -> Option<Self> {
+        let bytes = table.get(offset..offset + 12)?;
+        let address = read_u64(bytes, 4);
+        if address == 0 {
+            return None;
+        }
+        Some(GenericAddress {
+            address_space: match bytes[0] {
+                0 => AddressSpace::SystemMemory,
+                1 => AddressSpace::SystemIo,
+                2 => AddressSpace::PciConfiguration,
+                other => AddressSpace::Other(other),
+            },
+            bit_width: bytes[1],
+            bit_offset: bytes[2],
+            access_size: bytes[3],
+            address,
+        })
+    }
+
+    /// Describes a block of `length` bytes starting at the I/O port `port`, as found in the 32 bit
+    /// fields of older tables. Returns `None` if the port is 0.
+    pub fn io_port(port: u32, length: u8) -> Option<Self> {
+        if port == 0 {
+            return None;
+        }
+        Some(GenericAddress {
+            address_space: AddressSpace::SystemIo,
+            bit_width: length.saturating_mul(8),
+            bit_offset: 0,
+            access_size: 0,
+            address: port as u64,
+        })
+    }
+}
+
+/// The root of the ACPI tables, which is either the RSDT or (on ACPI 2.0 and later) the XSDT.
+pub struct AcpiTables {
+    revision: u8,
+    root: &'static [u8],
+    /// The size of a table address in the root table, 4 bytes for the RSDT and 8 for the XSDT.
+    entry_size: usize,
+}
+
+impl AcpiTables {
+    /// Finds the root table through the RSDP at the physical address `rsdp_address`.
+    ///
+    /// ## Safety
+    /// `rsdp_address` must be the physical address of the RSDP given to us by the firmware, and
+    /// all physical memory must be mapped (see [`crate::memory::physical_to_virtual`]).
+    pub unsafe fn new(rsdp_address: u64) -> Result<Self, AcpiError> {
+        let rsdp = physical_slice(rsdp_address, 20);
+        if &rsdp[0..8] != b"RSD PTR " {
+            return Err(AcpiError::InvalidRsdp);
+        }
+        if !checksum_is_valid(rsdp) {
+            return Err(AcpiError::InvalidChecksum(*b"RSDP"));
+        }
+
+        let revision = rsdp[15];
+        if revision >= 2 {
+            // ACPI 2.0 extends the RSDP with the address of the XSDT and a second checksum.
+            let length = read_u32(physical_slice(rsdp_address, 24), 20) as usize;
+            let extended_rsdp = physical_slice(rsdp_address, length.max(36));
+            if !checksum_is_valid(extended_rsdp) {
+                return Err(AcpiError::InvalidChecksum(*b"RSDP"));
+            }
+
+            let xsdt_address = read_u64(extended_rsdp, 24);
+            if xsdt_address != 0 {
+                let root = table_at(xsdt_address)?;
+                expect_signature(root, b"XSDT")?;
+                return Ok(AcpiTables { revision, root, entry_size: 8 });
+            }
+        }
+
+        let root = table_at(read_u32(rsdp, 16) as u64)?;
+        expect_signature(root, b"RSDT")?;
+        Ok(AcpiTables { revision, root, entry_size: 4 })
+    }
+
+    /// Returns the revision of the RSDP, 0 for ACPI 1.0 and 2 for ACPI 2.0 and later.
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Returns the physical addresses of all tables listed in the root table.
+    pub fn table_addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.root[SDT_HEADER_SIZE..]
+            .chunks_exact(self.entry_size)
+            .map(|entry| match entry.len() {
+                4 => read_u32(entry, 0) as u64,
+                _ => read_u64(entry, 0),
+            })
+    }
+
+    /// Returns all tables listed in the root table whose checksum is valid.
+    pub fn tables(&self) -> impl Iterator<Item = &'static [u8]> + '_ {
+        self.table_addresses()
+            .filter_map(|address| unsafe { table_at(address) }.ok())
+    }
+
+    /// Returns the first table with the signature `signature`.
+    pub fn find(&self, signature: &[u8; 4]) -> Result<&'static [u8], AcpiError> {
+        for address in self.table_addresses() {
+            let header = unsafe { physical_slice(address, 4) };
+            if header == signature {
+                return unsafe { table_at(address) };
+            }
+        }
+        Err(AcpiError::NotFound(*signature))
+    }
+
+    /// Returns the MADT (Multiple APIC Description Table), which lists the interrupt controllers.
+    pub fn madt(&self) -> Result<Madt<'static>, AcpiError> {
+        Madt::parse(self.find(b"APIC")?)
+    }
+
+    /// Returns the FADT (Fixed ACPI Description Table), which describes the power management
+    /// registers.
+    pub fn fadt(&self) -> Result<Fadt<'static>, AcpiError> {
+        Fadt::parse(self.find(b"FACP")?)
+    }
+
+    /// Returns the HPET table, which describes the High Precision Event Timer.
+    pub fn hpet(&self) -> Result<Hpet, AcpiError> {
+        Hpet::parse(self.find(b"HPET")?)
+    }
+
+    /// Returns the MCFG table, which describes the PCI Express configuration space.
+    pub fn mcfg(&self) -> Result<Mcfg<'static>, AcpiError> {
+        Mcfg::parse(self.find(b"MCFG")?)
+    }
+}
+
+/// Returns the table at the physical address `physical_address`, after checking its checksum.
+///
+/// ## Safety
+/// `physical_address` must be the address of an ACPI table and all physical memory must be mapped.
+pub unsafe fn table_at(physical_address: u64) -> Result<&'static [u8], AcpiError> {
+    let header = SdtHeader::parse(physical_slice(physical_address, SDT_HEADER_SIZE))?;
+    if (header.length as usize) < SDT_HEADER_SIZE {
+        return Err(AcpiError::InvalidLength(header.signature));
+    }
+
+    let table = physical_slice(physical_address, header.length as usize);
+    if !checksum_is_valid(table) {
+        return Err(AcpiError::InvalidChecksum(header.signature));
+    }
+    Ok(table)
+}
+
+/// Returns `true` if all bytes of `bytes` add up to zero, which is how every ACPI checksum works.
+pub fn checksum_is_valid(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// Returns an error unless `table` has the signature `signature`.
+pub(crate) fn expect_signature(table: &[u8], signature: &[u8; 4]) -> Result<(), AcpiError> {
+    if &signature_of(table) == signature {
+        Ok(())
+    } else {
+        Err(AcpiError::InvalidSignature(signature_of(table)))
+    }
+}
+
+fn signature_of(table: &[u8]) -> [u8; 4] {
+    let mut signature = [0; 4];
+    let length = table.len().min(4);
+    signature[..length].copy_from_slice(&table[..length]);
+    signature
+}
+
+unsafe fn physical_slice(physical_address: u64, length: usize) -> &'static [u8] {
+    slice::from_raw_parts(physical_to_virtual(physical_address) as *const u8, length)
+}
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+static TABLES: Once<AcpiTables> = Once::new();
+
+/// Finds the ACPI tables through the RSDP at `rsdp_address` and keeps them for [`tables`].
+///
+/// ## Safety
+/// See [`AcpiTables::new`].
+pub unsafe fn initialise(rsdp_address: u64) -> Result<&'static AcpiTables, AcpiError> {
+    TABLES.try_call_once(|| AcpiTables::new(rsdp_address))
+}
+
+/// Returns the ACPI tables, or `None` if [`initialise`] was not called or failed.
+pub fn tables() -> Option<&'static AcpiTables> {
+    TABLES.get()
+}
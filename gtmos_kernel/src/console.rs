@@ -1,73 +1,515 @@
-// use core::fmt::Write;
+//! A text console drawn on the framebuffer.
+//!
+//! Text written to the console may contain the ANSI escape sequences understood by VT100
+//! compatible terminals, so the same output can be sent to serial and to the screen. The
+//! supported sequences are:
+//! * `\n`, `\r`, `\t` and backspace.
+//! * Select Graphic Rendition (`ESC [ ... m`), with the 16 standard colours, the 256 colour
+//!   palette (`38;5;n`) and 24 bit colours (`38;2;r;g;b`).
+//! * Cursor movement (`ESC [ n A` to `ESC [ n G`) and positioning (`ESC [ row ; column H`).
+//! * Erase in display (`ESC [ n J`) and erase in line (`ESC [ n K`).
+//! * Saving and restoring the cursor (`ESC [ s` and `ESC [ u`, or `ESC 7` and `ESC 8`).
+//!
+//! Every character is kept in a [`Grid`], along with the last [`SCROLLBACK_LINES`] lines which
+//! scrolled off the top of the screen. Shift+PageUp and Shift+PageDown browse these lines, see
+//! [`Console::handle_key`].
+//!
+//! The consoles are shown as virtual [`terminals`], which are given to [`set_terminals`]. The
+//! [`print!`](crate::print) and [`println!`](crate::println) macros write to the
+//! [`SHELL`](terminals::SHELL) terminal.
+//!
+//! ## Example
+//! ```rust
+//! println!("\x1b[2J\x1b[H\x1b[1;32mOK\x1b[0m Started {} CPUs", cpus);
+//! ```
+//!
+//! In this example the screen is cleared, then "OK" is written in bright green in the top left
+//! corner, followed by the rest of the line in the default colours.
+//!
+//! ## See also:
+//! * [`ansi`] for the parser of the escape sequences.
+
+pub mod ansi;
+pub mod grid;
+pub mod terminals;
+
+use core::fmt;
 use core::str;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
 
 use crate::drivers::framebuffer::Pixel;
+use crate::drivers::keyboard::{KeyCode, KeyEvent, KeyState};
+use crate::graphics::font::{self, Font};
 use crate::graphics::GraphicsAPI;
+use crate::irq;
+use ansi::{Action, Attributes, Csi, Parser};
+use grid::{Cell, Grid};
+use terminals::Terminals;
+
+/// The distance between tab stops, in columns.
+const TAB_WIDTH: usize = 8;
+
+/// The number of lines kept after they scroll off the screen, unless another number is given to
+/// [`Console::with_scrollback`].
+pub const SCROLLBACK_LINES: usize = 500;
+
+/// The virtual terminals written to by [`print!`](crate::print) and the logger.
+static TERMINALS: Mutex<Option<Terminals<'static>>> = Mutex::new(None);
+
+/// Set when everything printed to the shell terminal is also written to serial.
+static MIRROR_TO_SERIAL: AtomicBool = AtomicBool::new(false);
+
+/// Makes `terminals` the ones which [`print!`](crate::print) and the logger write to, and returns
+/// the terminals which were set before.
+pub fn set_terminals(terminals: Option<Terminals<'static>>) -> Option<Terminals<'static>> {
+    irq::without_interrupts(|| core::mem::replace(&mut *TERMINALS.lock(), terminals))
+}
+
+/// Calls `f` with the virtual terminals, or returns `None` if there are none.
+///
+/// The terminals are locked with interrupts disabled, so interrupt handlers may print as well.
+/// `f` should be short, as interrupts wait until it returns.
+pub fn with_terminals<R>(f: impl FnOnce(&mut Terminals<'static>) -> R) -> Option<R> {
+    irq::without_interrupts(|| TERMINALS.lock().as_mut().map(f))
+}
+
+/// Calls `f` with virtual terminal `index`, or returns `None` if there is no such terminal.
+///
+/// ## Example
+/// ```rust
+/// console::with_terminal(terminals::KERNEL_LOG, |console| console.write_str("Started\n"));
+/// ```
+///
+/// In this example a line is written to the kernel log terminal, whether it is shown or not.
+pub fn with_terminal<R>(index: usize, f: impl FnOnce(&mut Console<'static>) -> R) -> Option<R> {
+    with_terminals(|terminals| terminals.get(index).map(f)).flatten()
+}
+
+/// Sets whether everything printed to the shell terminal is also written to serial, so it can be
+/// read from QEMU's `-serial stdio`.
+pub fn set_serial_mirror(enabled: bool) {
+    MIRROR_TO_SERIAL.store(enabled, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    if MIRROR_TO_SERIAL.load(Ordering::Relaxed) {
+        crate::drivers::serial::_print(args);
+    }
+    with_terminal(terminals::SHELL, |console| console.write_fmt(args));
+}
+
+#[macro_export]
+/// Prints a message to the shell terminal. Use this exactly like the `print` macro from the Rust
+/// standard library.
+///
+/// ## Example
+/// ```rust
+/// print!("Hello, world!");
+/// ```
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::console::_print(format_args!($($arg)*));
+    };
+}
+
+#[macro_export]
+/// Prints a message with a new line to the shell terminal. Use this exactly like the `println`
+/// macro from the Rust standard library.
+///
+/// ## Example
+/// ```rust
+/// println!("Hello, world!");
+/// ```
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($fmt:expr) => ($crate::print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::print!(
+        concat!($fmt, "\n"), $($arg)*));
+}
 
 pub struct Console<'a> {
-    graphics_api: &'a mut GraphicsAPI<'a>,
-    cursor_x: usize,
-    cursor_y: usize,
+    /// Where the console is drawn, or `None` while it is detached, such as a virtual terminal
+    /// which is not being shown.
+    graphics_api: Option<&'a mut GraphicsAPI<'a>>,
+    /// The size of the area the console was created for, in pixels.
+    width: usize,
+    height: usize,
+    font: &'a dyn Font,
     font_size: usize,
+    /// The column of the cursor, which is equal to the number of columns after a character is
+    /// written in the last column. The next character then goes at the start of the next line.
+    column: usize,
+    row: usize,
+    grid: Grid,
+    /// How many lines the view has been moved back into the scrollback, 0 shows the screen.
+    scroll_offset: usize,
+    parser: Parser,
+    attributes: Attributes,
+    default_text_colour: Pixel,
+    default_background_colour: Pixel,
+    /// The cursor and attributes stored by `ESC 7` or `ESC [ s`.
+    saved: (usize, usize, Attributes),
 }
 
 impl<'a> Console<'a> {
+    /// Creates a console which fills `graphics_api`, with characters `font_size` times the size of
+    /// the font. The heap must be initialised, as it holds the characters on the screen.
     pub fn new(graphics_api: &'a mut GraphicsAPI<'a>, font_size: usize) -> Self {
+        Self::with_scrollback(graphics_api, font_size, SCROLLBACK_LINES)
+    }
+
+    /// Creates a console which keeps `lines` lines of scrollback.
+    pub fn with_scrollback(graphics_api: &'a mut GraphicsAPI<'a>, font_size: usize, lines: usize) -> Self {
+        let font: &dyn Font = &font::FONT_8X8;
+        let (width, height) = (graphics_api.get_width(), graphics_api.get_height());
+        let columns = width / (font.width() * font_size);
+        let rows = height / (font.height() * font_size);
         Console {
-            graphics_api,
-            cursor_x: 0,
-            cursor_y: 0,
+            graphics_api: Some(graphics_api),
+            width,
+            height,
+            font,
             font_size,
+            column: 0,
+            row: 0,
+            grid: Grid::new(columns, rows, lines),
+            scroll_offset: 0,
+            parser: Parser::new(),
+            attributes: Attributes::DEFAULT,
+            default_text_colour: Pixel { r: 0xFF, g: 0xFF, b: 0xFF },
+            default_background_colour: Pixel { r: 0, g: 0, b: 0 },
+            saved: (0, 0, Attributes::DEFAULT),
         }
     }
 
-    pub fn set_cursor(&mut self, x: usize, y: usize) {
-        self.cursor_x = x;
-        self.cursor_y = y;
+    /// Changes the font, such as to a [`PsfFont`] with 8x16 cells, and draws the screen again with
+    /// it. The number of columns and rows changes with the size of the font.
+    ///
+    /// [`PsfFont`]: crate::graphics::font::PsfFont
+    pub fn set_font(&mut self, font: &'a dyn Font) {
+        self.font = font;
+        let columns = self.width / self.cell_width();
+        let rows = self.height / self.cell_height();
+        self.row = self.grid.resize(columns, rows, self.row);
+        self.column = self.column.min(self.grid.columns());
+        self.scroll_offset = 0;
+        self.render();
+        self.present();
     }
 
-    pub fn write_str(&mut self, s: &str, text_color: Pixel, background_color: Pixel) {
+    /// Stops drawing the console and hands back where it was drawn. Anything written while the
+    /// console is detached is kept in its grid, and shown once it is attached again.
+    pub fn detach(&mut self) -> Option<&'a mut GraphicsAPI<'a>> {
+        self.graphics_api.take()
+    }
+
+    /// Returns where the console is drawn, or `None` while it is detached.
+    pub fn graphics_api(&mut self) -> Option<&mut GraphicsAPI<'a>> {
+        self.graphics_api.as_deref_mut()
+    }
+
+    /// Starts drawing the console to `graphics_api`, and draws everything in view.
+    pub fn attach(&mut self, graphics_api: &'a mut GraphicsAPI<'a>) {
+        self.graphics_api = Some(graphics_api);
+        self.render();
+        self.present();
+    }
+
+    /// Changes the colours used when no colour has been selected with an escape sequence, or
+    /// after `ESC [ 0 m`, and draws the screen again with them.
+    pub fn set_default_colours(&mut self, text_colour: Pixel, background_colour: Pixel) {
+        self.default_text_colour = text_colour;
+        self.default_background_colour = background_colour;
+        self.render();
+        self.present();
+    }
+
+    /// Moves the cursor to `column` and `row`, counted in characters from the top left corner.
+    pub fn set_cursor(&mut self, column: usize, row: usize) {
+        self.column = column.min(self.columns() - 1);
+        self.row = row.min(self.rows() - 1);
+    }
+
+    /// The number of characters which fit across the screen.
+    pub fn columns(&self) -> usize {
+        self.grid.columns()
+    }
+
+    /// The number of lines which fit on the screen.
+    pub fn rows(&self) -> usize {
+        self.grid.rows()
+    }
+
+    /// Moves the view `lines` further back into the scrollback.
+    pub fn scroll_back(&mut self, lines: usize) {
+        self.set_scroll_offset((self.scroll_offset + lines).min(self.grid.history_length()));
+    }
+
+    /// Moves the view `lines` towards the screen.
+    pub fn scroll_forward(&mut self, lines: usize) {
+        self.set_scroll_offset(self.scroll_offset.saturating_sub(lines));
+    }
+
+    /// Browses the scrollback by half a screen with Shift+PageUp and Shift+PageDown. Returns
+    /// `true` if the key was used, otherwise it should be handled by whatever reads the keyboard.
+    ///
+    /// ## Example
+    /// ```rust
+    /// while let Some(event) = keyboard::read_event() {
+    ///     if !console.handle_key(&event) {
+    ///         // ...
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// In this example the console is given every key first, and the rest are passed on.
+    pub fn handle_key(&mut self, event: &KeyEvent) -> bool {
+        if !event.modifiers.shift() {
+            return false;
+        }
+        let lines = (self.rows() / 2).max(1);
+        match (event.code, event.state) {
+            (KeyCode::PageUp, KeyState::Pressed) => self.scroll_back(lines),
+            (KeyCode::PageDown, KeyState::Pressed) => self.scroll_forward(lines),
+            (KeyCode::PageUp | KeyCode::PageDown, KeyState::Released) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Writes `s` at the cursor, carrying out any escape sequences in it. An escape sequence may
+    /// be split between calls.
+    pub fn write_str(&mut self, s: &str) {
+        // New output is always shown, so the view returns from the scrollback.
+        if !s.is_empty() {
+            self.set_scroll_offset(0);
+        }
         for c in s.chars() {
-            if c == '\n' {
-                // Handle newline character
-                self.cursor_x = 0;
-                self.cursor_y += self.font_size * 8;
-                continue;
+            match self.parser.advance(c) {
+                Some(Action::Print(c)) => self.print(c),
+                Some(Action::Control(c)) => self.control(c),
+                Some(Action::Escape(c)) => self.escape(c),
+                Some(Action::Csi(csi)) => self.control_sequence(&csi),
+                None => {}
             }
+        }
+        self.present();
+    }
 
-            self.graphics_api.draw_char(
-                self.cursor_x,
-                self.cursor_y,
-                c,
-                text_color,
-                background_color,
-                self.font_size,
-            );
-
-            self.cursor_x += self.font_size * 8;
-
-            // Check if text exceeds the width of the screen
-            if self.cursor_x + self.font_size * 8 > self.graphics_api.get_width() {
-                self.cursor_x = 0;
-                self.cursor_y += self.font_size * 8;
+    fn cell_width(&self) -> usize {
+        self.font.width() * self.font_size
+    }
+
+    fn cell_height(&self) -> usize {
+        self.font.height() * self.font_size
+    }
+
+    fn colours(&self) -> (Pixel, Pixel) {
+        self.attributes.colours(self.default_text_colour, self.default_background_colour)
+    }
+
+    fn print(&mut self, c: char) {
+        if self.column >= self.columns() {
+            self.column = 0;
+            self.line_feed();
+        }
+
+        let cell = Cell { character: c, attributes: self.attributes };
+        self.grid.set(self.column, self.row, cell);
+        self.draw_cell(self.column, self.row, cell);
+        self.column += 1;
+    }
+
+    fn present(&mut self) {
+        if let Some(graphics_api) = self.graphics_api.as_deref_mut() {
+            graphics_api.present();
+        }
+    }
+
+    fn draw_cell(&mut self, column: usize, row: usize, cell: Cell) {
+        let (text_colour, background_colour) =
+            cell.attributes.colours(self.default_text_colour, self.default_background_colour);
+        let (x, y) = (column * self.cell_width(), row * self.cell_height());
+        let Some(graphics_api) = self.graphics_api.as_deref_mut() else {
+            return;
+        };
+        graphics_api.draw_char(
+            x,
+            y,
+            cell.character,
+            self.font,
+            text_colour,
+            background_colour,
+            self.font_size,
+        );
+    }
+
+    /// Draws every character in view from the grid.
+    fn render(&mut self) {
+        if self.graphics_api.is_none() {
+            return;
+        }
+        for row in 0..self.rows() {
+            for column in 0..self.columns() {
+                let cell = self.grid.line(self.scroll_offset, row)[column];
+                self.draw_cell(column, row, cell);
+            }
+        }
+    }
+
+    fn set_scroll_offset(&mut self, offset: usize) {
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            self.render();
+            self.present();
+        }
+    }
+
+    fn control(&mut self, c: char) {
+        match c {
+            // Line feed, vertical tab and form feed all start a new line.
+            '\n' | '\x0b' | '\x0c' => {
+                self.column = 0;
+                self.line_feed();
+            }
+            '\r' => self.column = 0,
+            '\t' => self.column = ((self.column / TAB_WIDTH + 1) * TAB_WIDTH).min(self.columns() - 1),
+            // Backspace only moves the cursor, the character is erased by writing over it.
+            '\x08' => self.column = self.column.min(self.columns() - 1).saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '7' => self.save_cursor(),
+            '8' => self.restore_cursor(),
+            // Index, which moves down a line and scrolls at the bottom.
+            'D' => self.line_feed(),
+            // Next line.
+            'E' => {
+                self.column = 0;
+                self.line_feed();
             }
+            // Reverse index.
+            'M' => self.row = self.row.saturating_sub(1),
+            // Reset to the initial state.
+            'c' => {
+                self.attributes = Attributes::DEFAULT;
+                self.erase(0, 0, self.columns(), self.rows());
+                self.set_cursor(0, 0);
+            }
+            _ => {}
+        }
+    }
+
+    fn control_sequence(&mut self, csi: &Csi) {
+        if csi.private {
+            return;
+        }
+        let count = csi.parameter(0, 1) as usize;
+        let (columns, rows) = (self.columns(), self.rows());
+        match csi.command {
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row = (self.row + count).min(rows - 1),
+            'C' => self.column = (self.column + count).min(columns - 1),
+            'D' => self.column = self.column.min(columns - 1).saturating_sub(count),
+            'E' => self.set_cursor(0, self.row + count),
+            'F' => self.set_cursor(0, self.row.saturating_sub(count)),
+            'G' => self.set_cursor(count - 1, self.row),
+            'd' => self.set_cursor(self.column, count - 1),
+            'H' | 'f' => self.set_cursor(csi.parameter(1, 1) as usize - 1, count - 1),
+            'J' => self.erase_in_display(csi.parameter(0, 0)),
+            'K' => self.erase_in_line(csi.parameter(0, 0)),
+            'm' => self.attributes.select_graphic_rendition(csi.parameters()),
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = (self.column, self.row, self.attributes);
+    }
 
-            // Check if text exceeds the height of the screen, and scroll if needed
-            if self.cursor_y + self.font_size * 8 > self.graphics_api.get_height() {
-                self.scroll_up(self.font_size * 8);
-                self.cursor_y -= self.font_size * 8;
+    fn restore_cursor(&mut self) {
+        let (column, row, attributes) = self.saved;
+        self.set_cursor(column, row);
+        self.attributes = attributes;
+    }
+
+    /// Moves the cursor down a line, scrolling the screen up at the bottom.
+    fn line_feed(&mut self) {
+        if self.row + 1 >= self.rows() {
+            self.scroll_up();
+        } else {
+            self.row += 1;
+        }
+    }
+
+    /// Erases from the cursor to the end of the screen (0), from the start of the screen to the
+    /// cursor (1), or the whole screen (2 and 3).
+    fn erase_in_display(&mut self, mode: u16) {
+        let (columns, rows) = (self.columns(), self.rows());
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                self.erase(0, self.row + 1, columns, rows.saturating_sub(self.row + 1));
+            }
+            1 => {
+                self.erase_in_line(1);
+                self.erase(0, 0, columns, self.row);
             }
+            2 | 3 => self.erase(0, 0, columns, rows),
+            _ => {}
         }
     }
 
-    fn scroll_up(&mut self, lines: usize) {
-        let fb_height = self.graphics_api.get_height();
-        let fb_width = self.graphics_api.get_width();
+    /// Erases from the cursor to the end of the line (0), from the start of the line to the
+    /// cursor (1), or the whole line (2).
+    fn erase_in_line(&mut self, mode: u16) {
+        let columns = self.columns();
+        let column = self.column.min(columns - 1);
+        match mode {
+            0 => self.erase(column, self.row, columns - column, 1),
+            1 => self.erase(0, self.row, column + 1, 1),
+            2 => self.erase(0, self.row, columns, 1),
+            _ => {}
+        }
+    }
+
+    /// Fills `columns` by `rows` characters from (`column`, `row`) with the background colour.
+    fn erase(&mut self, column: usize, row: usize, columns: usize, rows: usize) {
+        self.grid.fill(column, row, columns, rows, Cell::blank(self.attributes));
+        let (_, background_colour) = self.colours();
+        let (cell_width, cell_height) = (self.cell_width(), self.cell_height());
+        let Some(graphics_api) = self.graphics_api.as_deref_mut() else {
+            return;
+        };
+        graphics_api.draw_filled_rectangle(
+            column * cell_width,
+            row * cell_height,
+            columns * cell_width,
+            rows * cell_height,
+            background_colour,
+        );
+    }
 
-        // Copy the entire framebuffer content up by 'lines' pixels
-        self.graphics_api.copy_rect(0, lines, fb_width, fb_height - lines, 0, 0, Pixel { r: 0, g: 0, b: 0 });
+    /// Moves every line up by one into the scrollback, clears the bottom line, and draws the view
+    /// again from the grid.
+    fn scroll_up(&mut self) {
+        self.grid.scroll_up(Cell::blank(self.attributes));
+        self.render();
+    }
+}
 
-        // Clear the newly created empty space at the bottom
-        self.graphics_api.draw_filled_rectangle(0, fb_height - lines, fb_width, lines, Pixel { r: 0, g: 0, b: 0 });
+impl fmt::Write for Console<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        Console::write_str(self, s);
+        Ok(())
     }
 }
@@ -0,0 +1,332 @@
+//! A parser for the ANSI escape sequences understood by VT100 compatible terminals.
+//!
+//! The [`Parser`] is fed one character at a time and returns an [`Action`] whenever a character
+//! is printable, a control character, or finishes an escape sequence. It keeps no screen state
+//! of its own, so the [`Console`](super::Console) decides what each action does.
+//!
+//! ## Example
+//! ```rust
+//! let mut parser = Parser::new();
+//! for c in "\x1b[1;31mError".chars() {
+//!     match parser.advance(c) {
+//!         Some(Action::Print(c)) => serial_print!("{}", c),
+//!         Some(action) => serial_println!("\n{:?}", action),
+//!         None => {}
+//!     }
+//! }
+//! ```
+//!
+//! In this example `\x1b[1;31m` is returned as one [`Action::Csi`] with the command `m` and the
+//! parameters 1 and 31, followed by an [`Action::Print`] for each letter of "Error", which are
+//! written to serial.
+//!
+//! ## See also:
+//! * [ANSI escape code (Wikipedia)](https://en.wikipedia.org/wiki/ANSI_escape_code)
+//! * [A parser for DEC's ANSI-compatible video terminals](https://vt100.net/emu/dec_ansi_parser)
+
+use crate::drivers::framebuffer::Pixel;
+
+/// The most parameters kept for one control sequence, any more are ignored.
+pub const MAX_PARAMETERS: usize = 16;
+
+const ESCAPE: char = '\x1b';
+/// Cancels the escape sequence being parsed.
+const CANCEL: char = '\x18';
+/// Cancels the escape sequence being parsed.
+const SUBSTITUTE: char = '\x1a';
+const DELETE: char = '\x7f';
+
+/// Something for the terminal to do, returned by [`Parser::advance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Draws a character at the cursor.
+    Print(char),
+    /// A C0 control character, such as `\n`, `\r`, `\t` or backspace.
+    Control(char),
+    /// An escape sequence of `ESC` followed by one character, such as `ESC 7` to save the cursor.
+    Escape(char),
+    /// A control sequence starting with `ESC [`.
+    Csi(Csi),
+}
+
+/// A control sequence, made of numeric parameters separated by `;` and a final command character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csi {
+    parameters: [u16; MAX_PARAMETERS],
+    count: usize,
+    /// Set when the parameters start with `?`, `<`, `=` or `>`, which are used by private
+    /// sequences such as `ESC [ ? 25 l` to hide the cursor.
+    pub private: bool,
+    pub command: char,
+}
+
+impl Csi {
+    const fn new() -> Self {
+        Csi { parameters: [0; MAX_PARAMETERS], count: 0, private: false, command: '\0' }
+    }
+
+    pub fn parameters(&self) -> &[u16] {
+        &self.parameters[..self.count.min(MAX_PARAMETERS)]
+    }
+
+    /// Returns the parameter at `index`, or `default` if it is missing or 0, which is how cursor
+    /// movements treat their counts.
+    pub fn parameter(&self, index: usize, default: u16) -> u16 {
+        match self.parameters().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// Inside an escape sequence with intermediate characters, such as `ESC ( B` to select a
+    /// character set. These are not supported, so the sequence is skipped.
+    EscapeIntermediate,
+    Csi,
+    /// Inside a malformed control sequence, which is skipped up to its final character.
+    CsiIgnore,
+}
+
+/// Turns a stream of characters into [`Action`]s.
+pub struct Parser {
+    state: State,
+    csi: Csi,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub const fn new() -> Self {
+        Parser { state: State::Ground, csi: Csi::new() }
+    }
+
+    /// Parses the next character, returning an action if it completes one.
+    pub fn advance(&mut self, c: char) -> Option<Action> {
+        match c {
+            ESCAPE => {
+                self.state = State::Escape;
+                return None;
+            }
+            CANCEL | SUBSTITUTE => {
+                self.state = State::Ground;
+                return None;
+            }
+            // Control characters are carried out even in the middle of an escape sequence.
+            '\0'..='\x1f' => return Some(Action::Control(c)),
+            DELETE => return None,
+            _ => {}
+        }
+
+        match self.state {
+            State::Ground => Some(Action::Print(c)),
+            State::Escape => match c {
+                '[' => {
+                    self.csi = Csi::new();
+                    self.state = State::Csi;
+                    None
+                }
+                ' '..='/' => {
+                    self.state = State::EscapeIntermediate;
+                    None
+                }
+                _ => {
+                    self.state = State::Ground;
+                    Some(Action::Escape(c))
+                }
+            },
+            State::EscapeIntermediate => {
+                if !(' '..='/').contains(&c) {
+                    self.state = State::Ground;
+                }
+                None
+            }
+            State::Csi => match c {
+                '0'..='9' => {
+                    if self.csi.count == 0 {
+                        self.csi.count = 1;
+                    }
+                    if let Some(parameter) = self.csi.parameters.get_mut(self.csi.count - 1) {
+                        let digit = c as u16 - '0' as u16;
+                        *parameter = parameter.saturating_mul(10).saturating_add(digit);
+                    }
+                    None
+                }
+                // Colons separate sub parameters, as in `38:2:r:g:b`, which are treated the same.
+                ';' | ':' => {
+                    self.csi.count = self.csi.count.max(1) + 1;
+                    None
+                }
+                '<'..='?' if self.csi.count == 0 && !self.csi.private => {
+                    self.csi.private = true;
+                    None
+                }
+                '@'..='~' => {
+                    self.state = State::Ground;
+                    self.csi.command = c;
+                    Some(Action::Csi(self.csi))
+                }
+                _ => {
+                    self.state = State::CsiIgnore;
+                    None
+                }
+            },
+            State::CsiIgnore => {
+                if ('@'..='~').contains(&c) {
+                    self.state = State::Ground;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// A colour chosen by a Select Graphic Rendition (SGR) sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    /// The console's default text or background colour.
+    Default,
+    /// An entry of the 256 colour [`palette`].
+    Indexed(u8),
+    Rgb(Pixel),
+}
+
+/// How characters are drawn, changed by the `ESC [ ... m` sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attributes {
+    pub foreground: Colour,
+    pub background: Colour,
+    /// Draws the first 8 palette colours in their bright versions, as the Linux console does.
+    pub bold: bool,
+    /// Swaps the text and background colours.
+    pub reverse: bool,
+}
+
+impl Attributes {
+    pub const DEFAULT: Attributes = Attributes {
+        foreground: Colour::Default,
+        background: Colour::Default,
+        bold: false,
+        reverse: false,
+    };
+
+    /// Applies the parameters of a Select Graphic Rendition sequence. Unknown parameters are
+    /// ignored, and no parameters resets the attributes.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let mut attributes = Attributes::DEFAULT;
+    /// attributes.select_graphic_rendition(&[38, 2, 255, 128, 0, 44]);
+    /// ```
+    ///
+    /// In this example the text colour is set to orange (with 24 bit colour) and the background
+    /// to cyan (the seventh palette colour).
+    pub fn select_graphic_rendition(&mut self, parameters: &[u16]) {
+        if parameters.is_empty() {
+            *self = Self::DEFAULT;
+        }
+        let mut parameters = parameters.iter().copied();
+        while let Some(parameter) = parameters.next() {
+            match parameter {
+                0 => *self = Self::DEFAULT,
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                30..=37 => self.foreground = Colour::Indexed((parameter - 30) as u8),
+                38 => {
+                    if let Some(colour) = extended_colour(&mut parameters) {
+                        self.foreground = colour;
+                    }
+                }
+                39 => self.foreground = Colour::Default,
+                40..=47 => self.background = Colour::Indexed((parameter - 40) as u8),
+                48 => {
+                    if let Some(colour) = extended_colour(&mut parameters) {
+                        self.background = colour;
+                    }
+                }
+                49 => self.background = Colour::Default,
+                90..=97 => self.foreground = Colour::Indexed((parameter - 90 + 8) as u8),
+                100..=107 => self.background = Colour::Indexed((parameter - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the text and background colours to draw with, using `foreground` and `background`
+    /// for [`Colour::Default`].
+    pub fn colours(&self, foreground: Pixel, background: Pixel) -> (Pixel, Pixel) {
+        let text = match self.foreground {
+            Colour::Default => foreground,
+            Colour::Indexed(index) if self.bold && index < 8 => palette(index + 8),
+            Colour::Indexed(index) => palette(index),
+            Colour::Rgb(pixel) => pixel,
+        };
+        let background = match self.background {
+            Colour::Default => background,
+            Colour::Indexed(index) => palette(index),
+            Colour::Rgb(pixel) => pixel,
+        };
+        if self.reverse { (background, text) } else { (text, background) }
+    }
+}
+
+/// Reads the colour following a 38 or 48 parameter, which is either `5;n` for a palette colour or
+/// `2;r;g;b` for a 24 bit colour.
+fn extended_colour(parameters: &mut impl Iterator<Item = u16>) -> Option<Colour> {
+    let mut channel = || parameters.next().map(|value| value.min(0xFF) as u8);
+    match channel()? {
+        5 => Some(Colour::Indexed(channel()?)),
+        2 => Some(Colour::Rgb(Pixel { r: channel()?, g: channel()?, b: channel()? })),
+        _ => None,
+    }
+}
+
+/// The 16 standard colours, in the order black, red, green, yellow, blue, magenta, cyan and white,
+/// followed by their bright versions.
+const STANDARD_COLOURS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xAA, 0x00, 0x00),
+    (0x00, 0xAA, 0x00),
+    (0xAA, 0x55, 0x00),
+    (0x00, 0x00, 0xAA),
+    (0xAA, 0x00, 0xAA),
+    (0x00, 0xAA, 0xAA),
+    (0xAA, 0xAA, 0xAA),
+    (0x55, 0x55, 0x55),
+    (0xFF, 0x55, 0x55),
+    (0x55, 0xFF, 0x55),
+    (0xFF, 0xFF, 0x55),
+    (0x55, 0x55, 0xFF),
+    (0xFF, 0x55, 0xFF),
+    (0x55, 0xFF, 0xFF),
+    (0xFF, 0xFF, 0xFF),
+];
+
+/// Returns a colour of the xterm 256 colour palette: the 16 standard colours, a 6x6x6 colour cube,
+/// then 24 shades of grey.
+pub fn palette(index: u8) -> Pixel {
+    match index {
+        0..=15 => {
+            let (r, g, b) = STANDARD_COLOURS[index as usize];
+            Pixel { r, g, b }
+        }
+        16..=231 => {
+            let index = index - 16;
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            Pixel { r: level(index / 36), g: level(index / 6 % 6), b: level(index % 6) }
+        }
+        _ => {
+            let grey = 8 + (index - 232) * 10;
+            Pixel { r: grey, g: grey, b: grey }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! The characters shown by the console, and the lines which have scrolled off the top of the
+//! screen.
+//!
+//! The console draws every character from the [`Grid`], so the screen can be drawn again after
+//! scrolling or when browsing the scrollback, without reading pixels back from the framebuffer.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::ansi::Attributes;
+
+/// A character on the screen, and the attributes it was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub character: char,
+    pub attributes: Attributes,
+}
+
+impl Cell {
+    pub const BLANK: Cell = Cell { character: ' ', attributes: Attributes::DEFAULT };
+
+    /// A space, which shows only the background colour of `attributes`.
+    pub const fn blank(attributes: Attributes) -> Self {
+        Cell { character: ' ', attributes }
+    }
+}
+
+/// A screen of `columns` by `rows` cells, and a ring of the most recent lines which scrolled off
+/// its top.
+///
+/// ## Example
+/// ```rust
+/// let mut grid = Grid::new(80, 25, 100);
+/// grid.set(0, 0, Cell { character: 'A', ..Cell::BLANK });
+/// grid.scroll_up(Cell::BLANK);
+/// assert_eq!(grid.line(1, 0)[0].character, 'A');
+/// ```
+///
+/// In this example a line is scrolled into the scrollback, and is seen again at the top of the
+/// screen when the view is moved back by one line.
+pub struct Grid {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    /// The lines which scrolled off the screen, stored one after another from `history_start`.
+    history: Vec<Cell>,
+    history_capacity: usize,
+    /// The line in `history` holding the oldest line.
+    history_start: usize,
+    history_length: usize,
+}
+
+impl Grid {
+    /// Creates a blank grid which keeps up to `scrollback` lines of history.
+    pub fn new(columns: usize, rows: usize, scrollback: usize) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        Grid {
+            columns,
+            rows,
+            cells: vec![Cell::BLANK; columns * rows],
+            history: vec![Cell::BLANK; columns * scrollback],
+            history_capacity: scrollback,
+            history_start: 0,
+            history_length: 0,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of lines in the scrollback.
+    pub fn history_length(&self) -> usize {
+        self.history_length
+    }
+
+    /// Returns the cell at `column` and `row` on the screen.
+    pub fn get(&self, column: usize, row: usize) -> Cell {
+        self.cells[row * self.columns + column]
+    }
+
+    pub fn set(&mut self, column: usize, row: usize, cell: Cell) {
+        self.cells[row * self.columns + column] = cell;
+    }
+
+    /// Sets `columns` by `rows` cells from `column` and `row` to `cell`, cut to fit the screen.
+    pub fn fill(&mut self, column: usize, row: usize, columns: usize, rows: usize, cell: Cell) {
+        let end_column = (column + columns).min(self.columns);
+        for row in row..(row + rows).min(self.rows) {
+            let start = row * self.columns;
+            self.cells[start + column.min(end_column)..start + end_column].fill(cell);
+        }
+    }
+
+    /// Moves every line of the screen up by one, adding the top line to the scrollback, and fills
+    /// the bottom line with `blank`.
+    pub fn scroll_up(&mut self, blank: Cell) {
+        if self.history_capacity > 0 {
+            let line = if self.history_length < self.history_capacity {
+                self.history_length += 1;
+                (self.history_start + self.history_length - 1) % self.history_capacity
+            } else {
+                // The ring is full, so the oldest line is replaced.
+                let line = self.history_start;
+                self.history_start = (self.history_start + 1) % self.history_capacity;
+                line
+            };
+            let start = line * self.columns;
+            self.history[start..start + self.columns].copy_from_slice(&self.cells[..self.columns]);
+        }
+
+        self.cells.copy_within(self.columns.., 0);
+        let last = (self.rows - 1) * self.columns;
+        self.cells[last..].fill(blank);
+    }
+
+    /// Returns the cells of `row` on a screen which has been moved back by `offset` lines into the
+    /// scrollback. `offset` is limited to the length of the scrollback.
+    pub fn line(&self, offset: usize, row: usize) -> &[Cell] {
+        let line = self.history_length - offset.min(self.history_length) + row;
+        if line < self.history_length {
+            let start = (self.history_start + line) % self.history_capacity * self.columns;
+            &self.history[start..start + self.columns]
+        } else {
+            let start = (line - self.history_length) * self.columns;
+            &self.cells[start..start + self.columns]
+        }
+    }
+
+    /// Changes the size of the screen, keeping the start of each line. The scrollback and the lines
+    /// up to `row` are written to the new screen again, scrolling when it is full, and the new row
+    /// of the line which was at `row` is returned.
+    pub fn resize(&mut self, columns: usize, rows: usize, row: usize) -> usize {
+        let mut resized = Grid::new(columns, rows, self.history_capacity);
+        let lines = self.history_length + row.min(self.rows - 1) + 1;
+        for line in 0..lines {
+            if line >= resized.rows {
+                resized.scroll_up(Cell::BLANK);
+            }
+            let source = self.line(self.history_length, line);
+            let length = source.len().min(resized.columns);
+            let start = line.min(resized.rows - 1) * resized.columns;
+            resized.cells[start..start + length].copy_from_slice(&source[..length]);
+        }
+        *self = resized;
+        (lines - 1).min(self.rows - 1)
+    }
+}
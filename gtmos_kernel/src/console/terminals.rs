@@ -0,0 +1,149 @@
+//! Virtual terminals, several consoles which take turns to be shown on the screen.
+//!
+//! Every terminal keeps its own characters, cursor and scrollback, but only the one being shown
+//! is drawn. Alt+F1 to Alt+F6 switch between them, as on Linux. The [`SHELL`] terminal is shown
+//! at boot and is written to by [`print!`](crate::print), and the [`KERNEL_LOG`] terminal is
+//! written to by the [`logger`](crate::logger).
+//!
+//! ## Example
+//! ```rust
+//! let mut terminals = Terminals::new(graphics_api, 2);
+//! terminals.get(KERNEL_LOG).unwrap().write_str("Started\n");
+//! terminals.switch_to(KERNEL_LOG);
+//! ```
+//!
+//! In this example a line is written to the kernel log terminal while the shell is shown, then the
+//! kernel log is shown with the line on it.
+
+use crate::devices::{self, CharDevice, Device, DeviceError};
+use crate::drivers::keyboard::{KeyCode, KeyEvent, KeyState};
+use crate::graphics::GraphicsAPI;
+use super::Console;
+
+/// The number of virtual terminals, one for each of the keys F1 to F6.
+pub const TERMINAL_COUNT: usize = 6;
+
+/// The terminal for the interactive shell, shown at boot with Alt+F1.
+pub const SHELL: usize = 0;
+
+/// The terminal which shows the kernel log, with Alt+F2.
+pub const KERNEL_LOG: usize = 1;
+
+/// The names the terminals are registered with as [`CharDevice`]s, numbered from 1 as on Linux.
+pub const DEVICE_NAMES: [&str; TERMINAL_COUNT] = ["tty1", "tty2", "tty3", "tty4", "tty5", "tty6"];
+
+const SWITCH_KEYS: [KeyCode; TERMINAL_COUNT] =
+    [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6];
+
+/// The virtual terminals, and which one is shown.
+pub struct Terminals<'a> {
+    consoles: [Console<'a>; TERMINAL_COUNT],
+    active: usize,
+}
+
+impl<'a> Terminals<'a> {
+    /// Creates [`TERMINAL_COUNT`] consoles filling `graphics_api`, with characters `font_size`
+    /// times the size of the font, and shows the [`SHELL`].
+    pub fn new(graphics_api: &'a mut GraphicsAPI<'a>, font_size: usize) -> Self {
+        // The graphics API is lent to each console while it is created, then to the shell.
+        let mut graphics_api = Some(graphics_api);
+        let mut consoles: [Console<'a>; TERMINAL_COUNT] = core::array::from_fn(|_| {
+            let mut console = Console::new(graphics_api.take().expect("the console gives it back"), font_size);
+            graphics_api = console.detach();
+            console
+        });
+        consoles[SHELL].attach(graphics_api.expect("the console gives it back"));
+        Terminals { consoles, active: SHELL }
+    }
+
+    /// The number of the terminal which is shown.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Returns terminal `index`, or `None` if there is no such terminal.
+    pub fn get(&mut self, index: usize) -> Option<&mut Console<'a>> {
+        self.consoles.get_mut(index)
+    }
+
+    /// Returns every terminal, such as to set the font of all of them.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Console<'a>> {
+        self.consoles.iter_mut()
+    }
+
+    /// Shows terminal `index` instead of the active terminal. Nothing happens if there is no
+    /// such terminal.
+    pub fn switch_to(&mut self, index: usize) {
+        if index == self.active || index >= TERMINAL_COUNT {
+            return;
+        }
+        if let Some(graphics_api) = self.consoles[self.active].detach() {
+            self.consoles[index].attach(graphics_api);
+            self.active = index;
+        }
+    }
+
+    /// Moves the mouse cursor's tip to (x, y) on the screen. The cursor is kept by the graphics
+    /// API, so it stays in place when the terminal is switched.
+    pub fn move_cursor(&mut self, x: usize, y: usize) {
+        if let Some(graphics_api) = self.consoles[self.active].graphics_api() {
+            graphics_api.move_cursor(x, y);
+        }
+    }
+
+    /// Switches terminal with Alt+F1 to Alt+F6, and gives any other key to the active terminal.
+    /// Returns `true` if the key was used.
+    pub fn handle_key(&mut self, event: &KeyEvent) -> bool {
+        if event.modifiers.alt() {
+            if let Some(index) = SWITCH_KEYS.iter().position(|key| *key == event.code) {
+                if event.state == KeyState::Pressed {
+                    self.switch_to(index);
+                }
+                return true;
+            }
+        }
+        self.consoles[self.active].handle_key(event)
+    }
+}
+
+/// Gives a terminal of the ones set with [`set_terminals`](super::set_terminals) to the
+/// [`devices`] manager. Text is written to it, but typed keys are read from the keyboard.
+pub struct TerminalDevice {
+    index: usize,
+}
+
+pub static DEVICES: [TerminalDevice; TERMINAL_COUNT] = [
+    TerminalDevice { index: 0 },
+    TerminalDevice { index: 1 },
+    TerminalDevice { index: 2 },
+    TerminalDevice { index: 3 },
+    TerminalDevice { index: 4 },
+    TerminalDevice { index: 5 },
+];
+
+impl CharDevice for TerminalDevice {
+    fn read(&self, _buffer: &mut [u8]) -> Result<usize, DeviceError> {
+        Err(DeviceError::Unsupported)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize, DeviceError> {
+        super::with_terminal(self.index, |console| {
+            for chunk in data.utf8_chunks() {
+                console.write_str(chunk.valid());
+                if !chunk.invalid().is_empty() {
+                    console.write_str("\u{FFFD}");
+                }
+            }
+        })
+        .ok_or(DeviceError::Io)?;
+        Ok(data.len())
+    }
+}
+
+/// Registers every terminal as a [`CharDevice`] named from [`DEVICE_NAMES`].
+pub fn register_devices() -> Result<(), DeviceError> {
+    for (name, device) in DEVICE_NAMES.iter().zip(&DEVICES) {
+        devices::register(name, Device::Char(device))?;
+    }
+    Ok(())
+}
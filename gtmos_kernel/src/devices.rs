@@ -0,0 +1,327 @@
+//! Module containing the kernel's device manager.
+//!
+//! Drivers register each device they find with [`register`], under a name which is unique for its
+//! [`DeviceClass`], such as `serial0` for the first serial port. The kernel then looks the device
+//! up by its class and name, and talks to it through the trait of its class, like [`CharDevice`]
+//! for a serial port or a terminal. Looking up a name which was never registered, or a device of
+//! another class, is an error.
+//!
+//! ## Example
+//! ```rust
+//! pub static SERIAL1_DEVICE: Serial1Device = Serial1Device;
+//!
+//! devices::register("serial0", Device::Char(&SERIAL1_DEVICE))?;
+//! devices::char_device("serial0")?.write(b"Hello, world!\n")?;
+//! ```
+//!
+//! In this example a SubSystem registers its first serial port, which the kernel then finds and
+//! writes a line to.
+//!
+//! ## See also:
+//! * [Device Management](./../docs/design/kernel/platform-abstraction/device-manager/index.md)
+
+use core::fmt;
+
+use spin::Mutex;
+
+use crate::drivers::framebuffer::Pixel;
+use crate::drivers::keyboard::KeyEvent;
+use crate::drivers::mouse::MouseEvent;
+use crate::irq;
+
+/// The largest number of devices which can be registered.
+pub const MAX_DEVICES: usize = 32;
+
+/// The longest name a device may be registered with.
+pub const NAME_LENGTH: usize = 16;
+
+/// The kinds of device, each of which is used through its own trait.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceClass {
+    /// Devices which send and receive a stream of bytes, see [`CharDevice`].
+    Char,
+    /// Storage which is read and written in blocks, see [`BlockDevice`].
+    Block,
+    /// Screens, see [`DisplayDevice`].
+    Display,
+    /// Keyboards, mice and other devices which report [`InputEvent`]s, see [`InputDevice`].
+    Input,
+    /// Network cards, see [`NetDevice`].
+    Net,
+}
+
+/// Errors returned by the device manager and by devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceError {
+    /// No device has been registered with the name.
+    NotFound,
+    /// The device with the name is of another class.
+    WrongClass { expected: DeviceClass, found: DeviceClass },
+    /// A device of the same class has already been registered with the name.
+    AlreadyRegistered,
+    /// The name is empty or longer than [`NAME_LENGTH`].
+    InvalidName,
+    /// All [`MAX_DEVICES`] entries are in use.
+    TooManyDevices,
+    /// The device can not do what was asked, such as writing to read only storage.
+    Unsupported,
+    /// The device has gone away or failed to do what was asked.
+    Io,
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceError::NotFound => write!(f, "no such device"),
+            DeviceError::WrongClass { expected, found } => {
+                write!(f, "expected a {:?} device, found a {:?} device", expected, found)
+            }
+            DeviceError::AlreadyRegistered => write!(f, "the name is already in use"),
+            DeviceError::InvalidName => write!(f, "invalid device name"),
+            DeviceError::TooManyDevices => write!(f, "too many devices"),
+            DeviceError::Unsupported => write!(f, "operation not supported by the device"),
+            DeviceError::Io => write!(f, "input/output error"),
+        }
+    }
+}
+
+/// A device which sends and receives a stream of bytes, such as a serial port or a terminal.
+pub trait CharDevice: Sync {
+    /// Moves the bytes which have arrived into `buffer` without waiting, and returns how many
+    /// were read.
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, DeviceError>;
+
+    /// Sends `data`, and returns how many bytes were sent.
+    fn write(&self, data: &[u8]) -> Result<usize, DeviceError>;
+}
+
+/// Storage which is read and written in blocks of [`block_size`](BlockDevice::block_size) bytes.
+pub trait BlockDevice: Sync {
+    fn block_size(&self) -> usize;
+
+    /// The number of blocks on the device.
+    fn block_count(&self) -> u64;
+
+    /// Reads the blocks from `first` into `buffer`, whose length is a multiple of the block size.
+    fn read_blocks(&self, first: u64, buffer: &mut [u8]) -> Result<(), DeviceError>;
+
+    /// Writes `data`, whose length is a multiple of the block size, to the blocks from `first`.
+    fn write_blocks(&self, first: u64, data: &[u8]) -> Result<(), DeviceError>;
+}
+
+/// A screen, which is drawn on pixel by pixel.
+pub trait DisplayDevice: Sync {
+    /// The width and height of the screen in pixels.
+    fn size(&self) -> (usize, usize);
+
+    /// Draws `pixels`, a rectangle `width` pixels wide, with its top left corner at `x` and `y`.
+    fn draw_pixels(&self, x: usize, y: usize, width: usize, pixels: &[Pixel]) -> Result<(), DeviceError>;
+
+    /// Shows what has been drawn, for displays which draw into a back buffer.
+    fn present(&self) {}
+}
+
+/// Something which happened on an [`InputDevice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+}
+
+/// A keyboard, mouse or other device which reports [`InputEvent`]s.
+pub trait InputDevice: Sync {
+    /// Returns the oldest event which has not been read yet, without waiting.
+    fn read_event(&self) -> Option<InputEvent>;
+}
+
+/// A network card, which sends and receives Ethernet frames.
+pub trait NetDevice: Sync {
+    fn mac_address(&self) -> [u8; 6];
+
+    fn send(&self, frame: &[u8]) -> Result<(), DeviceError>;
+
+    /// Moves the oldest received frame into `buffer` without waiting, and returns its length, or 0
+    /// if no frame has arrived.
+    fn receive(&self, buffer: &mut [u8]) -> Result<usize, DeviceError>;
+}
+
+/// A registered device, through the trait of its class.
+#[derive(Clone, Copy)]
+pub enum Device {
+    Char(&'static dyn CharDevice),
+    Block(&'static dyn BlockDevice),
+    Display(&'static dyn DisplayDevice),
+    Input(&'static dyn InputDevice),
+    Net(&'static dyn NetDevice),
+}
+
+impl Device {
+    pub fn class(&self) -> DeviceClass {
+        match self {
+            Device::Char(_) => DeviceClass::Char,
+            Device::Block(_) => DeviceClass::Block,
+            Device::Display(_) => DeviceClass::Display,
+            Device::Input(_) => DeviceClass::Input,
+            Device::Net(_) => DeviceClass::Net,
+        }
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}Device", self.class())
+    }
+}
+
+/// The name and class a device was registered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceId {
+    pub class: DeviceClass,
+    name: [u8; NAME_LENGTH],
+    name_length: u8,
+}
+
+impl DeviceId {
+    /// Returns the ID of the device of `class` named `name`, or `None` if the name is empty or
+    /// longer than [`NAME_LENGTH`].
+    pub fn new(class: DeviceClass, name: &str) -> Option<Self> {
+        if name.is_empty() || name.len() > NAME_LENGTH {
+            return None;
+        }
+        let mut id = DeviceId { class, name: [0; NAME_LENGTH], name_length: name.len() as u8 };
+        id.name[..name.len()].copy_from_slice(name.as_bytes());
+        Some(id)
+    }
+
+    pub fn name(&self) -> &str {
+        // The name was copied from a `str`, and is never cut.
+        core::str::from_utf8(&self.name[..self.name_length as usize]).unwrap_or("")
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?})", self.name(), self.class)
+    }
+}
+
+/// The registered devices, and the IDs they were registered with.
+#[derive(Clone, Copy)]
+struct Registry {
+    entries: [Option<(DeviceId, Device)>; MAX_DEVICES],
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Registry { entries: [None; MAX_DEVICES] }
+    }
+
+    fn register(&mut self, name: &str, device: Device) -> Result<DeviceId, DeviceError> {
+        let id = DeviceId::new(device.class(), name).ok_or(DeviceError::InvalidName)?;
+        if self.entries.iter().flatten().any(|(registered, _)| *registered == id) {
+            return Err(DeviceError::AlreadyRegistered);
+        }
+        let slot = self.entries.iter_mut().find(|slot| slot.is_none()).ok_or(DeviceError::TooManyDevices)?;
+        *slot = Some((id, device));
+        Ok(id)
+    }
+
+    fn unregister(&mut self, id: &DeviceId) -> Option<Device> {
+        let slot = self.entries.iter_mut().find(|slot| matches!(slot, Some((registered, _)) if registered == id))?;
+        slot.take().map(|(_, device)| device)
+    }
+
+    /// Returns the device named `name` of class `class`. If there is only a device of another
+    /// class with the name, [`DeviceError::WrongClass`] is returned.
+    fn lookup(&self, class: DeviceClass, name: &str) -> Result<Device, DeviceError> {
+        let mut found = None;
+        for (id, device) in self.entries.iter().flatten() {
+            if id.name() == name {
+                if id.class == class {
+                    return Ok(*device);
+                }
+                found = Some(id.class);
+            }
+        }
+        match found {
+            Some(found) => Err(DeviceError::WrongClass { expected: class, found }),
+            None => Err(DeviceError::NotFound),
+        }
+    }
+}
+
+/// The devices registered by the SubSystem and the drivers. Devices are looked up from interrupt
+/// handlers, such as to print, so it is only locked with interrupts disabled.
+static DEVICES: Mutex<Registry> = Mutex::new(Registry::new());
+
+/// Registers `device` under `name`, which must be unique among the devices of its class. Returns
+/// the ID the device is found with.
+pub fn register(name: &str, device: Device) -> Result<DeviceId, DeviceError> {
+    irq::without_interrupts(|| DEVICES.lock().register(name, device))
+}
+
+/// Removes the device with `id` and returns it, or `None` if it is not registered.
+pub fn unregister(id: &DeviceId) -> Option<Device> {
+    irq::without_interrupts(|| DEVICES.lock().unregister(id))
+}
+
+/// Returns the device of `class` named `name`.
+pub fn lookup(class: DeviceClass, name: &str) -> Result<Device, DeviceError> {
+    irq::without_interrupts(|| DEVICES.lock().lookup(class, name))
+}
+
+/// Returns the character device named `name`.
+pub fn char_device(name: &str) -> Result<&'static dyn CharDevice, DeviceError> {
+    match lookup(DeviceClass::Char, name)? {
+        Device::Char(device) => Ok(device),
+        _ => unreachable!("the device was looked up by its class"),
+    }
+}
+
+/// Returns the block device named `name`.
+pub fn block_device(name: &str) -> Result<&'static dyn BlockDevice, DeviceError> {
+    match lookup(DeviceClass::Block, name)? {
+        Device::Block(device) => Ok(device),
+        _ => unreachable!("the device was looked up by its class"),
+    }
+}
+
+/// Returns the display named `name`.
+pub fn display_device(name: &str) -> Result<&'static dyn DisplayDevice, DeviceError> {
+    match lookup(DeviceClass::Display, name)? {
+        Device::Display(device) => Ok(device),
+        _ => unreachable!("the device was looked up by its class"),
+    }
+}
+
+/// Returns the input device named `name`.
+pub fn input_device(name: &str) -> Result<&'static dyn InputDevice, DeviceError> {
+    match lookup(DeviceClass::Input, name)? {
+        Device::Input(device) => Ok(device),
+        _ => unreachable!("the device was looked up by its class"),
+    }
+}
+
+/// Returns the network card named `name`.
+pub fn net_device(name: &str) -> Result<&'static dyn NetDevice, DeviceError> {
+    match lookup(DeviceClass::Net, name)? {
+        Device::Net(device) => Ok(device),
+        _ => unreachable!("the device was looked up by its class"),
+    }
+}
+
+/// Calls `f` with every registered device, in the order they were registered in.
+///
+/// ## Example
+/// ```rust
+/// devices::for_each(|id, _device| log::info!("Found {}", id));
+/// ```
+///
+/// In this example every device is written to the kernel log, such as "Found serial0 (Char)".
+pub fn for_each(mut f: impl FnMut(&DeviceId, Device)) {
+    // The registry is copied, so `f` may look up or register devices.
+    let registry = irq::without_interrupts(|| *DEVICES.lock());
+    for (id, device) in registry.entries.iter().flatten() {
+        f(id, *device);
+    }
+}
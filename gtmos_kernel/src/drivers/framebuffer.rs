@@ -1,6 +1,4 @@
-use spin::MutexGuard;
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Pixel {
     /// blue channel
     pub b: u8,
@@ -10,6 +8,101 @@ pub struct Pixel {
     pub r: u8
 }
 
+/// Describes how a [`Pixel`] is stored in the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Red, green and blue bytes in that order, followed by padding up to the pixel size.
+    Rgb,
+    /// Blue, green and red bytes in that order, followed by padding up to the pixel size.
+    Bgr,
+    /// A single grayscale byte.
+    U8,
+    /// A little endian value with 8 bits per channel, the positions are the lowest bit of each
+    /// channel.
+    Unknown {
+        red_position: u8,
+        green_position: u8,
+        blue_position: u8,
+    },
+}
+
+impl PixelFormat {
+    /// Encodes `pixel` into its first `bytes_per_pixel` bytes (at most 4).
+    pub fn encode(self, pixel: Pixel) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgb => [pixel.r, pixel.g, pixel.b, 0],
+            PixelFormat::Bgr => [pixel.b, pixel.g, pixel.r, 0],
+            PixelFormat::U8 => [pixel.luminance(), 0, 0, 0],
+            PixelFormat::Unknown { red_position, green_position, blue_position } => {
+                let value = (pixel.r as u32) << red_position
+                    | (pixel.g as u32) << green_position
+                    | (pixel.b as u32) << blue_position;
+                value.to_le_bytes()
+            }
+        }
+    }
+
+    /// Decodes a pixel from its bytes in the framebuffer.
+    pub fn decode(self, bytes: &[u8]) -> Pixel {
+        match self {
+            PixelFormat::Rgb => Pixel { r: bytes[0], g: bytes[1], b: bytes[2] },
+            PixelFormat::Bgr => Pixel { r: bytes[2], g: bytes[1], b: bytes[0] },
+            PixelFormat::U8 => Pixel { r: bytes[0], g: bytes[0], b: bytes[0] },
+            PixelFormat::Unknown { red_position, green_position, blue_position } => {
+                let mut value = [0; 4];
+                let length = bytes.len().min(4);
+                value[..length].copy_from_slice(&bytes[..length]);
+                let value = u32::from_le_bytes(value);
+                Pixel {
+                    r: (value >> red_position) as u8,
+                    g: (value >> green_position) as u8,
+                    b: (value >> blue_position) as u8,
+                }
+            }
+        }
+    }
+}
+
+impl Pixel {
+    /// Returns the brightness of the pixel, used for grayscale framebuffers.
+    pub fn luminance(&self) -> u8 {
+        ((self.r as u32 * 77 + self.g as u32 * 150 + self.b as u32 * 29) >> 8) as u8
+    }
+}
+
+/// A colour with an alpha channel, used for drawing translucent colours over the framebuffer.
+/// An alpha of `0xFF` is opaque and `0x00` is invisible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Rgba { r, g, b, a }
+    }
+
+    /// Returns the colour without its alpha channel.
+    pub const fn pixel(self) -> Pixel {
+        Pixel { r: self.r, g: self.g, b: self.b }
+    }
+
+    /// Returns the same colour with its alpha multiplied by `coverage`, which is how much of a
+    /// pixel an anti-aliased edge covers (`0xFF` is all of it).
+    pub const fn with_coverage(self, coverage: u8) -> Self {
+        Rgba { a: ((self.a as u16 * coverage as u16 + 0xFF) >> 8) as u8, ..self }
+    }
+}
+
+impl From<Pixel> for Rgba {
+    fn from(pixel: Pixel) -> Self {
+        Rgba { r: pixel.r, g: pixel.g, b: pixel.b, a: 0xFF }
+    }
+}
+
 /// Represents a framebuffer memory region and other metadata used to control
 /// different functions of framebuffer.
 pub struct FramebufferMemory<'a> {
@@ -17,10 +110,13 @@ pub struct FramebufferMemory<'a> {
     pub buffer: &'a mut[u8],
     pub width: usize,
     pub height: usize,
+    /// The number of pixels from the start of one row to the start of the next, which can be more
+    /// than `width`.
+    pub stride: usize,
     pub bytes_per_pixel: usize,
+    pub pixel_format: PixelFormat,
 }
 
-
 pub struct FramebufferIndex {
     pub x: usize,
     pub y: usize,
@@ -38,7 +134,7 @@ impl Framebuffer {
     #[inline]
     fn index_to_offset(fb: &FramebufferMemory, index: FramebufferIndex) -> Option<usize> {
         if Framebuffer::index_in_bounds(&fb, &index) {
-            Some((index.y * fb.width + index.x) * fb.bytes_per_pixel)
+            Some((index.y * fb.stride + index.x) * fb.bytes_per_pixel)
         } else {
             None
         }
@@ -47,43 +143,133 @@ impl Framebuffer {
     #[inline]
     pub fn set_pixel(fb: &mut FramebufferMemory, pixel: Pixel, index: FramebufferIndex) {
         if let Some(offset) = Framebuffer::index_to_offset(&fb, index) {
-            if offset+2 < fb.buffer.len() {
-                fb.buffer[offset] = pixel.b;
-                fb.buffer[offset + 1] = pixel.g;
-                fb.buffer[offset + 2] = pixel.r;
+            let bytes_per_pixel = fb.bytes_per_pixel;
+            if let Some(destination) = fb.buffer.get_mut(offset..offset + bytes_per_pixel) {
+                let encoded = fb.pixel_format.encode(pixel);
+                destination.copy_from_slice(&encoded[..bytes_per_pixel.min(4)]);
             }
         }
     }
 
     #[inline]
     pub fn get_pixel(fb: &mut FramebufferMemory, index: FramebufferIndex) -> Option<Pixel> {
-        if let Some(offset) = Framebuffer::index_to_offset(&fb, index) {
-            return Some(Pixel {
-                b: fb.buffer[offset],
-                g: fb.buffer[offset + 1],
-                r: fb.buffer[offset + 2]
-            });
+        let offset = Framebuffer::index_to_offset(&fb, index)?;
+        let bytes = fb.buffer.get(offset..offset + fb.bytes_per_pixel)?;
+        Some(fb.pixel_format.decode(bytes))
+    }
+
+    pub fn fill(fb: &mut FramebufferMemory, pixel: Pixel) {
+        let (width, height) = (fb.width, fb.height);
+        Framebuffer::fill_rect(fb, 0, 0, width, height, pixel);
+    }
+
+    /// Clips a rectangle to the framebuffer, returning its new width and height.
+    ///
+    /// The rows are also clipped to the end of the buffer, in case it is shorter than the width,
+    /// height and stride say, so that the rows which are left can be sliced without checking.
+    #[inline]
+    fn clip(fb: &FramebufferMemory, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let width = width.min(fb.width.saturating_sub(x));
+        let height = height.min(fb.height.saturating_sub(y));
+        // The number of rows from y onwards which end inside the buffer.
+        let pixels = fb.buffer.len() / fb.bytes_per_pixel.max(1);
+        let rows = match pixels.checked_sub(y * fb.stride + x + width) {
+            Some(rest) => rest / fb.stride.max(1) + 1,
+            None => 0,
+        };
+        (width, height.min(rows))
+    }
+
+    /// Fills a rectangle with one colour, the part outside of the framebuffer is ignored.
+    ///
+    /// The colour is only encoded once. The first row is filled by doubling the filled part with
+    /// `memcpy` and every other row is a copy of the first, so no pixel is written on its own.
+    pub fn fill_rect(fb: &mut FramebufferMemory, x: usize, y: usize, width: usize, height: usize, pixel: Pixel) {
+        let (width, height) = Framebuffer::clip(fb, x, y, width, height);
+        let bps = fb.bytes_per_pixel;
+        if width == 0 || height == 0 || bps == 0 || bps > 4 {
+            return;
+        }
+
+        let row_length = width * bps;
+        let first = (y * fb.stride + x) * bps;
+        let encoded = fb.pixel_format.encode(pixel);
+        fb.buffer[first..first + bps].copy_from_slice(&encoded[..bps]);
+        let mut filled = bps;
+        while filled < row_length {
+            let length = filled.min(row_length - filled);
+            fb.buffer.copy_within(first..first + length, first + filled);
+            filled += length;
         }
 
-        None
+        for row in 1..height {
+            let start = ((y + row) * fb.stride + x) * bps;
+            fb.buffer.copy_within(first..first + row_length, start);
+        }
     }
 
-    pub fn fill(fb: &mut MutexGuard<FramebufferMemory>, pixel: Pixel) {
+    /// Draws `pixels`, a `width` by `height` image stored one row after another, with its top left
+    /// corner at (x, y). The part outside of the framebuffer is ignored.
+    pub fn blit_from(fb: &mut FramebufferMemory, x: usize, y: usize, width: usize, height: usize, pixels: &[Pixel]) {
+        let (visible_width, visible_height) = Framebuffer::clip(fb, x, y, width, height);
         let bps = fb.bytes_per_pixel;
-        Framebuffer::fill_region(fb.buffer, pixel, bps);
+        if visible_width == 0 || bps == 0 || bps > 4 {
+            return;
+        }
+
+        let pixel_format = fb.pixel_format;
+        for (row, source) in pixels.chunks(width).take(visible_height).enumerate() {
+            let start = ((y + row) * fb.stride + x) * bps;
+            let destination = &mut fb.buffer[start..start + visible_width * bps];
+            for (bytes, pixel) in destination.chunks_exact_mut(bps).zip(source) {
+                bytes.copy_from_slice(&pixel_format.encode(*pixel)[..bps]);
+            }
+        }
+    }
+
+    /// Moves a rectangle from (src_x, src_y) to (dest_x, dest_y) a whole row at a time. The source
+    /// and destination may overlap, such as when scrolling. Only the part which is inside the
+    /// framebuffer at both places is moved, its width and height are returned.
+    pub fn move_rect(
+        fb: &mut FramebufferMemory,
+        src_x: usize,
+        src_y: usize,
+        width: usize,
+        height: usize,
+        dest_x: usize,
+        dest_y: usize,
+    ) -> (usize, usize) {
+        let (width, height) = Framebuffer::clip(fb, src_x, src_y, width, height);
+        let (width, height) = Framebuffer::clip(fb, dest_x, dest_y, width, height);
+        let bps = fb.bytes_per_pixel;
+        if width == 0 || height == 0 {
+            return (width, height);
+        }
+
+        let row_length = width * bps;
+        let mut move_row = |row: usize| {
+            let source = ((src_y + row) * fb.stride + src_x) * bps;
+            let destination = ((dest_y + row) * fb.stride + dest_x) * bps;
+            fb.buffer.copy_within(source..source + row_length, destination);
+        };
+        // When moving down the bottom rows are moved first, so that no row is overwritten before
+        // it has been moved.
+        if dest_y > src_y {
+            (0..height).rev().for_each(&mut move_row);
+        } else {
+            (0..height).for_each(&mut move_row);
+        }
+        (width, height)
     }
 
-    pub fn fill_region(fb_region_slice: &mut [u8], pixel: Pixel, bps: usize) {
-        if fb_region_slice.len() % bps != 0 {
+    pub fn fill_region(fb_region_slice: &mut [u8], pixel: Pixel, bps: usize, pixel_format: PixelFormat) {
+        if bps == 0 || bps > 4 || fb_region_slice.len() % bps != 0 {
             return;
         }
 
-        let mut offset = 0;
-        while offset < fb_region_slice.len() {
-            fb_region_slice[offset] = pixel.b;
-            fb_region_slice[offset + 1] = pixel.g;
-            fb_region_slice[offset + 2] = pixel.r;
-            offset += bps;
+        let encoded = pixel_format.encode(pixel);
+        for destination in fb_region_slice.chunks_exact_mut(bps) {
+            destination.copy_from_slice(&encoded[..bps]);
         }
     }
 }
@@ -0,0 +1,228 @@
+//! Driver for the i8042 PS/2 controller, which connects the PS/2 keyboard (first channel) and the
+//! PS/2 mouse (second channel).
+//!
+//! ## See also:
+//! * [I8042 PS/2 Controller (OsDev.org)](https://wiki.osdev.org/I8042_PS/2_Controller)
+
+use super::port::Port;
+
+const DATA: Port = Port::new(0x60);
+const STATUS_COMMAND: Port = Port::new(0x64);
+
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+
+const CONFIG_FIRST_INTERRUPT: u8 = 1 << 0;
+const CONFIG_SECOND_INTERRUPT: u8 = 1 << 1;
+const CONFIG_FIRST_CLOCK_DISABLED: u8 = 1 << 4;
+const CONFIG_SECOND_CLOCK_DISABLED: u8 = 1 << 5;
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+const COMMAND_READ_CONFIG: u8 = 0x20;
+const COMMAND_WRITE_CONFIG: u8 = 0x60;
+const COMMAND_DISABLE_SECOND: u8 = 0xA7;
+const COMMAND_ENABLE_SECOND: u8 = 0xA8;
+const COMMAND_TEST_SECOND: u8 = 0xA9;
+const COMMAND_SELF_TEST: u8 = 0xAA;
+const COMMAND_TEST_FIRST: u8 = 0xAB;
+const COMMAND_DISABLE_FIRST: u8 = 0xAD;
+const COMMAND_ENABLE_FIRST: u8 = 0xAE;
+const COMMAND_WRITE_SECOND: u8 = 0xD4;
+
+/// Sent by a device when it accepted a command.
+pub const ACK: u8 = 0xFA;
+/// Sent by a device when a command should be sent again.
+pub const RESEND: u8 = 0xFE;
+/// Sent by a device after it has been reset successfully.
+pub const SELF_TEST_PASSED: u8 = 0xAA;
+
+/// How many times the status register is polled before giving up.
+const TIMEOUT: usize = 100_000;
+
+/// Errors returned by the PS/2 controller or its devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I8042Error {
+    /// The controller or device did not respond in time.
+    Timeout,
+    /// The controller's self test returned this value instead of `0x55`.
+    SelfTestFailed(u8),
+    /// There is no working device on the channel.
+    NoDevice,
+    /// A device answered a command with this value instead of [`ACK`].
+    UnexpectedResponse(u8),
+}
+
+/// One of the two channels of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// The first channel, normally used by the keyboard.
+    First,
+    /// The second channel, normally used by the mouse.
+    Second,
+}
+
+/// What [`initialise`] found out about the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Controller {
+    /// `true` if the first channel passed its test.
+    pub first: bool,
+    /// `true` if the controller has a second channel and it passed its test.
+    pub second: bool,
+    /// `true` if the controller translates the keyboard's scancodes into scancode set 1.
+    pub translation: bool,
+}
+
+/// Tests the controller and enables the working channels. Interrupts stay disabled until the
+/// drivers of the devices call [`set_interrupt`].
+///
+/// ## Safety
+/// This function must only be called once, before any PS/2 device driver is used.
+pub unsafe fn initialise() -> Result<Controller, I8042Error> {
+    send_command(COMMAND_DISABLE_FIRST)?;
+    send_command(COMMAND_DISABLE_SECOND)?;
+    flush();
+
+    let mut config = read_config()?;
+    let translation = config & CONFIG_TRANSLATION != 0;
+    config &= !(CONFIG_FIRST_INTERRUPT | CONFIG_SECOND_INTERRUPT);
+    write_config(config)?;
+
+    send_command(COMMAND_SELF_TEST)?;
+    match read_data()? {
+        0x55 => {}
+        result => return Err(I8042Error::SelfTestFailed(result)),
+    }
+    // The self test resets some controllers.
+    write_config(config)?;
+
+    // A controller with a second channel clears its clock bit when the channel is enabled.
+    send_command(COMMAND_ENABLE_SECOND)?;
+    let has_second = read_config()? & CONFIG_SECOND_CLOCK_DISABLED == 0;
+    send_command(COMMAND_DISABLE_SECOND)?;
+
+    send_command(COMMAND_TEST_FIRST)?;
+    let first = read_data()? == 0;
+    let second = has_second && {
+        send_command(COMMAND_TEST_SECOND)?;
+        read_data()? == 0
+    };
+    if !first && !second {
+        return Err(I8042Error::NoDevice);
+    }
+
+    if first {
+        send_command(COMMAND_ENABLE_FIRST)?;
+        config &= !CONFIG_FIRST_CLOCK_DISABLED;
+    }
+    if second {
+        send_command(COMMAND_ENABLE_SECOND)?;
+        config &= !CONFIG_SECOND_CLOCK_DISABLED;
+    }
+    write_config(config)?;
+
+    Ok(Controller { first, second, translation })
+}
+
+/// Enables or disables the interrupt (IRQ 1 or IRQ 12) of a channel.
+///
+/// ## Safety
+/// Must not be called while another PS/2 command is in progress.
+pub unsafe fn set_interrupt(channel: Channel, enabled: bool) -> Result<(), I8042Error> {
+    let bit = match channel {
+        Channel::First => CONFIG_FIRST_INTERRUPT,
+        Channel::Second => CONFIG_SECOND_INTERRUPT,
+    };
+    let config = read_config()?;
+    write_config(if enabled { config | bit } else { config & !bit })
+}
+
+/// Sends a command byte to the device on `channel` and waits for it to be acknowledged, sending
+/// the command again when the device asks for it.
+///
+/// ## Safety
+/// Interrupts of the channel must be disabled, otherwise the interrupt handler takes the response.
+pub unsafe fn send_device_command(channel: Channel, command: u8) -> Result<(), I8042Error> {
+    for _ in 0..3 {
+        write_device(channel, command)?;
+        match read_data()? {
+            ACK => return Ok(()),
+            RESEND => continue,
+            response => return Err(I8042Error::UnexpectedResponse(response)),
+        }
+    }
+    Err(I8042Error::UnexpectedResponse(RESEND))
+}
+
+/// Writes a byte to the device on `channel`.
+///
+/// ## Safety
+/// See [`send_device_command`].
+pub unsafe fn write_device(channel: Channel, value: u8) -> Result<(), I8042Error> {
+    if channel == Channel::Second {
+        send_command(COMMAND_WRITE_SECOND)?;
+    }
+    wait_for_input_empty()?;
+    DATA.write(value);
+    Ok(())
+}
+
+/// Waits for a byte from the controller or a device and returns it.
+///
+/// ## Safety
+/// See [`send_device_command`].
+pub unsafe fn read_data() -> Result<u8, I8042Error> {
+    for _ in 0..TIMEOUT {
+        if STATUS_COMMAND.read() & STATUS_OUTPUT_FULL != 0 {
+            return Ok(DATA.read());
+        }
+        core::hint::spin_loop();
+    }
+    Err(I8042Error::Timeout)
+}
+
+/// Reads the data port without waiting. This is used by interrupt handlers, which are only called
+/// when a byte has arrived.
+///
+/// ## Safety
+/// Reading the data port removes the byte from the controller.
+pub unsafe fn read_data_unchecked() -> u8 {
+    DATA.read()
+}
+
+/// Throws away any bytes waiting in the controller's output buffer.
+unsafe fn flush() {
+    for _ in 0..16 {
+        if STATUS_COMMAND.read() & STATUS_OUTPUT_FULL == 0 {
+            break;
+        }
+        DATA.read();
+    }
+}
+
+unsafe fn send_command(command: u8) -> Result<(), I8042Error> {
+    wait_for_input_empty()?;
+    STATUS_COMMAND.write(command);
+    Ok(())
+}
+
+unsafe fn read_config() -> Result<u8, I8042Error> {
+    send_command(COMMAND_READ_CONFIG)?;
+    read_data()
+}
+
+unsafe fn write_config(config: u8) -> Result<(), I8042Error> {
+    send_command(COMMAND_WRITE_CONFIG)?;
+    wait_for_input_empty()?;
+    DATA.write(config);
+    Ok(())
+}
+
+unsafe fn wait_for_input_empty() -> Result<(), I8042Error> {
+    for _ in 0..TIMEOUT {
+        if STATUS_COMMAND.read() & STATUS_INPUT_FULL == 0 {
+            return Ok(());
+        }
+        core::hint::spin_loop();
+    }
+    Err(I8042Error::Timeout)
+}
@@ -0,0 +1,171 @@
+use super::{KeyCode, Modifiers};
+
+/// A keyboard layout, which maps keys to the characters printed on them.
+///
+/// Keys which are the same on every layout (Enter, Tab, the keypad, ...) are handled by the
+/// keyboard driver, a keymap only lists the keys which type characters. Any `static` keymap can be
+/// loaded with [`set_keymap`](super::set_keymap).
+///
+/// ## Example
+/// ```rust
+/// static DVORAK: Keymap = Keymap {
+///     name: "Dvorak",
+///     keys: &[(KeyCode::Q, '\'', '"'), (KeyCode::W, ',', '<'), /* ... */],
+///     alt_gr: &[],
+/// };
+/// keyboard::set_keymap(&DVORAK);
+/// ```
+///
+/// In this example a Dvorak layout is loaded.
+#[derive(Debug)]
+pub struct Keymap {
+    pub name: &'static str,
+    /// Each key with the character it types, and the character it types with Shift.
+    pub keys: &'static [(KeyCode, char, char)],
+    /// Keys which type a different character when AltGr is held.
+    pub alt_gr: &'static [(KeyCode, char)],
+}
+
+impl Keymap {
+    /// Returns the character `key` types with `modifiers`, or `None` if it does not type one.
+    pub fn character(&self, key: KeyCode, modifiers: Modifiers) -> Option<char> {
+        if modifiers.alt_gr {
+            return self
+                .alt_gr
+                .iter()
+                .find(|(alt_gr_key, _)| *alt_gr_key == key)
+                .map(|(_, character)| *character);
+        }
+
+        let (_, normal, shifted) = self.keys.iter().find(|(map_key, _, _)| *map_key == key)?;
+        // Caps Lock only affects letters, and Shift undoes it.
+        let shift = if normal.is_alphabetic() {
+            modifiers.shift() != modifiers.caps_lock
+        } else {
+            modifiers.shift()
+        };
+        Some(if shift { *shifted } else { *normal })
+    }
+}
+
+/// The US (ANSI) layout.
+pub static US: Keymap = Keymap {
+    name: "US",
+    keys: &[
+        (KeyCode::Backtick, '`', '~'),
+        (KeyCode::Key1, '1', '!'),
+        (KeyCode::Key2, '2', '@'),
+        (KeyCode::Key3, '3', '#'),
+        (KeyCode::Key4, '4', '$'),
+        (KeyCode::Key5, '5', '%'),
+        (KeyCode::Key6, '6', '^'),
+        (KeyCode::Key7, '7', '&'),
+        (KeyCode::Key8, '8', '*'),
+        (KeyCode::Key9, '9', '('),
+        (KeyCode::Key0, '0', ')'),
+        (KeyCode::Minus, '-', '_'),
+        (KeyCode::Equals, '=', '+'),
+        (KeyCode::Q, 'q', 'Q'),
+        (KeyCode::W, 'w', 'W'),
+        (KeyCode::E, 'e', 'E'),
+        (KeyCode::R, 'r', 'R'),
+        (KeyCode::T, 't', 'T'),
+        (KeyCode::Y, 'y', 'Y'),
+        (KeyCode::U, 'u', 'U'),
+        (KeyCode::I, 'i', 'I'),
+        (KeyCode::O, 'o', 'O'),
+        (KeyCode::P, 'p', 'P'),
+        (KeyCode::LeftBracket, '[', '{'),
+        (KeyCode::RightBracket, ']', '}'),
+        (KeyCode::Backslash, '\\', '|'),
+        (KeyCode::A, 'a', 'A'),
+        (KeyCode::S, 's', 'S'),
+        (KeyCode::D, 'd', 'D'),
+        (KeyCode::F, 'f', 'F'),
+        (KeyCode::G, 'g', 'G'),
+        (KeyCode::H, 'h', 'H'),
+        (KeyCode::J, 'j', 'J'),
+        (KeyCode::K, 'k', 'K'),
+        (KeyCode::L, 'l', 'L'),
+        (KeyCode::Semicolon, ';', ':'),
+        (KeyCode::Quote, '\'', '"'),
+        (KeyCode::Z, 'z', 'Z'),
+        (KeyCode::X, 'x', 'X'),
+        (KeyCode::C, 'c', 'C'),
+        (KeyCode::V, 'v', 'V'),
+        (KeyCode::B, 'b', 'B'),
+        (KeyCode::N, 'n', 'N'),
+        (KeyCode::M, 'm', 'M'),
+        (KeyCode::Comma, ',', '<'),
+        (KeyCode::Period, '.', '>'),
+        (KeyCode::Slash, '/', '?'),
+        (KeyCode::Space, ' ', ' '),
+    ],
+    alt_gr: &[],
+};
+
+/// The UK (ISO) layout, which has an extra key next to Left Shift.
+pub static UK: Keymap = Keymap {
+    name: "UK",
+    keys: &[
+        (KeyCode::Backtick, '`', '¬'),
+        (KeyCode::Key1, '1', '!'),
+        (KeyCode::Key2, '2', '"'),
+        (KeyCode::Key3, '3', '£'),
+        (KeyCode::Key4, '4', '$'),
+        (KeyCode::Key5, '5', '%'),
+        (KeyCode::Key6, '6', '^'),
+        (KeyCode::Key7, '7', '&'),
+        (KeyCode::Key8, '8', '*'),
+        (KeyCode::Key9, '9', '('),
+        (KeyCode::Key0, '0', ')'),
+        (KeyCode::Minus, '-', '_'),
+        (KeyCode::Equals, '=', '+'),
+        (KeyCode::Q, 'q', 'Q'),
+        (KeyCode::W, 'w', 'W'),
+        (KeyCode::E, 'e', 'E'),
+        (KeyCode::R, 'r', 'R'),
+        (KeyCode::T, 't', 'T'),
+        (KeyCode::Y, 'y', 'Y'),
+        (KeyCode::U, 'u', 'U'),
+        (KeyCode::I, 'i', 'I'),
+        (KeyCode::O, 'o', 'O'),
+        (KeyCode::P, 'p', 'P'),
+        (KeyCode::LeftBracket, '[', '{'),
+        (KeyCode::RightBracket, ']', '}'),
+        (KeyCode::A, 'a', 'A'),
+        (KeyCode::S, 's', 'S'),
+        (KeyCode::D, 'd', 'D'),
+        (KeyCode::F, 'f', 'F'),
+        (KeyCode::G, 'g', 'G'),
+        (KeyCode::H, 'h', 'H'),
+        (KeyCode::J, 'j', 'J'),
+        (KeyCode::K, 'k', 'K'),
+        (KeyCode::L, 'l', 'L'),
+        (KeyCode::Semicolon, ';', ':'),
+        (KeyCode::Quote, '\'', '@'),
+        // On an ISO keyboard this key sits to the left of Enter.
+        (KeyCode::Backslash, '#', '~'),
+        (KeyCode::NonUsBackslash, '\\', '|'),
+        (KeyCode::Z, 'z', 'Z'),
+        (KeyCode::X, 'x', 'X'),
+        (KeyCode::C, 'c', 'C'),
+        (KeyCode::V, 'v', 'V'),
+        (KeyCode::B, 'b', 'B'),
+        (KeyCode::N, 'n', 'N'),
+        (KeyCode::M, 'm', 'M'),
+        (KeyCode::Comma, ',', '<'),
+        (KeyCode::Period, '.', '>'),
+        (KeyCode::Slash, '/', '?'),
+        (KeyCode::Space, ' ', ' '),
+    ],
+    alt_gr: &[
+        (KeyCode::Backtick, '¦'),
+        (KeyCode::Key4, '€'),
+        (KeyCode::A, 'á'),
+        (KeyCode::E, 'é'),
+        (KeyCode::I, 'í'),
+        (KeyCode::O, 'ó'),
+        (KeyCode::U, 'ú'),
+    ],
+};
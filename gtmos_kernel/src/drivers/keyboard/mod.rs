@@ -0,0 +1,386 @@
+//! Driver for the PS/2 keyboard.
+//!
+//! The keyboard sends scancodes to the [i8042 controller](super::i8042), which raises IRQ 1. The
+//! interrupt handler decodes them into [`KeyEvent`]s, using the loaded [`Keymap`] to find the
+//! typed character, and puts them into a lock-free queue which is read with [`read_event`].
+//!
+//! ## Example
+//! ```rust
+//! while let Some(event) = keyboard::read_event() {
+//!     if let Some(character) = event.character {
+//!         serial_print!("{}", character);
+//!     }
+//! }
+//! ```
+//!
+//! In this example every character typed since the last call is printed to serial.
+//!
+//! ## See also:
+//! * [PS/2 Keyboard (OsDev.org)](https://wiki.osdev.org/PS/2_Keyboard)
+
+pub mod keymap;
+pub mod scancode;
+
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use spin::Mutex;
+
+use super::i8042::{self, Channel, Controller, I8042Error};
+use crate::devices::{self, Device, DeviceError, InputDevice, InputEvent};
+use crate::irq::{self, IrqError};
+use crate::ring_buffer::RingBuffer;
+use keymap::Keymap;
+use scancode::{ScancodeDecoder, ScancodeSet};
+
+/// The legacy IRQ line of the PS/2 keyboard.
+pub const IRQ: u8 = 1;
+
+/// A physical key, named after what it shows on a US keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+    Backtick,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    Minus,
+    Equals,
+    Backspace,
+    Tab,
+    Q,
+    W,
+    E,
+    R,
+    T,
+    Y,
+    U,
+    I,
+    O,
+    P,
+    LeftBracket,
+    RightBracket,
+    /// The key above Enter on a US keyboard, which is left of Enter on an ISO keyboard.
+    Backslash,
+    CapsLock,
+    A,
+    S,
+    D,
+    F,
+    G,
+    H,
+    J,
+    K,
+    L,
+    Semicolon,
+    Quote,
+    Enter,
+    LeftShift,
+    /// The extra key next to Left Shift on an ISO keyboard.
+    NonUsBackslash,
+    Z,
+    X,
+    C,
+    V,
+    B,
+    N,
+    M,
+    Comma,
+    Period,
+    Slash,
+    RightShift,
+    LeftControl,
+    LeftSuper,
+    LeftAlt,
+    Space,
+    /// Right Alt, which is AltGr on most non-US layouts.
+    RightAlt,
+    RightSuper,
+    Menu,
+    RightControl,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    NumLock,
+    KeypadDivide,
+    KeypadMultiply,
+    KeypadMinus,
+    KeypadPlus,
+    KeypadEnter,
+    KeypadPeriod,
+    Keypad0,
+    Keypad1,
+    Keypad2,
+    Keypad3,
+    Keypad4,
+    Keypad5,
+    Keypad6,
+    Keypad7,
+    Keypad8,
+    Keypad9,
+}
+
+/// Whether a key went down or up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Pressed,
+    Released,
+}
+
+/// The modifier keys which are held, and the lock keys which are on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_control: bool,
+    pub right_control: bool,
+    pub left_alt: bool,
+    /// Right Alt.
+    pub alt_gr: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    const NONE: Modifiers = Modifiers {
+        left_shift: false,
+        right_shift: false,
+        left_control: false,
+        right_control: false,
+        left_alt: false,
+        alt_gr: false,
+        caps_lock: false,
+        num_lock: false,
+        scroll_lock: false,
+    };
+
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn control(&self) -> bool {
+        self.left_control || self.right_control
+    }
+
+    pub fn alt(&self) -> bool {
+        self.left_alt
+    }
+
+    /// Updates the modifiers for a key event, returns `true` if `key` is a modifier or lock key.
+    fn update(&mut self, key: KeyCode, state: KeyState) -> bool {
+        let pressed = state == KeyState::Pressed;
+        match key {
+            KeyCode::LeftShift => self.left_shift = pressed,
+            KeyCode::RightShift => self.right_shift = pressed,
+            KeyCode::LeftControl => self.left_control = pressed,
+            KeyCode::RightControl => self.right_control = pressed,
+            KeyCode::LeftAlt => self.left_alt = pressed,
+            KeyCode::RightAlt => self.alt_gr = pressed,
+            KeyCode::CapsLock if pressed => self.caps_lock = !self.caps_lock,
+            KeyCode::NumLock if pressed => self.num_lock = !self.num_lock,
+            KeyCode::ScrollLock if pressed => self.scroll_lock = !self.scroll_lock,
+            KeyCode::CapsLock | KeyCode::NumLock | KeyCode::ScrollLock => {}
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// A key press or release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub state: KeyState,
+    /// The modifiers after this event was applied.
+    pub modifiers: Modifiers,
+    /// The character typed by this event, `None` for releases and keys which do not type one.
+    /// Control characters are used for Enter (`'\n'`), Tab, Backspace, Escape and Control with a
+    /// letter (`'\u{1}'` to `'\u{1a}'`).
+    pub character: Option<char>,
+}
+
+/// Turns the bytes sent by a keyboard into [`KeyEvent`]s.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyboard {
+    decoder: ScancodeDecoder,
+    modifiers: Modifiers,
+}
+
+impl Keyboard {
+    pub const fn new(set: ScancodeSet) -> Self {
+        Keyboard {
+            decoder: ScancodeDecoder::new(set),
+            // Most firmware turns Num Lock on at boot.
+            modifiers: Modifiers { num_lock: true, ..Modifiers::NONE },
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Decodes the next byte from the keyboard, returns an event once a key has been pressed or
+    /// released.
+    pub fn process(&mut self, byte: u8, keymap: &Keymap) -> Option<KeyEvent> {
+        let (code, state) = self.decoder.decode(byte)?;
+        let is_modifier = self.modifiers.update(code, state);
+
+        let character = if state == KeyState::Released || is_modifier {
+            None
+        } else {
+            character(code, self.modifiers, keymap)
+        };
+        Some(KeyEvent { code, state, modifiers: self.modifiers, character })
+    }
+}
+
+/// Returns the character typed by pressing `code`.
+fn character(code: KeyCode, modifiers: Modifiers, keymap: &Keymap) -> Option<char> {
+    let keypad = |digit: char| modifiers.num_lock.then_some(digit);
+    let character = match code {
+        KeyCode::Enter | KeyCode::KeypadEnter => Some('\n'),
+        KeyCode::Tab => Some('\t'),
+        KeyCode::Backspace => Some('\u{8}'),
+        KeyCode::Escape => Some('\u{1b}'),
+        KeyCode::KeypadDivide => Some('/'),
+        KeyCode::KeypadMultiply => Some('*'),
+        KeyCode::KeypadMinus => Some('-'),
+        KeyCode::KeypadPlus => Some('+'),
+        KeyCode::KeypadPeriod => keypad('.'),
+        KeyCode::Keypad0 => keypad('0'),
+        KeyCode::Keypad1 => keypad('1'),
+        KeyCode::Keypad2 => keypad('2'),
+        KeyCode::Keypad3 => keypad('3'),
+        KeyCode::Keypad4 => keypad('4'),
+        KeyCode::Keypad5 => keypad('5'),
+        KeyCode::Keypad6 => keypad('6'),
+        KeyCode::Keypad7 => keypad('7'),
+        KeyCode::Keypad8 => keypad('8'),
+        KeyCode::Keypad9 => keypad('9'),
+        _ => keymap.character(code, modifiers),
+    }?;
+
+    if modifiers.control() && character.is_ascii_alphabetic() {
+        return char::from_u32(character.to_ascii_lowercase() as u32 - 'a' as u32 + 1);
+    }
+    Some(character)
+}
+
+/// Errors returned by [`initialise`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardError {
+    Controller(I8042Error),
+    Irq(IrqError),
+    Device(DeviceError),
+}
+
+static KEYBOARD: Mutex<Keyboard> = Mutex::new(Keyboard::new(ScancodeSet::Set1));
+
+static KEYMAP: AtomicPtr<Keymap> = AtomicPtr::new(&keymap::US as *const Keymap as *mut Keymap);
+
+/// The name the keyboard is registered with as an [`InputDevice`].
+pub const DEVICE_NAME: &str = "keyboard0";
+
+/// Gives the events of the keyboard to the [`devices`] manager.
+pub struct KeyboardDevice;
+
+pub static DEVICE: KeyboardDevice = KeyboardDevice;
+
+impl InputDevice for KeyboardDevice {
+    fn read_event(&self) -> Option<InputEvent> {
+        read_event().map(InputEvent::Key)
+    }
+}
+
+static EVENTS: RingBuffer<KeyEvent, 128> = RingBuffer::new();
+
+/// Resets the keyboard on the first channel of the controller, attaches to IRQ 1 and registers
+/// the keyboard as [`DEVICE_NAME`].
+///
+/// If the controller translates scancodes the keyboard is decoded as scancode set 1, otherwise it
+/// is switched to scancode set 2.
+///
+/// ## Safety
+/// This function must only be called once, after [`i8042::initialise`].
+pub unsafe fn initialise(controller: &Controller) -> Result<(), KeyboardError> {
+    if !controller.first {
+        return Err(KeyboardError::Controller(I8042Error::NoDevice));
+    }
+
+    i8042::send_device_command(Channel::First, 0xFF).map_err(KeyboardError::Controller)?;
+    match i8042::read_data().map_err(KeyboardError::Controller)? {
+        i8042::SELF_TEST_PASSED => {}
+        response => return Err(KeyboardError::Controller(I8042Error::UnexpectedResponse(response))),
+    }
+
+    let set = if controller.translation {
+        ScancodeSet::Set1
+    } else {
+        i8042::send_device_command(Channel::First, 0xF0).map_err(KeyboardError::Controller)?;
+        i8042::send_device_command(Channel::First, 0x02).map_err(KeyboardError::Controller)?;
+        ScancodeSet::Set2
+    };
+    // Enable scanning.
+    i8042::send_device_command(Channel::First, 0xF4).map_err(KeyboardError::Controller)?;
+
+    *KEYBOARD.lock() = Keyboard::new(set);
+    irq::register_irq(IRQ, interrupt).map_err(KeyboardError::Irq)?;
+    i8042::set_interrupt(Channel::First, true).map_err(KeyboardError::Controller)?;
+    devices::register(DEVICE_NAME, Device::Input(&DEVICE)).map_err(KeyboardError::Device)?;
+    Ok(())
+}
+
+/// Loads a keyboard layout, it is used for every key pressed afterwards.
+pub fn set_keymap(keymap: &'static Keymap) {
+    KEYMAP.store(keymap as *const Keymap as *mut Keymap, Ordering::Release);
+}
+
+/// Returns the loaded keyboard layout.
+pub fn keymap() -> &'static Keymap {
+    // SAFETY: KEYMAP only ever holds pointers to `'static` keymaps.
+    unsafe { &*KEYMAP.load(Ordering::Acquire) }
+}
+
+/// Returns the oldest key event which has not been read yet.
+///
+/// Only one reader should take events at a time, such as the active console.
+pub fn read_event() -> Option<KeyEvent> {
+    EVENTS.pop()
+}
+
+fn interrupt(_vector: u8) {
+    let byte = unsafe { i8042::read_data_unchecked() };
+    if let Some(event) = KEYBOARD.lock().process(byte, keymap()) {
+        // Events are dropped while the queue is full.
+        let _ = EVENTS.push(event);
+    }
+}
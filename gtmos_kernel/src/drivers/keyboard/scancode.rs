@@ -0,0 +1,324 @@
+use super::{KeyCode, KeyState};
+
+/// The scancode set a keyboard sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScancodeSet {
+    /// Scancode set 1, which is what the controller sends when it translates scancodes.
+    Set1,
+    /// Scancode set 2, the default set of every PS/2 keyboard.
+    Set2,
+}
+
+/// Turns the bytes sent by a keyboard into key presses and releases.
+///
+/// Most keys send one byte, but extended keys are prefixed with `0xE0`, releases in set 2 are
+/// prefixed with `0xF0` and Pause sends a sequence starting with `0xE1`, so the decoder keeps the
+/// prefixes it has seen so far.
+#[derive(Debug, Clone, Copy)]
+pub struct ScancodeDecoder {
+    set: ScancodeSet,
+    extended: bool,
+    release: bool,
+    /// The number of bytes of the Pause sequence which still have to be skipped.
+    pause_remaining: u8,
+}
+
+impl ScancodeDecoder {
+    pub const fn new(set: ScancodeSet) -> Self {
+        ScancodeDecoder { set, extended: false, release: false, pause_remaining: 0 }
+    }
+
+    pub fn set(&self) -> ScancodeSet {
+        self.set
+    }
+
+    /// Decodes the next byte from the keyboard. Returns `None` while a sequence is incomplete, and
+    /// for bytes which are not keys (such as acknowledgements).
+    pub fn decode(&mut self, byte: u8) -> Option<(KeyCode, KeyState)> {
+        if self.pause_remaining > 0 {
+            self.pause_remaining -= 1;
+            return None;
+        }
+
+        match (self.set, byte) {
+            // Acknowledgements, errors and echoes from the keyboard.
+            (_, 0x00 | 0xEE | 0xFA | 0xFE | 0xFF) => return None,
+            // In set 2, 0xAA is the self test result, in set 1 it is the release of Left Shift.
+            (ScancodeSet::Set2, 0xAA) => return None,
+            (_, 0xE0) => {
+                self.extended = true;
+                return None;
+            }
+            (_, 0xE1) => {
+                // Pause has no release, its whole sequence is sent when it is pressed.
+                self.pause_remaining = match self.set {
+                    ScancodeSet::Set1 => 5,
+                    ScancodeSet::Set2 => 7,
+                };
+                return Some((KeyCode::Pause, KeyState::Pressed));
+            }
+            (ScancodeSet::Set2, 0xF0) => {
+                self.release = true;
+                return None;
+            }
+            _ => {}
+        }
+
+        let extended = core::mem::take(&mut self.extended);
+        let (code, state) = match self.set {
+            ScancodeSet::Set1 => {
+                let state = if byte & 0x80 != 0 { KeyState::Released } else { KeyState::Pressed };
+                (set_1(byte & 0x7F, extended)?, state)
+            }
+            ScancodeSet::Set2 => {
+                let state = if core::mem::take(&mut self.release) {
+                    KeyState::Released
+                } else {
+                    KeyState::Pressed
+                };
+                (set_2(byte, extended)?, state)
+            }
+        };
+        Some((code, state))
+    }
+}
+
+/// Returns the key with the scancode `code` in set 1, `extended` is set for codes after `0xE0`.
+fn set_1(code: u8, extended: bool) -> Option<KeyCode> {
+    use KeyCode::*;
+
+    if extended {
+        return Some(match code {
+            0x1C => KeypadEnter,
+            0x1D => RightControl,
+            0x35 => KeypadDivide,
+            0x37 => PrintScreen,
+            0x38 => RightAlt,
+            0x47 => Home,
+            0x48 => ArrowUp,
+            0x49 => PageUp,
+            0x4B => ArrowLeft,
+            0x4D => ArrowRight,
+            0x4F => End,
+            0x50 => ArrowDown,
+            0x51 => PageDown,
+            0x52 => Insert,
+            0x53 => Delete,
+            0x5B => LeftSuper,
+            0x5C => RightSuper,
+            0x5D => Menu,
+            // Includes the fake shifts (0x2A and 0x36) sent around some extended keys.
+            _ => return None,
+        });
+    }
+
+    Some(match code {
+        0x01 => Escape,
+        0x02 => Key1,
+        0x03 => Key2,
+        0x04 => Key3,
+        0x05 => Key4,
+        0x06 => Key5,
+        0x07 => Key6,
+        0x08 => Key7,
+        0x09 => Key8,
+        0x0A => Key9,
+        0x0B => Key0,
+        0x0C => Minus,
+        0x0D => Equals,
+        0x0E => Backspace,
+        0x0F => Tab,
+        0x10 => Q,
+        0x11 => W,
+        0x12 => E,
+        0x13 => R,
+        0x14 => T,
+        0x15 => Y,
+        0x16 => U,
+        0x17 => I,
+        0x18 => O,
+        0x19 => P,
+        0x1A => LeftBracket,
+        0x1B => RightBracket,
+        0x1C => Enter,
+        0x1D => LeftControl,
+        0x1E => A,
+        0x1F => S,
+        0x20 => D,
+        0x21 => F,
+        0x22 => G,
+        0x23 => H,
+        0x24 => J,
+        0x25 => K,
+        0x26 => L,
+        0x27 => Semicolon,
+        0x28 => Quote,
+        0x29 => Backtick,
+        0x2A => LeftShift,
+        0x2B => Backslash,
+        0x2C => Z,
+        0x2D => X,
+        0x2E => C,
+        0x2F => V,
+        0x30 => B,
+        0x31 => N,
+        0x32 => M,
+        0x33 => Comma,
+        0x34 => Period,
+        0x35 => Slash,
+        0x36 => RightShift,
+        0x37 => KeypadMultiply,
+        0x38 => LeftAlt,
+        0x39 => Space,
+        0x3A => CapsLock,
+        0x3B => F1,
+        0x3C => F2,
+        0x3D => F3,
+        0x3E => F4,
+        0x3F => F5,
+        0x40 => F6,
+        0x41 => F7,
+        0x42 => F8,
+        0x43 => F9,
+        0x44 => F10,
+        0x45 => NumLock,
+        0x46 => ScrollLock,
+        0x47 => Keypad7,
+        0x48 => Keypad8,
+        0x49 => Keypad9,
+        0x4A => KeypadMinus,
+        0x4B => Keypad4,
+        0x4C => Keypad5,
+        0x4D => Keypad6,
+        0x4E => KeypadPlus,
+        0x4F => Keypad1,
+        0x50 => Keypad2,
+        0x51 => Keypad3,
+        0x52 => Keypad0,
+        0x53 => KeypadPeriod,
+        0x56 => NonUsBackslash,
+        0x57 => F11,
+        0x58 => F12,
+        _ => return None,
+    })
+}
+
+/// Returns the key with the scancode `code` in set 2, `extended` is set for codes after `0xE0`.
+fn set_2(code: u8, extended: bool) -> Option<KeyCode> {
+    use KeyCode::*;
+
+    if extended {
+        return Some(match code {
+            0x11 => RightAlt,
+            0x14 => RightControl,
+            0x1F => LeftSuper,
+            0x27 => RightSuper,
+            0x2F => Menu,
+            0x4A => KeypadDivide,
+            0x5A => KeypadEnter,
+            0x69 => End,
+            0x6B => ArrowLeft,
+            0x6C => Home,
+            0x70 => Insert,
+            0x71 => Delete,
+            0x72 => ArrowDown,
+            0x74 => ArrowRight,
+            0x75 => ArrowUp,
+            0x7A => PageDown,
+            0x7C => PrintScreen,
+            0x7D => PageUp,
+            // Includes the fake shifts (0x12 and 0x59) sent around some extended keys.
+            _ => return None,
+        });
+    }
+
+    Some(match code {
+        0x01 => F9,
+        0x03 => F5,
+        0x04 => F3,
+        0x05 => F1,
+        0x06 => F2,
+        0x07 => F12,
+        0x09 => F10,
+        0x0A => F8,
+        0x0B => F6,
+        0x0C => F4,
+        0x0D => Tab,
+        0x0E => Backtick,
+        0x11 => LeftAlt,
+        0x12 => LeftShift,
+        0x14 => LeftControl,
+        0x15 => Q,
+        0x16 => Key1,
+        0x1A => Z,
+        0x1B => S,
+        0x1C => A,
+        0x1D => W,
+        0x1E => Key2,
+        0x21 => C,
+        0x22 => X,
+        0x23 => D,
+        0x24 => E,
+        0x25 => Key4,
+        0x26 => Key3,
+        0x29 => Space,
+        0x2A => V,
+        0x2B => F,
+        0x2C => T,
+        0x2D => R,
+        0x2E => Key5,
+        0x31 => N,
+        0x32 => B,
+        0x33 => H,
+        0x34 => G,
+        0x35 => Y,
+        0x36 => Key6,
+        0x3A => M,
+        0x3B => J,
+        0x3C => U,
+        0x3D => Key7,
+        0x3E => Key8,
+        0x41 => Comma,
+        0x42 => K,
+        0x43 => I,
+        0x44 => O,
+        0x45 => Key0,
+        0x46 => Key9,
+        0x49 => Period,
+        0x4A => Slash,
+        0x4B => L,
+        0x4C => Semicolon,
+        0x4D => P,
+        0x4E => Minus,
+        0x52 => Quote,
+        0x54 => LeftBracket,
+        0x55 => Equals,
+        0x58 => CapsLock,
+        0x59 => RightShift,
+        0x5A => Enter,
+        0x5B => RightBracket,
+        0x5D => Backslash,
+        0x61 => NonUsBackslash,
+        0x66 => Backspace,
+        0x69 => Keypad1,
+        0x6B => Keypad4,
+        0x6C => Keypad7,
+        0x70 => Keypad0,
+        0x71 => KeypadPeriod,
+        0x72 => Keypad2,
+        0x73 => Keypad5,
+        0x74 => Keypad6,
+        0x75 => Keypad8,
+        0x76 => Escape,
+        0x77 => NumLock,
+        0x78 => F11,
+        0x79 => KeypadPlus,
+        0x7A => Keypad3,
+        0x7B => KeypadMinus,
+        0x7C => KeypadMultiply,
+        0x7D => Keypad9,
+        0x7E => ScrollLock,
+        0x83 => F7,
+        _ => return None,
+    })
+}
@@ -11,3 +11,7 @@
 
 pub mod serial;
 pub mod framebuffer;
+pub mod i8042;
+pub mod keyboard;
+pub mod mouse;
+pub mod port;
@@ -0,0 +1,256 @@
+//! Driver for the PS/2 mouse.
+//!
+//! The mouse is connected to the second channel of the [i8042 controller](super::i8042) and raises
+//! IRQ 12. It sends a packet of 3 bytes for every movement, or 4 bytes if it supports the
+//! IntelliMouse extension which adds a scroll wheel. The interrupt handler decodes the packets,
+//! moves the pointer (which is kept inside the screen) and puts a [`MouseEvent`] into a lock-free
+//! queue which is read with [`read_event`].
+//!
+//! ## See also:
+//! * [PS/2 Mouse (OsDev.org)](https://wiki.osdev.org/PS/2_Mouse)
+//! * [Mouse Input (OsDev.org)](https://wiki.osdev.org/Mouse_Input)
+
+use spin::Mutex;
+
+use super::i8042::{self, Channel, Controller, I8042Error};
+use crate::devices::{self, Device, DeviceError, InputDevice, InputEvent};
+use crate::irq::{self, IrqError};
+use crate::ring_buffer::RingBuffer;
+
+/// The legacy IRQ line of the PS/2 mouse.
+pub const IRQ: u8 = 12;
+
+const COMMAND_GET_ID: u8 = 0xF2;
+const COMMAND_SET_SAMPLE_RATE: u8 = 0xF3;
+const COMMAND_ENABLE_STREAMING: u8 = 0xF4;
+const COMMAND_SET_DEFAULTS: u8 = 0xF6;
+
+/// The ID of a mouse which sends 4 byte packets with a scroll wheel.
+const INTELLIMOUSE_ID: u8 = 3;
+
+/// The flags in the first byte of every packet.
+pub const PACKET_LEFT: u8 = 1 << 0;
+pub const PACKET_RIGHT: u8 = 1 << 1;
+pub const PACKET_MIDDLE: u8 = 1 << 2;
+/// Always set in the first byte of a packet, this is used to find the start of a packet again.
+pub const PACKET_ALWAYS_ONE: u8 = 1 << 3;
+pub const PACKET_X_SIGN: u8 = 1 << 4;
+pub const PACKET_Y_SIGN: u8 = 1 << 5;
+pub const PACKET_X_OVERFLOW: u8 = 1 << 6;
+pub const PACKET_Y_OVERFLOW: u8 = 1 << 7;
+
+/// The buttons which are held down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseButtons {
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+}
+
+/// One packet from the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MousePacket {
+    /// The horizontal movement, positive is to the right.
+    pub dx: i16,
+    /// The vertical movement, positive is up.
+    pub dy: i16,
+    /// The scroll wheel movement, positive is towards the user. Always 0 without IntelliMouse.
+    pub dz: i8,
+    pub buttons: MouseButtons,
+}
+
+/// Collects the bytes sent by the mouse into [`MousePacket`]s.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketDecoder {
+    bytes: [u8; 4],
+    received: usize,
+    packet_size: usize,
+}
+
+impl PacketDecoder {
+    /// Creates a decoder for 3 byte packets, or 4 byte packets if `intellimouse` is set.
+    pub const fn new(intellimouse: bool) -> Self {
+        PacketDecoder { bytes: [0; 4], received: 0, packet_size: if intellimouse { 4 } else { 3 } }
+    }
+
+    /// Adds the next byte from the mouse, returns a packet once it is complete.
+    pub fn decode(&mut self, byte: u8) -> Option<MousePacket> {
+        // A first byte without this bit means a byte was lost, wait for the next packet.
+        if self.received == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+            return None;
+        }
+
+        self.bytes[self.received] = byte;
+        self.received += 1;
+        if self.received < self.packet_size {
+            return None;
+        }
+        self.received = 0;
+
+        let flags = self.bytes[0];
+        // The movement is a 9 bit two's complement number, the sign bit is in the first byte.
+        let movement = |value: u8, sign: u8, overflow: u8| -> i16 {
+            if flags & overflow != 0 {
+                0
+            } else if flags & sign != 0 {
+                value as i16 - 0x100
+            } else {
+                value as i16
+            }
+        };
+        let dz = if self.packet_size == 4 {
+            // The wheel movement is a 4 bit two's complement number.
+            ((self.bytes[3] << 4) as i8) >> 4
+        } else {
+            0
+        };
+
+        Some(MousePacket {
+            dx: movement(self.bytes[1], PACKET_X_SIGN, PACKET_X_OVERFLOW),
+            dy: movement(self.bytes[2], PACKET_Y_SIGN, PACKET_Y_OVERFLOW),
+            dz,
+            buttons: MouseButtons {
+                left: flags & PACKET_LEFT != 0,
+                right: flags & PACKET_RIGHT != 0,
+                middle: flags & PACKET_MIDDLE != 0,
+            },
+        })
+    }
+}
+
+/// The pointer after a packet was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// The position of the pointer in pixels, from the top left corner of the screen.
+    pub x: usize,
+    pub y: usize,
+    /// The movement of the packet, in screen directions (positive `dy` is down).
+    pub dx: i16,
+    pub dy: i16,
+    pub dz: i8,
+    pub buttons: MouseButtons,
+}
+
+/// Tracks the position of the pointer and keeps it inside the screen.
+#[derive(Debug, Clone, Copy)]
+pub struct Mouse {
+    decoder: PacketDecoder,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Mouse {
+    /// Creates a pointer in the middle of a screen of `width` by `height` pixels.
+    pub const fn new(intellimouse: bool, width: usize, height: usize) -> Self {
+        Mouse { decoder: PacketDecoder::new(intellimouse), x: width / 2, y: height / 2, width, height }
+    }
+
+    /// Changes the size of the screen, moving the pointer inside it if needed.
+    pub fn set_bounds(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.x = self.x.min(width.saturating_sub(1));
+        self.y = self.y.min(height.saturating_sub(1));
+    }
+
+    /// Adds the next byte from the mouse, returns an event once a packet is complete.
+    pub fn process(&mut self, byte: u8) -> Option<MouseEvent> {
+        let packet = self.decoder.decode(byte)?;
+        // The mouse counts up as positive, the screen counts down as positive.
+        let (dx, dy) = (packet.dx, -packet.dy);
+        self.x = clamp(self.x, dx, self.width);
+        self.y = clamp(self.y, dy, self.height);
+
+        Some(MouseEvent { x: self.x, y: self.y, dx, dy, dz: packet.dz, buttons: packet.buttons })
+    }
+}
+
+/// Moves `position` by `delta`, keeping it in `0..limit`.
+fn clamp(position: usize, delta: i16, limit: usize) -> usize {
+    let moved = position as isize + delta as isize;
+    moved.clamp(0, limit.saturating_sub(1) as isize) as usize
+}
+
+/// Errors returned by [`initialise`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseError {
+    Controller(I8042Error),
+    Irq(IrqError),
+    Device(DeviceError),
+}
+
+static MOUSE: Mutex<Mouse> = Mutex::new(Mouse::new(false, 0, 0));
+
+/// The name the mouse is registered with as an [`InputDevice`].
+pub const DEVICE_NAME: &str = "mouse0";
+
+/// Gives the events of the mouse to the [`devices`] manager.
+pub struct MouseDevice;
+
+pub static DEVICE: MouseDevice = MouseDevice;
+
+impl InputDevice for MouseDevice {
+    fn read_event(&self) -> Option<InputEvent> {
+        read_event().map(InputEvent::Mouse)
+    }
+}
+
+static EVENTS: RingBuffer<MouseEvent, 128> = RingBuffer::new();
+
+/// Sets up the mouse on the second channel of the controller, attaches to IRQ 12 and registers the
+/// mouse as [`DEVICE_NAME`]. The pointer is kept inside a screen of `width` by `height` pixels,
+/// which usually come from [`GraphicsAPI::get_width`](crate::graphics::GraphicsAPI::get_width) and
+/// [`GraphicsAPI::get_height`](crate::graphics::GraphicsAPI::get_height).
+///
+/// Returns `true` if the mouse has a scroll wheel.
+///
+/// ## Safety
+/// This function must only be called once, after [`i8042::initialise`].
+pub unsafe fn initialise(controller: &Controller, width: usize, height: usize) -> Result<bool, MouseError> {
+    if !controller.second {
+        return Err(MouseError::Controller(I8042Error::NoDevice));
+    }
+
+    let command = |command: u8| i8042::send_device_command(Channel::Second, command);
+    command(COMMAND_SET_DEFAULTS).map_err(MouseError::Controller)?;
+
+    // Setting the sample rate to 200, 100 and then 80 is the knock which switches an IntelliMouse
+    // into 4 byte packets, after that its ID changes from 0 to 3.
+    for rate in [200, 100, 80] {
+        command(COMMAND_SET_SAMPLE_RATE).map_err(MouseError::Controller)?;
+        command(rate).map_err(MouseError::Controller)?;
+    }
+    command(COMMAND_GET_ID).map_err(MouseError::Controller)?;
+    let intellimouse = i8042::read_data().map_err(MouseError::Controller)? == INTELLIMOUSE_ID;
+
+    command(COMMAND_ENABLE_STREAMING).map_err(MouseError::Controller)?;
+
+    *MOUSE.lock() = Mouse::new(intellimouse, width, height);
+    irq::register_irq(IRQ, interrupt).map_err(MouseError::Irq)?;
+    i8042::set_interrupt(Channel::Second, true).map_err(MouseError::Controller)?;
+    devices::register(DEVICE_NAME, Device::Input(&DEVICE)).map_err(MouseError::Device)?;
+    Ok(intellimouse)
+}
+
+/// Changes the size of the screen the pointer is kept inside of.
+pub fn set_bounds(width: usize, height: usize) {
+    // The interrupt handler locks the mouse as well, so it must not run while it is held here.
+    irq::without_interrupts(|| MOUSE.lock().set_bounds(width, height));
+}
+
+/// Returns the oldest mouse event which has not been read yet.
+///
+/// Only one reader should take events at a time.
+pub fn read_event() -> Option<MouseEvent> {
+    EVENTS.pop()
+}
+
+fn interrupt(_vector: u8) {
+    let byte = unsafe { i8042::read_data_unchecked() };
+    if let Some(event) = MOUSE.lock().process(byte) {
+        // Events are dropped while the queue is full.
+        let _ = EVENTS.push(event);
+    }
+}
@@ -0,0 +1,65 @@
+use spin::Once;
+
+/// Reads and writes I/O ports. Every SubSystem with I/O ports implements this, so drivers in the
+/// kernel crate can use [`Port`] without knowing how the architecture accesses them.
+pub trait PortIo: Sync {
+    /// Reads a byte from `port`.
+    ///
+    /// ## Safety
+    /// Reading from a port can have side effects on the hardware.
+    unsafe fn read_u8(&self, port: u16) -> u8;
+
+    /// Writes a byte to `port`.
+    ///
+    /// ## Safety
+    /// Writing to a port can have side effects on the hardware.
+    unsafe fn write_u8(&self, port: u16, value: u8);
+}
+
+static PORT_IO: Once<&'static dyn PortIo> = Once::new();
+
+/// Sets the SubSystem's port I/O implementation, only the first call has an effect.
+pub fn set_port_io(port_io: &'static dyn PortIo) {
+    PORT_IO.call_once(|| port_io);
+}
+
+/// An I/O port.
+///
+/// ## Example
+/// ```rust
+/// let status = unsafe { Port::new(0x64).read() };
+/// ```
+///
+/// In this example the status register of the PS/2 controller is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Port {
+    port: u16,
+}
+
+impl Port {
+    pub const fn new(port: u16) -> Self {
+        Port { port }
+    }
+
+    /// Reads a byte from the port. Returns `0xFF`, which is what reading a port without a device
+    /// gives, if the SubSystem has no port I/O.
+    ///
+    /// ## Safety
+    /// Reading from a port can have side effects on the hardware.
+    pub unsafe fn read(&self) -> u8 {
+        match PORT_IO.get() {
+            Some(port_io) => port_io.read_u8(self.port),
+            None => 0xFF,
+        }
+    }
+
+    /// Writes a byte to the port. Does nothing if the SubSystem has no port I/O.
+    ///
+    /// ## Safety
+    /// Writing to a port can have side effects on the hardware.
+    pub unsafe fn write(&self, value: u8) {
+        if let Some(port_io) = PORT_IO.get() {
+            port_io.write_u8(self.port, value);
+        }
+    }
+}
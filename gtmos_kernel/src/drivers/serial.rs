@@ -1,6 +1,11 @@
 use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use crate::platform::get_sub_system;
+use crate::devices;
+
+/// The name of the character device which serial is written to and read from. It is registered by
+/// the SubSystem.
+pub const DEVICE_NAME: &str = "serial0";
 
 #[doc(hidden)]
 pub fn _print(args: ::core::fmt::Arguments) {
@@ -11,17 +16,79 @@ pub fn _print(args: ::core::fmt::Arguments) {
 struct Writer {}
 impl fmt::Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        if let Some(cpu) = get_sub_system() {
-            cpu.write("serial", s);
-        }
+        let device = devices::char_device(DEVICE_NAME).map_err(|_| fmt::Error)?;
+        device.write(s.as_bytes()).map_err(|_| fmt::Error)?;
         Ok(())
     }
 }
 
-// pub fn receive() -> char  {
-//     let char1 = SERIAL1.lock().receive() as char;
-//     return char1;
-// }
+/// Set when the last byte of a line was `\r`, so the `\n` of a `\r\n` pair is skipped.
+static LAST_WAS_CARRIAGE_RETURN: AtomicBool = AtomicBool::new(false);
+
+/// Moves the bytes which have been received on serial into `buffer` without waiting, and returns
+/// how many were read.
+pub fn try_read(buffer: &mut [u8]) -> usize {
+    devices::char_device(DEVICE_NAME)
+        .and_then(|device| device.read(buffer))
+        .unwrap_or(0)
+}
+
+/// Waits until at least one byte has been received on serial, then moves the received bytes into
+/// `buffer` and returns how many were read.
+pub fn read(buffer: &mut [u8]) -> usize {
+    if buffer.is_empty() {
+        return 0;
+    }
+    loop {
+        let length = try_read(buffer);
+        if length > 0 {
+            return length;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Waits for a line on serial and returns its length. The line ends with `\r`, `\n` or `\r\n`,
+/// which is not put into `buffer`. Backspace removes the last byte, and bytes which do not fit
+/// into `buffer` are dropped.
+///
+/// ## Example
+/// ```rust
+/// let mut line = [0; 80];
+/// let length = serial::read_line(&mut line);
+/// serial_println!("You typed {:?}", core::str::from_utf8(&line[..length]));
+/// ```
+///
+/// In this example a line typed into QEMU's `-serial stdio` is read and printed back.
+pub fn read_line(buffer: &mut [u8]) -> usize {
+    let mut length = 0;
+    loop {
+        let mut byte = [0];
+        read(&mut byte);
+        if byte[0] == b'\n' && LAST_WAS_CARRIAGE_RETURN.swap(false, Ordering::Relaxed) {
+            continue;
+        }
+        if edit_line(buffer, &mut length, byte[0]) {
+            LAST_WAS_CARRIAGE_RETURN.store(byte[0] == b'\r', Ordering::Relaxed);
+            return length;
+        }
+    }
+}
+
+/// Applies a received byte to the line in `buffer`, returns `true` once the line is complete.
+fn edit_line(buffer: &mut [u8], length: &mut usize, byte: u8) -> bool {
+    match byte {
+        b'\r' | b'\n' => return true,
+        // Backspace and delete.
+        0x08 | 0x7F => *length = length.saturating_sub(1),
+        _ if *length < buffer.len() => {
+            buffer[*length] = byte;
+            *length += 1;
+        }
+        _ => {}
+    }
+    false
+}
 
 #[macro_export]
 /// Prints a message to Serial. Use this exactly like the `print` macro from the Rust standard library.
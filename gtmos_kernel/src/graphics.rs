@@ -1,13 +1,99 @@
+pub mod back_buffer;
+pub mod blend;
+pub mod cursor;
+pub mod font;
+pub mod image;
+pub mod shapes;
+pub mod surface;
+
+use alloc::vec::Vec;
 use core::cell::RefCell;
 
-use crate::drivers::framebuffer::{Framebuffer, FramebufferIndex, Pixel, FramebufferMemory};
-use font8x8::{BASIC_FONTS, UnicodeFonts};
+use crate::drivers::framebuffer::{Framebuffer, FramebufferIndex, Pixel, FramebufferMemory, Rgba};
+use back_buffer::BackBuffer;
+use blend::BlendMode;
+use cursor::Cursor;
+use surface::Surface;
+use font::Font;
+use image::Bitmap;
+
+/// A rectangle on the screen, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const EMPTY: Rect = Rect { x: 0, y: 0, width: 0, height: 0 };
+
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the part of the rectangle which is also inside `other`.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= x || bottom <= y {
+            return Rect::EMPTY;
+        }
+        Rect { x, y, width: right - x, height: bottom - y }
+    }
+
+    /// Returns the smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect { x, y, width: right - x, height: bottom - y }
+    }
+
+    /// Returns `true` if the point (x, y) is inside the rectangle.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Returns `true` if the rectangles overlap or are next to each other, including diagonally.
+    pub fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
 
 /// A simple graphics API for plotting pixels and drawing rectangles on the framebuffer.
 /// In the future this will become a proper driver.
-
+///
+/// Once the kernel heap exists the API can draw into a [back buffer](GraphicsAPI::enable_back_buffer)
+/// instead, nothing then reaches the screen until [`present`](GraphicsAPI::present) is called.
+///
+/// Every drawing function uses the coordinates of the current [`Surface`], which is the whole
+/// screen unless it is changed with [`with_surface`](GraphicsAPI::with_surface).
 pub struct GraphicsAPI<'a> {
     framebuffer: RefCell<FramebufferMemory<'a>>,
+    back_buffer: Option<BackBuffer>,
+    surface: Surface,
+    cursor: Cursor,
+    /// The pixels of the last character drawn by [`draw_char`](GraphicsAPI::draw_char), kept so
+    /// the memory is only allocated once.
+    glyph_pixels: Vec<Pixel>,
 }
 
 impl<'a> GraphicsAPI<'a> {
@@ -17,14 +103,16 @@ impl<'a> GraphicsAPI<'a> {
     /// ```rust
     /// fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
     ///     use core::cell::RefCell;
-    ///     use gtmos_kernel::drivers::framebuffer::Pixel;
+    ///     use gtmos_kernel::drivers::framebuffer::{Pixel, PixelFormat};
     ///     if let Some(framebuffer) = boot_info.framebuffer.as_mut() {
     ///         let width = {framebuffer.info().width};
     ///         let height = {framebuffer.info().height};
     ///         let fb_mem = RefCell::new(gtmos_kernel::drivers::framebuffer::FramebufferMemory {
     ///             width: width,
     ///             height: height,
+    ///             stride: framebuffer.info().stride,
     ///             bytes_per_pixel: framebuffer.info().bytes_per_pixel,
+    ///             pixel_format: PixelFormat::Bgr,
     ///             buffer: framebuffer.buffer_mut(),
     ///         });
     ///         let graphics_api: &mut gtmos_kernel::graphics::GraphicsAPI = &mut gtmos_kernel::graphics::GraphicsAPI::new(fb_mem);
@@ -34,8 +122,143 @@ impl<'a> GraphicsAPI<'a> {
     ///
     /// In this example, a hypotetical `kernel_main` is creating an instance of the grahpics API using the frambuffer from the bootloader.
     pub fn new(framebuffer: RefCell<FramebufferMemory<'a>>) -> Self {
+        let surface = {
+            let fb = framebuffer.borrow();
+            Surface::screen(fb.width, fb.height)
+        };
         GraphicsAPI {
             framebuffer: framebuffer,
+            back_buffer: None,
+            surface,
+            cursor: Cursor::new(),
+            glyph_pixels: Vec::new(),
+        }
+    }
+
+    /// Returns the surface which is drawn on.
+    pub fn surface(&self) -> Surface {
+        self.surface
+    }
+
+    /// Runs `draw` with a surface for `rect`, which is relative to the current surface, and then
+    /// goes back to the current surface. See [`Surface`] for an example.
+    pub fn with_surface<R>(&mut self, rect: Rect, draw: impl FnOnce(&mut Self) -> R) -> R {
+        let parent = self.surface;
+        self.surface = parent.child(rect);
+        let result = draw(self);
+        self.surface = parent;
+        result
+    }
+
+    /// Starts drawing into a back buffer on the kernel heap, which holds a copy of the screen.
+    /// Only the parts which changed are copied to the screen by [`present`](GraphicsAPI::present),
+    /// so large updates such as scrolling do not flicker.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.enable_back_buffer();
+    /// graphics_api.draw_filled_rectangle(0, 0, 100, 100, Pixel { b: 0x80, g: 0x80, r: 0x00 });
+    /// graphics_api.present();
+    /// ```
+    ///
+    /// In this example the rectangle only appears on the screen once `present` is called.
+    ///
+    /// The heap must be initialised before this is called.
+    pub fn enable_back_buffer(&mut self) {
+        if self.back_buffer.is_none() {
+            // The cursor is taken off the screen so it is not copied, and is drawn again on top.
+            let mut screen = self.framebuffer.borrow_mut();
+            self.cursor.restore(&mut screen);
+            self.back_buffer = Some(BackBuffer::new(&screen));
+            self.cursor.draw(&mut screen);
+        }
+    }
+
+    /// Returns `true` if drawing goes to a back buffer instead of the screen.
+    pub fn has_back_buffer(&self) -> bool {
+        self.back_buffer.is_some()
+    }
+
+    /// Copies everything drawn since the last call from the back buffer to the screen, then draws
+    /// the [cursor](GraphicsAPI::move_cursor) on top if it was covered. Does nothing without a back
+    /// buffer, since drawing then goes straight to the screen.
+    pub fn present(&mut self) {
+        if let Some(back_buffer) = &mut self.back_buffer {
+            let cursor = self.cursor.rect();
+            let covered = back_buffer.dirty().rects().iter()
+                .any(|rect| !rect.intersection(&cursor).is_empty());
+            let mut screen = self.framebuffer.borrow_mut();
+            back_buffer.present(&mut screen);
+            if covered {
+                self.cursor.draw(&mut screen);
+            }
+        }
+    }
+
+    /// Returns the mouse cursor.
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
+    /// Moves the mouse cursor's tip to (x, y) on the screen, shows it and presents the change.
+    ///
+    /// The cursor is kept on top of everything which is drawn, so it does not need to be hidden
+    /// while drawing.
+    pub fn move_cursor(&mut self, x: usize, y: usize) {
+        match &mut self.back_buffer {
+            Some(back_buffer) => {
+                back_buffer.damage(self.cursor.rect());
+                self.cursor.move_to(x, y);
+                back_buffer.damage(self.cursor.rect());
+                self.present();
+            }
+            None => {
+                let mut screen = self.framebuffer.borrow_mut();
+                self.cursor.restore(&mut screen);
+                self.cursor.move_to(x, y);
+                self.cursor.save_and_draw(&mut screen);
+            }
+        }
+    }
+
+    /// Removes the mouse cursor from the screen, putting back what is under it.
+    pub fn hide_cursor(&mut self) {
+        match &mut self.back_buffer {
+            Some(back_buffer) => {
+                back_buffer.damage(self.cursor.rect());
+                self.cursor.hide();
+                self.present();
+            }
+            None => {
+                self.cursor.restore(&mut self.framebuffer.borrow_mut());
+                self.cursor.hide();
+            }
+        }
+    }
+
+    /// Runs `draw` on the memory which is drawn on, marking `damage` as changed in the back buffer.
+    ///
+    /// Without a back buffer the cursor is taken off the screen while `damage` is drawn under it,
+    /// so that the pixels it saved stay up to date.
+    fn draw<R>(&mut self, damage: Rect, draw: impl FnOnce(&mut FramebufferMemory) -> R) -> R {
+        match &mut self.back_buffer {
+            Some(back_buffer) => {
+                back_buffer.damage(damage);
+                draw(&mut back_buffer.memory())
+            }
+            None => {
+                let mut screen = self.framebuffer.borrow_mut();
+                let covered = self.cursor.is_visible()
+                    && !damage.intersection(&self.cursor.rect()).is_empty();
+                if covered {
+                    self.cursor.restore(&mut screen);
+                }
+                let result = draw(&mut screen);
+                if covered {
+                    self.cursor.save_and_draw(&mut screen);
+                }
+                result
+            }
         }
     }
 
@@ -52,9 +275,22 @@ impl<'a> GraphicsAPI<'a> {
     ///
     /// In this example, a teal coloured pixel is plotted at 50, 50 on the screen.
     pub fn plot_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
-        let mut fb = self.framebuffer.borrow_mut();
+        if let Some((x, y)) = self.surface.point_to_screen(x, y) {
+            let index = FramebufferIndex { x, y };
+            self.draw(Rect::new(x, y, 1, 1), |fb| Framebuffer::set_pixel(fb, pixel, index));
+        }
+    }
+
+    /// Returns the colour of the pixel at (x, y), or `None` if it is outside of the surface.
+    pub fn get_pixel(&mut self, x: usize, y: usize) -> Option<Pixel> {
+        let (x, y) = self.surface.point_to_screen(x, y)?;
         let index = FramebufferIndex { x, y };
-        Framebuffer::set_pixel(&mut fb, pixel, index);
+        match &mut self.back_buffer {
+            Some(back_buffer) => Framebuffer::get_pixel(&mut back_buffer.memory(), index),
+            // The pixel under the cursor is returned, rather than the cursor itself.
+            None => self.cursor.saved_pixel(x, y)
+                .or_else(|| Framebuffer::get_pixel(&mut self.framebuffer.borrow_mut(), index)),
+        }
     }
 
     /// Draw a filled rectangle at the specified location with the given colour.
@@ -70,11 +306,36 @@ impl<'a> GraphicsAPI<'a> {
     ///
     /// In this example, a teal coloured rectangle will fill the screen, assuming `width` is the width of the scrren and `height` is the height of the screen.
     pub fn draw_filled_rectangle(&mut self, x: usize, y: usize, width: usize, height: usize, pixel: Pixel) {
-        for i in y..(y + height) {
-            for j in x..(x + width) {
-                self.plot_pixel(j, i, pixel);
-            }
+        let rect = self.surface.to_screen(Rect::new(x, y, width, height));
+        self.draw(rect, |fb| Framebuffer::fill_rect(fb, rect.x, rect.y, rect.width, rect.height, pixel));
+    }
+
+    /// Draws `pixels`, a `width` by `height` image stored one row after another, at (x, y).
+    ///
+    /// ## Example
+    /// ```rust
+    /// let red = Pixel { b: 0x00, g: 0x00, r: 0xFF };
+    /// let blue = Pixel { b: 0xFF, g: 0x00, r: 0x00 };
+    /// graphics_api.draw_pixels(10, 10, 2, 2, &[red, blue, blue, red]);
+    /// ```
+    ///
+    /// In this example a 2 by 2 checkerboard is drawn at 10, 10 on the screen.
+    pub fn draw_pixels(&mut self, x: usize, y: usize, width: usize, height: usize, pixels: &[Pixel]) {
+        let rect = self.surface.to_screen(Rect::new(x, y, width, height));
+        if rect.is_empty() {
+            return;
         }
+        // The clipped part of the image starts this far into it.
+        let skip_x = rect.x - (self.surface.area.x + x);
+        let skip_y = rect.y - (self.surface.area.y + y);
+        self.draw(rect, |fb| {
+            for row in 0..rect.height {
+                let start = (skip_y + row) * width + skip_x;
+                if let Some(source) = pixels.get(start..start + rect.width) {
+                    Framebuffer::blit_from(fb, rect.x, rect.y + row, rect.width, 1, source);
+                }
+            }
+        });
     }
 
     /// Draw a line from (x1, y1) to (x2, y2) with the given colour.
@@ -99,8 +360,12 @@ impl<'a> GraphicsAPI<'a> {
         let mut x = x1 as isize;
         let mut y = y1 as isize;
 
-        while x != x2 as isize || y != y2 as isize {
+        loop {
             self.plot_pixel(x as usize, y as usize, pixel);
+            // The end point is plotted too, so a line from a point to itself is one pixel.
+            if x == x2 as isize && y == y2 as isize {
+                break;
+            }
 
             let e2 = 2 * err;
             if e2 > -dy {
@@ -115,24 +380,27 @@ impl<'a> GraphicsAPI<'a> {
     }
 
 
-    /// Copy a rectangular region from the source location to the destination location.
+    /// Copy a rectangular region from the source location to the destination location. The regions
+    /// may overlap. The part of the destination whose source is outside of the framebuffer is
+    /// filled with `fill_colour`.
     pub fn copy_rect(&mut self, src_x: usize, src_y: usize, width: usize, height: usize, dest_x: usize, dest_y: usize, fill_colour: Pixel) {
-        let mut fb = self.framebuffer.borrow_mut();
-
-        for y in 0..height {
-            for x in 0..width {
-                let src_index = FramebufferIndex { x: src_x + x, y: src_y + y };
-                let dest_index = FramebufferIndex { x: dest_x + x, y: dest_y + y };
-
-                // Get the colour at the source location and copy it to the destination
-                let src_colour = Framebuffer::get_pixel(&mut fb, src_index).unwrap_or(fill_colour);
-                Framebuffer::set_pixel(&mut fb, src_colour, dest_index);
-            }
+        // Only the part of the destination inside the surface changes, the source moves with it.
+        let dest = self.surface.to_screen(Rect::new(dest_x, dest_y, width, height));
+        if dest.is_empty() {
+            return;
         }
+        let src_x = self.surface.area.x + src_x + (dest.x - (self.surface.area.x + dest_x));
+        let src_y = self.surface.area.y + src_y + (dest.y - (self.surface.area.y + dest_y));
+        let (dest_x, dest_y, width, height) = (dest.x, dest.y, dest.width, dest.height);
+        self.draw(dest, |fb| {
+            let (moved_width, moved_height) = Framebuffer::move_rect(fb, src_x, src_y, width, height, dest_x, dest_y);
+            Framebuffer::fill_rect(fb, dest_x + moved_width, dest_y, width - moved_width, moved_height, fill_colour);
+            Framebuffer::fill_rect(fb, dest_x, dest_y + moved_height, width, height - moved_height, fill_colour);
+        });
     }
 
-    /// Draws a character at a given location with a font size.
-    /// All characters supported by [`font8x8::BASIC_FONTS`] can be used.
+    /// Draws a character at a given location with a font size, which scales every pixel of the
+    /// font. Characters which `font` does not have are drawn with its fallback glyph.
     ///
     /// ## Example
     /// ```
@@ -140,49 +408,39 @@ impl<'a> GraphicsAPI<'a> {
     ///     0,
     ///     0,
     ///     'A',
-    ///     Pixel {
-    ///         b: 0x00,
-    ///         g: 0x00,
-    ///         r: 0x00
-    ///     },
+    ///     &font::FONT_8X8,
     ///     Pixel {
     ///         b: 0xFF,
     ///         g: 0xFF,
     ///         r: 0xFF
     ///     },
+    ///     Pixel {
+    ///         b: 0x00,
+    ///         g: 0x00,
+    ///         r: 0x00
+    ///     },
     ///     1
     /// );
     /// ```
     ///
-    /// In this example a white letter "A" is drawn at 0, 0 with font size 1 and a black backround colour.
-    pub fn draw_char(&mut self, x: usize, y: usize, c: char, text: Pixel, background: Pixel, font_size: usize) {
-        if let Some(glyph) = BASIC_FONTS.get(c) {
-            let glyph_width = 8;
-            let glyph_height = 8;
-
-            for gy in 0..glyph_height {
-                for gx in 0..glyph_width {
-                    let bit = glyph[gy] & (1 << gx);
-                    if bit != 0 {
-                        for dx in 0..font_size {
-                            for dy in 0..font_size {
-                                self.plot_pixel(x + gx * font_size + dx, y + gy * font_size + dy, text);
-                            }
-                        }
-                    } else {
-                        for dx in 0..font_size {
-                            for dy in 0..font_size {
-                                self.plot_pixel(x + gx * font_size + dx, y + gy * font_size + dy, background);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    /// In this example a white letter "A" is drawn at 0, 0 with the built in 8x8 font, font size 1 and a black backround colour.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_char(&mut self, x: usize, y: usize, c: char, font: &dyn Font, text: Pixel, background: Pixel, font_size: usize) {
+        let glyph = font.glyph_or_fallback(c);
+        let (width, height) = (font.width() * font_size, font.height() * font_size);
+        // The scaled glyph is built first, so it is drawn with one blit.
+        let mut pixels = core::mem::take(&mut self.glyph_pixels);
+        pixels.clear();
+        pixels.extend((0..width * height).map(|index| {
+            let (gx, gy) = (index % width / font_size, index / width / font_size);
+            if glyph.is_set(gx, gy) { text } else { background }
+        }));
+        self.draw_pixels(x, y, width, height, &pixels);
+        self.glyph_pixels = pixels;
     }
 
-    /// Draws a character at a given location with a font size with a transparent transparent.
-    /// All characters supported by [`font8x8::BASIC_FONTS`] can be used.
+    /// Draws a character at a given location with a font size with a transparent background.
+    /// Characters which `font` does not have are drawn with its fallback glyph.
     ///
     /// ## Example
     /// ```
@@ -190,6 +448,7 @@ impl<'a> GraphicsAPI<'a> {
     ///     0,
     ///     0,
     ///     'A',
+    ///     &font::FONT_8X8,
     ///     Pixel {
     ///         b: 0xFF,
     ///         g: 0xFF,
@@ -201,33 +460,182 @@ impl<'a> GraphicsAPI<'a> {
     ///
     /// In this example a white letter "A" is drawn at 0, 0 with font size 1. The background will be based of what was behind the character when you called
     /// this function.
-    pub fn draw_char_transparent(&mut self, x: usize, y: usize, c: char, text: Pixel, font_size: usize) {
-        if let Some(glyph) = BASIC_FONTS.get(c) {
-            let glyph_width = 8;
-            let glyph_height = 8;
-
-            for gy in 0..glyph_height {
-                for gx in 0..glyph_width {
-                    let bit = glyph[gy] & (1 << gx);
-                    if bit != 0 {
-                        for dx in 0..font_size {
-                            for dy in 0..font_size {
-                                self.plot_pixel(x + gx * font_size + dx, y + gy * font_size + dy, text);
-                            }
-                        }
+    pub fn draw_char_transparent(&mut self, x: usize, y: usize, c: char, font: &dyn Font, text: Pixel, font_size: usize) {
+        let glyph = font.glyph_or_fallback(c);
+        for gy in 0..font.height() {
+            for gx in 0..font.width() {
+                if glyph.is_set(gx, gy) {
+                    self.draw_filled_rectangle(x + gx * font_size, y + gy * font_size, font_size, font_size, text);
+                }
+            }
+        }
+    }
+
+    /// Blends a translucent colour into the pixel at (x, y).
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.blend_pixel(50, 50, Rgba::new(0xFF, 0x00, 0x00, 0x80), BlendMode::SourceOver);
+    /// ```
+    ///
+    /// In this example the pixel at 50, 50 is tinted half way towards red.
+    pub fn blend_pixel(&mut self, x: usize, y: usize, colour: Rgba, mode: BlendMode) {
+        // Every blend mode leaves the pixel alone when the colour is invisible.
+        if colour.a == 0 {
+            return;
+        }
+        if let Some(destination) = self.get_pixel(x, y) {
+            self.plot_pixel(x, y, mode.blend(colour, destination));
+        }
+    }
+
+    /// Blends a translucent colour into every pixel of a rectangle.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let width = graphics_api.get_width();
+    /// graphics_api.blend_filled_rectangle(0, 0, width, 24, Rgba::new(0x00, 0x00, 0x00, 0x80), BlendMode::SourceOver);
+    /// ```
+    ///
+    /// In this example a translucent black status bar is drawn along the top of the screen.
+    pub fn blend_filled_rectangle(&mut self, x: usize, y: usize, width: usize, height: usize, colour: Rgba, mode: BlendMode) {
+        if colour.a == 0xFF && mode == BlendMode::SourceOver {
+            return self.draw_filled_rectangle(x, y, width, height, colour.pixel());
+        }
+        let rect = self.surface.to_screen(Rect::new(x, y, width, height));
+        self.draw(rect, |fb| {
+            for y in rect.y..rect.y + rect.height {
+                for x in rect.x..rect.x + rect.width {
+                    if let Some(destination) = Framebuffer::get_pixel(fb, FramebufferIndex { x, y }) {
+                        Framebuffer::set_pixel(fb, mode.blend(colour, destination), FramebufferIndex { x, y });
                     }
                 }
             }
+        });
+    }
+
+    /// Draws an anti-aliased line from (x1, y1) to (x2, y2), using Xiaolin Wu's algorithm. The two
+    /// pixels next to the exact line share the colour depending on how close they are to it.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_line_antialiased(10, 10, 100, 40, Rgba::new(0xFF, 0xFF, 0xFF, 0xFF));
+    /// ```
+    ///
+    /// In this example a smooth white line is drawn from 10, 10 to 100, 40.
+    pub fn draw_line_antialiased(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, colour: Rgba) {
+        let steep = y1.abs_diff(y2) > x1.abs_diff(x2);
+        // Walk along the longer axis, from the lower end.
+        let (x1, y1, x2, y2) = if steep { (y1, x1, y2, x2) } else { (x1, y1, x2, y2) };
+        let (x1, y1, x2, y2) = if x1 > x2 { (x2, y2, x1, y1) } else { (x1, y1, x2, y2) };
+
+        let plot = |api: &mut Self, major: usize, minor: usize, coverage: u8| {
+            let (x, y) = if steep { (minor, major) } else { (major, minor) };
+            api.blend_pixel(x, y, colour.with_coverage(coverage), BlendMode::SourceOver);
+        };
+
+        // The minor coordinate is kept as a fixed point number with 16 fractional bits.
+        let length = (x2 - x1) as isize;
+        let gradient = if length == 0 { 0 } else { ((y2 as isize - y1 as isize) << 16) / length };
+        let mut minor = (y1 as isize) << 16;
+        for major in x1..=x2 {
+            let fraction = ((minor >> 8) & 0xFF) as u8;
+            let whole = (minor >> 16) as usize;
+            plot(self, major, whole, 0xFF - fraction);
+            if fraction != 0 {
+                plot(self, major, whole + 1, fraction);
+            }
+            minor += gradient;
+        }
+    }
+
+    /// Draws a character with smooth edges and a transparent background. The glyph from `font` is
+    /// scaled up by `font_size` with bilinear filtering, so its edges fade into what is behind it
+    /// instead of being blocky.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_char_antialiased(0, 0, 'A', &font::FONT_8X8, Rgba::new(0xFF, 0xFF, 0xFF, 0xFF), 3);
+    /// ```
+    ///
+    /// In this example a smooth white letter "A" is drawn at 0, 0 with font size 3.
+    pub fn draw_char_antialiased(&mut self, x: usize, y: usize, c: char, font: &dyn Font, text: Rgba, font_size: usize) {
+        if font_size == 0 {
+            return;
+        }
+        let glyph = font.glyph_or_fallback(c);
+        let bit = |gx: isize, gy: isize| -> u32 {
+            (gx >= 0 && gy >= 0 && glyph.is_set(gx as usize, gy as usize)) as u32 * 0xFF
+        };
+
+        for py in 0..font.height() * font_size {
+            for px in 0..font.width() * font_size {
+                // The centre of the pixel in glyph coordinates, with 8 fractional bits, measured
+                // from the centre of the first glyph pixel.
+                let u = ((2 * px + 1) * 256 / (2 * font_size)) as isize - 128;
+                let v = ((2 * py + 1) * 256 / (2 * font_size)) as isize - 128;
+                let (gx, gy) = (u >> 8, v >> 8);
+                let (fx, fy) = ((u & 0xFF) as u32, (v & 0xFF) as u32);
+
+                let top = bit(gx, gy) * (256 - fx) + bit(gx + 1, gy) * fx;
+                let bottom = bit(gx, gy + 1) * (256 - fx) + bit(gx + 1, gy + 1) * fx;
+                let coverage = ((top * (256 - fy) + bottom * fy) >> 16) as u8;
+                if coverage != 0 {
+                    self.blend_pixel(x + px, y + py, text.with_coverage(coverage), BlendMode::SourceOver);
+                }
+            }
+        }
+    }
+
+    /// Draws a bitmap with its top left corner at (x, y). Translucent pixels are blended with what
+    /// is behind them, and pixels of the colour `colour_key` are not drawn at all.
+    ///
+    /// ## Example
+    /// ```rust
+    /// let icon = image::decode(include_bytes!("icon.bmp")).unwrap();
+    /// graphics_api.draw_bitmap(10, 10, &icon, Some(Pixel { b: 0xFF, g: 0x00, r: 0xFF }));
+    /// ```
+    ///
+    /// In this example an icon is drawn at 10, 10, with its magenta background left out.
+    pub fn draw_bitmap(&mut self, x: usize, y: usize, bitmap: &Bitmap, colour_key: Option<Pixel>) {
+        self.draw_bitmap_scaled(x, y, bitmap.width(), bitmap.height(), bitmap, colour_key);
+    }
+
+    /// Draws a bitmap stretched to `width` by `height` pixels, using the nearest pixel of the
+    /// bitmap for every pixel drawn. See [`draw_bitmap`](GraphicsAPI::draw_bitmap).
+    pub fn draw_bitmap_scaled(&mut self, x: usize, y: usize, width: usize, height: usize, bitmap: &Bitmap, colour_key: Option<Pixel>) {
+        // Only the part inside the surface is scaled.
+        let visible = self.surface.to_screen(Rect::new(x, y, width, height));
+        let (left, top) = (self.surface.area.x + x, self.surface.area.y + y);
+        for screen_y in visible.y..visible.y + visible.height {
+            let dy = screen_y - top;
+            let source_y = dy * bitmap.height() / height;
+            for screen_x in visible.x..visible.x + visible.width {
+                let dx = screen_x - left;
+                let Some(colour) = bitmap.get(dx * bitmap.width() / width, source_y) else {
+                    continue;
+                };
+                if colour_key == Some(colour.pixel()) || colour.a == 0 {
+                    continue;
+                }
+                if colour.a == 0xFF {
+                    self.plot_pixel(x + dx, y + dy, colour.pixel());
+                } else {
+                    self.blend_pixel(x + dx, y + dy, colour, BlendMode::SourceOver);
+                }
+            }
         }
     }
 
-    /// Get the width of the framebuffer.
+    /// Get the width of the current surface, which is the width of the framebuffer unless
+    /// [`with_surface`](GraphicsAPI::with_surface) is used.
     pub fn get_width(&self) -> usize {
-        self.framebuffer.borrow().width
+        self.surface.area.width
     }
 
-    /// Get the height of the framebuffer.
+    /// Get the height of the current surface, which is the height of the framebuffer unless
+    /// [`with_surface`](GraphicsAPI::with_surface) is used.
     pub fn get_height(&self) -> usize {
-        self.framebuffer.borrow().height
+        self.surface.area.height
     }
 }
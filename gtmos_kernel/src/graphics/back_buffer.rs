@@ -0,0 +1,150 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::drivers::framebuffer::{FramebufferMemory, PixelFormat};
+use super::Rect;
+
+/// The most rectangles a [`DirtyRegion`] keeps before merging them all into one.
+pub const MAX_DIRTY_RECTS: usize = 32;
+
+/// The parts of the screen which have changed since they were last presented.
+///
+/// Rectangles which overlap or touch are merged as they are added, so drawing a line or a glyph
+/// pixel by pixel only produces one rectangle. When more than [`MAX_DIRTY_RECTS`] separate
+/// rectangles are damaged they are replaced by their bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct DirtyRegion {
+    rects: [Rect; MAX_DIRTY_RECTS],
+    count: usize,
+}
+
+impl DirtyRegion {
+    pub const fn new() -> Self {
+        DirtyRegion { rects: [Rect::EMPTY; MAX_DIRTY_RECTS], count: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the damaged rectangles, they never overlap each other.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects[..self.count]
+    }
+
+    /// Marks `rect` as damaged.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        // Merging can make a rectangle touch others, so keep merging until nothing changes.
+        let mut merged = rect;
+        let mut index = 0;
+        while index < self.count {
+            if self.rects[index].touches(&merged) {
+                merged = merged.union(&self.rects[index]);
+                self.count -= 1;
+                self.rects[index] = self.rects[self.count];
+                index = 0;
+            } else {
+                index += 1;
+            }
+        }
+
+        if self.count == MAX_DIRTY_RECTS {
+            merged = self.rects().iter().fold(merged, |bounds, rect| bounds.union(rect));
+            self.count = 0;
+        }
+        self.rects[self.count] = merged;
+        self.count += 1;
+    }
+
+    /// Forgets every damaged rectangle.
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+}
+
+impl Default for DirtyRegion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An off-screen copy of the framebuffer, allocated on the kernel heap.
+///
+/// Pixels are stored already encoded in the framebuffer's [`PixelFormat`], but without the padding
+/// at the end of each row, so every damaged row can be copied to the screen with one `memcpy`.
+pub struct BackBuffer {
+    buffer: Vec<u8>,
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    pixel_format: PixelFormat,
+    dirty: DirtyRegion,
+}
+
+impl BackBuffer {
+    /// Allocates a back buffer holding a copy of what is on `front`.
+    pub fn new(front: &FramebufferMemory) -> Self {
+        let bytes_per_pixel = front.bytes_per_pixel;
+        let row_length = front.width * bytes_per_pixel;
+        let mut buffer = vec![0; row_length * front.height];
+        for (y, row) in buffer.chunks_exact_mut(row_length).enumerate() {
+            let start = y * front.stride * bytes_per_pixel;
+            if let Some(source) = front.buffer.get(start..start + row_length) {
+                row.copy_from_slice(source);
+            }
+        }
+
+        BackBuffer {
+            buffer,
+            width: front.width,
+            height: front.height,
+            bytes_per_pixel,
+            pixel_format: front.pixel_format,
+            dirty: DirtyRegion::new(),
+        }
+    }
+
+    /// Returns the back buffer as framebuffer memory, so that the [`Framebuffer`] functions can
+    /// draw on it.
+    ///
+    /// [`Framebuffer`]: crate::drivers::framebuffer::Framebuffer
+    pub fn memory(&mut self) -> FramebufferMemory<'_> {
+        FramebufferMemory {
+            buffer: &mut self.buffer,
+            width: self.width,
+            height: self.height,
+            stride: self.width,
+            bytes_per_pixel: self.bytes_per_pixel,
+            pixel_format: self.pixel_format,
+        }
+    }
+
+    /// Marks `rect` as changed, it is clipped to the size of the buffer.
+    pub fn damage(&mut self, rect: Rect) {
+        self.dirty.add(rect.intersection(&Rect::new(0, 0, self.width, self.height)));
+    }
+
+    pub fn dirty(&self) -> &DirtyRegion {
+        &self.dirty
+    }
+
+    /// Copies every damaged rectangle to `front`, one row at a time, and clears the damage.
+    pub fn present(&mut self, front: &mut FramebufferMemory) {
+        let bytes_per_pixel = self.bytes_per_pixel;
+        for rect in self.dirty.rects() {
+            let length = rect.width * bytes_per_pixel;
+            for y in rect.y..rect.y + rect.height {
+                let source = (y * self.width + rect.x) * bytes_per_pixel;
+                let destination = (y * front.stride + rect.x) * bytes_per_pixel;
+                if let Some(row) = front.buffer.get_mut(destination..destination + length) {
+                    row.copy_from_slice(&self.buffer[source..source + length]);
+                }
+            }
+        }
+        self.dirty.clear();
+    }
+}
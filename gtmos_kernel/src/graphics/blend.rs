@@ -0,0 +1,46 @@
+use crate::drivers::framebuffer::{Pixel, Rgba};
+
+/// How a translucent colour is combined with the pixel already on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// The colour is painted over the pixel, letting it show through by the colour's alpha.
+    #[default]
+    SourceOver,
+    /// The colour is added to the pixel, which brightens it, such as for glows.
+    Additive,
+    /// The pixel is multiplied by the colour, which darkens it, such as for shadows.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Combines `source` with the pixel `destination` which is under it.
+    pub fn blend(self, source: Rgba, destination: Pixel) -> Pixel {
+        let alpha = source.a;
+        let channel = |source: u8, destination: u8| -> u8 {
+            match self {
+                BlendMode::SourceOver => mix(destination, source, alpha),
+                BlendMode::Additive => destination.saturating_add(multiply(source, alpha)),
+                BlendMode::Multiply => mix(destination, multiply(source, destination), alpha),
+            }
+        };
+
+        Pixel {
+            r: channel(source.r, destination.r),
+            g: channel(source.g, destination.g),
+            b: channel(source.b, destination.b),
+        }
+    }
+}
+
+/// Multiplies two values as if they were fractions of `0xFF`, rounding to the nearest value.
+#[inline]
+fn multiply(a: u8, b: u8) -> u8 {
+    let product = a as u32 * b as u32 + 0x80;
+    ((product + (product >> 8)) >> 8) as u8
+}
+
+/// Moves `from` towards `to` by `amount`, where `0xFF` reaches `to`.
+#[inline]
+fn mix(from: u8, to: u8, amount: u8) -> u8 {
+    multiply(from, 0xFF - amount).saturating_add(multiply(to, amount))
+}
@@ -0,0 +1,157 @@
+use crate::drivers::framebuffer::{Framebuffer, FramebufferIndex, FramebufferMemory, Pixel};
+use super::Rect;
+
+/// The width of the cursor sprite in pixels.
+pub const CURSOR_WIDTH: usize = 12;
+/// The height of the cursor sprite in pixels.
+pub const CURSOR_HEIGHT: usize = 19;
+
+/// The arrow sprite, `#` is the outline, `.` is the fill and spaces are transparent.
+const SPRITE: [&[u8; CURSOR_WIDTH]; CURSOR_HEIGHT] = [
+    b"#           ",
+    b"##          ",
+    b"#.#         ",
+    b"#..#        ",
+    b"#...#       ",
+    b"#....#      ",
+    b"#.....#     ",
+    b"#......#    ",
+    b"#.......#   ",
+    b"#........#  ",
+    b"#.........# ",
+    b"#......#####",
+    b"#...#..#    ",
+    b"#..# #..#   ",
+    b"#.#  #..#   ",
+    b"##    #..#  ",
+    b"#     #..#  ",
+    b"       #..# ",
+    b"       ##   ",
+];
+
+const OUTLINE: Pixel = Pixel { r: 0x00, g: 0x00, b: 0x00 };
+const FILL: Pixel = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+
+/// A mouse cursor drawn on top of the screen.
+///
+/// The cursor belongs to the [`GraphicsAPI`](super::GraphicsAPI), which draws it straight onto the
+/// screen each time the back buffer is [presented](super::GraphicsAPI::present). It is never drawn
+/// into the back buffer, so whatever is drawn under it is kept, and it reappears on top once the
+/// change is presented.
+///
+/// Without a back buffer the pixels under the cursor are saved before it is drawn, and put back
+/// when it moves. The graphics API takes the cursor off the screen while drawing under it.
+///
+/// ## Example
+/// ```rust
+/// while let Some(event) = mouse::read_event() {
+///     graphics_api.move_cursor(event.x, event.y);
+/// }
+/// ```
+///
+/// In this example the cursor follows the PS/2 mouse.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor {
+    x: usize,
+    y: usize,
+    visible: bool,
+    /// The pixels under the sprite, saved by [`save_and_draw`](Cursor::save_and_draw).
+    saved: [Option<Pixel>; CURSOR_WIDTH * CURSOR_HEIGHT],
+}
+
+impl Cursor {
+    /// Creates a hidden cursor.
+    pub const fn new() -> Self {
+        Cursor { x: 0, y: 0, visible: false, saved: [None; CURSOR_WIDTH * CURSOR_HEIGHT] }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Returns the position of the cursor's tip.
+    pub fn position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    /// Returns the part of the screen the sprite covers, on or off screen.
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, CURSOR_WIDTH, CURSOR_HEIGHT)
+    }
+
+    /// Moves the cursor's tip to (x, y) and shows it.
+    pub fn move_to(&mut self, x: usize, y: usize) {
+        self.x = x;
+        self.y = y;
+        self.visible = true;
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    /// Draws the sprite onto `screen`, if the cursor is visible.
+    pub fn draw(&self, screen: &mut FramebufferMemory) {
+        if !self.visible {
+            return;
+        }
+        for (row, line) in SPRITE.iter().enumerate() {
+            for (column, kind) in line.iter().enumerate() {
+                let index = FramebufferIndex { x: self.x + column, y: self.y + row };
+                match kind {
+                    b'#' => Framebuffer::set_pixel(screen, OUTLINE, index),
+                    b'.' => Framebuffer::set_pixel(screen, FILL, index),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Saves the pixels of `screen` under the sprite and then draws it, if the cursor is visible.
+    /// This is used when there is no back buffer to copy them from.
+    pub fn save_and_draw(&mut self, screen: &mut FramebufferMemory) {
+        if !self.visible {
+            return;
+        }
+        for (row, line) in SPRITE.iter().enumerate() {
+            for (column, kind) in line.iter().enumerate() {
+                let index = FramebufferIndex { x: self.x + column, y: self.y + row };
+                self.saved[row * CURSOR_WIDTH + column] = match kind {
+                    b' ' => None,
+                    _ => Framebuffer::get_pixel(screen, index),
+                };
+            }
+        }
+        self.draw(screen);
+    }
+
+    /// Puts back the pixels saved by [`save_and_draw`](Cursor::save_and_draw), if the cursor is
+    /// visible.
+    pub fn restore(&self, screen: &mut FramebufferMemory) {
+        if !self.visible {
+            return;
+        }
+        for row in 0..CURSOR_HEIGHT {
+            for column in 0..CURSOR_WIDTH {
+                if let Some(pixel) = self.saved[row * CURSOR_WIDTH + column] {
+                    let index = FramebufferIndex { x: self.x + column, y: self.y + row };
+                    Framebuffer::set_pixel(screen, pixel, index);
+                }
+            }
+        }
+    }
+
+    /// Returns the saved pixel which the visible cursor covers at (x, y) on the screen.
+    pub fn saved_pixel(&self, x: usize, y: usize) -> Option<Pixel> {
+        if !self.visible || !self.rect().contains(x, y) {
+            return None;
+        }
+        self.saved[(y - self.y) * CURSOR_WIDTH + (x - self.x)]
+    }
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
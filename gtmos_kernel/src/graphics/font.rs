@@ -0,0 +1,128 @@
+//! Bitmap fonts used to draw text.
+//!
+//! A [`Font`] turns characters into [`Glyph`]s, which are bitmaps of one cell. Every font has a
+//! fallback glyph, which is drawn for characters it does not have instead of dropping them.
+//! [`FONT_8X8`] is built into the kernel, and PC Screen Fonts can be loaded with [`PsfFont`].
+//!
+//! ## Example
+//! ```rust
+//! static TERMINUS: &[u8] = include_bytes!("ter-v16n.psf");
+//! let font = PsfFont::parse(TERMINUS).expect("the font should be valid");
+//! graphics_api.draw_char(0, 0, 'é', &font, Pixel { b: 0xFF, g: 0xFF, r: 0xFF }, Pixel { b: 0, g: 0, r: 0 }, 1);
+//! ```
+//!
+//! In this example an 8x16 font is embedded into the kernel and used to draw a character.
+
+pub mod psf;
+
+use font8x8::legacy::{BASIC_LEGACY, LATIN_LEGACY};
+
+pub use psf::{PsfError, PsfFont};
+
+/// The order of the pixels in each byte of a glyph's rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The lowest bit is the leftmost pixel, as in [`font8x8`].
+    LeastSignificantFirst,
+    /// The highest bit is the leftmost pixel, as in PC Screen Fonts.
+    MostSignificantFirst,
+}
+
+/// The bitmap of one character, stored one row after another.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph<'a> {
+    rows: &'a [u8],
+    width: usize,
+    height: usize,
+    bytes_per_row: usize,
+    bit_order: BitOrder,
+}
+
+impl<'a> Glyph<'a> {
+    /// Creates a glyph from its rows, each of which is padded to a whole number of bytes.
+    pub const fn new(rows: &'a [u8], width: usize, height: usize, bit_order: BitOrder) -> Self {
+        Glyph { rows, width, height, bytes_per_row: width.div_ceil(8), bit_order }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns `true` if the pixel at (x, y) is part of the character.
+    #[inline]
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let byte = self.rows.get(y * self.bytes_per_row + x / 8).copied().unwrap_or(0);
+        let bit = match self.bit_order {
+            BitOrder::LeastSignificantFirst => x % 8,
+            BitOrder::MostSignificantFirst => 7 - x % 8,
+        };
+        byte & (1 << bit) != 0
+    }
+}
+
+/// A bitmap font, where every character is drawn in a cell of the same size.
+///
+/// Fonts are `Sync` so that a console using one can be shared, such as the active console which
+/// `println!` writes to.
+pub trait Font: Sync {
+    /// The width of a cell in pixels.
+    fn width(&self) -> usize;
+
+    /// The height of a cell in pixels.
+    fn height(&self) -> usize;
+
+    /// Returns the glyph for `c`, or `None` if the font does not have one.
+    fn glyph(&self, c: char) -> Option<Glyph<'_>>;
+
+    /// Returns the glyph drawn for characters the font does not have.
+    fn fallback(&self) -> Glyph<'_>;
+
+    /// Returns the glyph for `c`, or the fallback glyph if the font does not have one.
+    fn glyph_or_fallback(&self, c: char) -> Glyph<'_> {
+        self.glyph(c).unwrap_or_else(|| self.fallback())
+    }
+}
+
+/// The 8x8 font from [`font8x8`], with ASCII and Latin-1.
+#[derive(Debug, Clone, Copy)]
+pub struct Font8x8;
+
+/// The built in font, which is used by the console until another font is loaded.
+pub static FONT_8X8: Font8x8 = Font8x8;
+
+// The font8x8 tables are constants, they are copied into statics so glyphs can borrow them.
+static BASIC: [[u8; 8]; 128] = BASIC_LEGACY;
+static LATIN: [[u8; 8]; 96] = LATIN_LEGACY;
+
+/// An empty box, drawn for characters [`Font8x8`] does not have.
+const FALLBACK_8X8: [u8; 8] = [0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x00];
+
+impl Font for Font8x8 {
+    fn width(&self) -> usize {
+        8
+    }
+
+    fn height(&self) -> usize {
+        8
+    }
+
+    fn glyph(&self, c: char) -> Option<Glyph<'_>> {
+        let rows: &'static [u8; 8] = match c as usize {
+            code @ 0x00..=0x7F => &BASIC[code],
+            code @ 0xA0..=0xFF => &LATIN[code - 0xA0],
+            _ => return None,
+        };
+        Some(Glyph::new(rows, 8, 8, BitOrder::LeastSignificantFirst))
+    }
+
+    fn fallback(&self) -> Glyph<'_> {
+        Glyph::new(&FALLBACK_8X8, 8, 8, BitOrder::LeastSignificantFirst)
+    }
+}
@@ -0,0 +1,211 @@
+//! Parser for PC Screen Fonts (PSF), the console font format used by Linux.
+//!
+//! Both versions are supported. PSF1 fonts are 8 pixels wide with 256 or 512 glyphs, PSF2 fonts
+//! can be any size. Either may have a Unicode table, which lists the characters each glyph is
+//! used for. Without one, glyph `n` is used for the character with code point `n`.
+//!
+//! ## See also:
+//! * [PC Screen Font (OsDev.org)](https://wiki.osdev.org/PC_Screen_Font)
+
+use alloc::collections::BTreeMap;
+
+use super::{BitOrder, Font, Glyph};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_MODE_HAS_SEQUENCES: u8 = 0x04;
+const PSF1_HEADER_SIZE: usize = 4;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_START_SEQUENCE: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_HEADER_SIZE: usize = 32;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_START_SEQUENCE: u8 = 0xFE;
+
+/// The replacement character, used as the fallback glyph when the font has it.
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// Errors returned by [`PsfFont::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsfError {
+    /// The data does not start with the PSF1 or PSF2 magic number.
+    InvalidMagic,
+    /// The data ends before the glyphs or the Unicode table.
+    Truncated,
+    /// The header describes a font with no glyphs or glyphs of no size.
+    InvalidHeader,
+}
+
+/// A font parsed from PSF1 or PSF2 data. The glyphs are borrowed from the data, only the Unicode
+/// table is copied onto the heap.
+#[derive(Debug, Clone)]
+pub struct PsfFont<'a> {
+    glyphs: &'a [u8],
+    glyph_count: usize,
+    bytes_per_glyph: usize,
+    width: usize,
+    height: usize,
+    /// Maps characters to glyphs, `None` when the font has no Unicode table.
+    unicode: Option<BTreeMap<char, usize>>,
+    fallback: usize,
+}
+
+impl<'a> PsfFont<'a> {
+    /// Parses a PSF1 or PSF2 font, such as one embedded with `include_bytes!` or loaded from an
+    /// initrd.
+    pub fn parse(data: &'a [u8]) -> Result<Self, PsfError> {
+        let mut font = if data.starts_with(&PSF2_MAGIC) {
+            Self::parse_psf2(data)?
+        } else if data.starts_with(&PSF1_MAGIC) {
+            Self::parse_psf1(data)?
+        } else {
+            return Err(PsfError::InvalidMagic);
+        };
+
+        font.fallback = [REPLACEMENT_CHARACTER, '?']
+            .iter()
+            .find_map(|c| font.glyph_index(*c))
+            .unwrap_or(0);
+        Ok(font)
+    }
+
+    fn parse_psf1(data: &'a [u8]) -> Result<Self, PsfError> {
+        let mode = *data.get(2).ok_or(PsfError::Truncated)?;
+        let height = *data.get(3).ok_or(PsfError::Truncated)? as usize;
+        let glyph_count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+        if height == 0 {
+            return Err(PsfError::InvalidHeader);
+        }
+
+        let table_start = PSF1_HEADER_SIZE + glyph_count * height;
+        let glyphs = data.get(PSF1_HEADER_SIZE..table_start).ok_or(PsfError::Truncated)?;
+        let unicode = if mode & (PSF1_MODE_HAS_TABLE | PSF1_MODE_HAS_SEQUENCES) != 0 {
+            Some(psf1_unicode_table(&data[table_start..], glyph_count)?)
+        } else {
+            None
+        };
+
+        Ok(PsfFont { glyphs, glyph_count, bytes_per_glyph: height, width: 8, height, unicode, fallback: 0 })
+    }
+
+    fn parse_psf2(data: &'a [u8]) -> Result<Self, PsfError> {
+        let field = |index: usize| -> Result<usize, PsfError> {
+            let offset = 4 + index * 4;
+            let bytes = data.get(offset..offset + 4).ok_or(PsfError::Truncated)?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        };
+        // The fields after the magic number: version, header size, flags, glyph count, bytes per
+        // glyph, height and width.
+        let header_size = field(1)?;
+        let flags = field(2)? as u32;
+        let glyph_count = field(3)?;
+        let bytes_per_glyph = field(4)?;
+        let height = field(5)?;
+        let width = field(6)?;
+        if header_size < PSF2_HEADER_SIZE
+            || glyph_count == 0
+            || width == 0
+            || height == 0
+            || bytes_per_glyph < width.div_ceil(8) * height
+        {
+            return Err(PsfError::InvalidHeader);
+        }
+
+        let table_start = glyph_count
+            .checked_mul(bytes_per_glyph)
+            .and_then(|size| size.checked_add(header_size))
+            .ok_or(PsfError::InvalidHeader)?;
+        let glyphs = data.get(header_size..table_start).ok_or(PsfError::Truncated)?;
+        let unicode = if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            Some(psf2_unicode_table(&data[table_start..], glyph_count)?)
+        } else {
+            None
+        };
+
+        Ok(PsfFont { glyphs, glyph_count, bytes_per_glyph, width, height, unicode, fallback: 0 })
+    }
+
+    /// Returns the number of glyphs in the font.
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_count
+    }
+
+    /// Returns the index of the glyph used for `c`.
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        match &self.unicode {
+            Some(table) => table.get(&c).copied(),
+            None => Some(c as usize).filter(|index| *index < self.glyph_count),
+        }
+    }
+
+    fn glyph_at(&self, index: usize) -> Glyph<'_> {
+        let start = index * self.bytes_per_glyph;
+        Glyph::new(
+            &self.glyphs[start..start + self.bytes_per_glyph],
+            self.width,
+            self.height,
+            BitOrder::MostSignificantFirst,
+        )
+    }
+}
+
+impl Font for PsfFont<'_> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn glyph(&self, c: char) -> Option<Glyph<'_>> {
+        self.glyph_index(c).map(|index| self.glyph_at(index))
+    }
+
+    fn fallback(&self) -> Glyph<'_> {
+        self.glyph_at(self.fallback)
+    }
+}
+
+/// Reads a PSF1 Unicode table. Each glyph has a list of UCS-2 characters, then optionally
+/// sequences of characters which start with `0xFFFE`, and ends with `0xFFFF`.
+fn psf1_unicode_table(table: &[u8], glyph_count: usize) -> Result<BTreeMap<char, usize>, PsfError> {
+    let mut map = BTreeMap::new();
+    let mut values = (0..table.len() / 2)
+        .map(|index| u16::from_le_bytes([table[2 * index], table[2 * index + 1]]));
+    for glyph in 0..glyph_count {
+        let mut in_sequence = false;
+        loop {
+            match values.next().ok_or(PsfError::Truncated)? {
+                PSF1_SEPARATOR => break,
+                // Sequences such as a letter with a combining accent are not drawn as one glyph.
+                PSF1_START_SEQUENCE => in_sequence = true,
+                value if !in_sequence => {
+                    if let Some(c) = char::from_u32(value as u32) {
+                        map.entry(c).or_insert(glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(map)
+}
+
+/// Reads a PSF2 Unicode table. Each glyph has UTF-8 characters, then optionally sequences of
+/// characters which start with `0xFE`, and ends with `0xFF`.
+fn psf2_unicode_table(table: &[u8], glyph_count: usize) -> Result<BTreeMap<char, usize>, PsfError> {
+    let mut map = BTreeMap::new();
+    let mut entries = table.split(|byte| *byte == PSF2_SEPARATOR);
+    for glyph in 0..glyph_count {
+        let entry = entries.next().ok_or(PsfError::Truncated)?;
+        let characters = entry.split(|byte| *byte == PSF2_START_SEQUENCE).next().unwrap_or(&[]);
+        for c in core::str::from_utf8(characters).unwrap_or("").chars() {
+            map.entry(c).or_insert(glyph);
+        }
+    }
+    Ok(map)
+}
@@ -0,0 +1,119 @@
+//! Decoders for BMP, TGA and QOI images.
+//!
+//! Images are decoded into a [`Bitmap`] on the kernel heap, which is drawn with
+//! [`GraphicsAPI::draw_bitmap`](super::GraphicsAPI::draw_bitmap). The formats are simple enough
+//! to decode without `std`, and can be embedded into the kernel with `include_bytes!`.
+//!
+//! ## Example
+//! ```rust
+//! static LOGO: &[u8] = include_bytes!("logo.qoi");
+//! let logo = image::decode(LOGO).expect("the logo should be valid");
+//! graphics_api.draw_bitmap(10, 10, &logo, None);
+//! ```
+//!
+//! In this example a logo embedded in the kernel is drawn in the top left corner of the screen.
+
+pub mod bmp;
+pub mod qoi;
+pub mod tga;
+
+use alloc::vec::Vec;
+
+use crate::drivers::framebuffer::Rgba;
+
+/// The largest image which is decoded, in pixels. This stops a small corrupt file from asking for
+/// more memory than the heap has.
+pub const MAX_PIXELS: usize = 4096 * 4096;
+
+/// Errors returned by the image decoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    /// The data does not start with the magic number of the format.
+    InvalidMagic,
+    /// The data ends before the image does.
+    Truncated,
+    /// The header has impossible values, such as a size of 0.
+    InvalidHeader,
+    /// The image uses a part of the format which is not supported, such as JPEG compressed BMPs.
+    Unsupported,
+    /// The image has more than [`MAX_PIXELS`] pixels, or there is not enough memory for it.
+    TooLarge,
+}
+
+/// An image, stored one row after another from the top left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl Bitmap {
+    /// Creates a bitmap from its pixels, returns `None` if there are not `width * height` of them.
+    pub fn new(width: usize, height: usize, pixels: Vec<Rgba>) -> Option<Self> {
+        (width.checked_mul(height) == Some(pixels.len())).then_some(Bitmap { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    /// Returns the pixel at (x, y), or `None` if it is outside of the bitmap.
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgba> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.pixels[y * self.width + x])
+    }
+}
+
+/// Decodes a BMP, TGA or QOI image. BMP and QOI are recognised by their magic numbers, anything
+/// else is decoded as TGA, which has none.
+pub fn decode(data: &[u8]) -> Result<Bitmap, ImageError> {
+    if data.starts_with(bmp::MAGIC) {
+        bmp::decode(data)
+    } else if data.starts_with(qoi::MAGIC) {
+        qoi::decode(data)
+    } else {
+        tga::decode(data)
+    }
+}
+
+/// Allocates the pixels of a `width` by `height` image, all transparent.
+fn allocate(width: usize, height: usize) -> Result<Vec<Rgba>, ImageError> {
+    let count = width.checked_mul(height).filter(|count| *count <= MAX_PIXELS).ok_or(ImageError::TooLarge)?;
+    if count == 0 {
+        return Err(ImageError::InvalidHeader);
+    }
+    let mut pixels = Vec::new();
+    pixels.try_reserve_exact(count).map_err(|_| ImageError::TooLarge)?;
+    pixels.resize(count, Rgba::new(0, 0, 0, 0));
+    Ok(pixels)
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, ImageError> {
+    data.get(offset).copied().ok_or(ImageError::Truncated)
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Result<u16, ImageError> {
+    let bytes = data.get(offset..offset + 2).ok_or(ImageError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32, ImageError> {
+    let bytes = data.get(offset..offset + 4).ok_or(ImageError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Result<u32, ImageError> {
+    let bytes = data.get(offset..offset + 4).ok_or(ImageError::Truncated)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
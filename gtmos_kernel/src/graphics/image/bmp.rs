@@ -0,0 +1,199 @@
+//! Decoder for Windows bitmaps, with 1, 4 or 8 bit palettes, 16, 24 or 32 bit colours and
+//! RLE4 or RLE8 compression.
+//!
+//! ## See also:
+//! * [BMP file format (Wikipedia)](https://en.wikipedia.org/wiki/BMP_file_format)
+
+use alloc::vec::Vec;
+
+use crate::drivers::framebuffer::Rgba;
+use super::{allocate, read_u16_le, read_u32_le, Bitmap, ImageError};
+
+pub const MAGIC: &[u8] = b"BM";
+
+const FILE_HEADER_SIZE: usize = 14;
+/// The size of `BITMAPINFOHEADER`, the oldest header which is supported. The newer headers start
+/// with the same fields.
+const INFO_HEADER_SIZE: usize = 40;
+
+const COMPRESSION_NONE: u32 = 0;
+const COMPRESSION_RLE8: u32 = 1;
+const COMPRESSION_RLE4: u32 = 2;
+const COMPRESSION_BITFIELDS: u32 = 3;
+const COMPRESSION_ALPHA_BITFIELDS: u32 = 6;
+
+/// Decodes a BMP image.
+pub fn decode(data: &[u8]) -> Result<Bitmap, ImageError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ImageError::InvalidMagic);
+    }
+    let pixel_offset = read_u32_le(data, 10)? as usize;
+    let header_size = read_u32_le(data, FILE_HEADER_SIZE)? as usize;
+    if header_size < INFO_HEADER_SIZE {
+        return Err(ImageError::Unsupported);
+    }
+    let info = |offset: usize| read_u32_le(data, FILE_HEADER_SIZE + offset);
+    let width = info(4)? as i32;
+    let height = info(8)? as i32;
+    let depth = read_u16_le(data, FILE_HEADER_SIZE + 14)?;
+    let compression = info(16)?;
+    let colours_used = info(32)? as usize;
+
+    // A negative height means the rows are stored from the top, otherwise from the bottom.
+    let top_down = height < 0;
+    let (width, height) = (width.unsigned_abs() as usize, height.unsigned_abs() as usize);
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    // The masks follow the header for BITMAPINFOHEADER, newer headers include them.
+    let masks = match (compression, depth) {
+        (COMPRESSION_BITFIELDS | COMPRESSION_ALPHA_BITFIELDS, 16 | 32) => {
+            let alpha = if compression == COMPRESSION_ALPHA_BITFIELDS || header_size > INFO_HEADER_SIZE + 12 {
+                info(INFO_HEADER_SIZE + 12)?
+            } else {
+                0
+            };
+            [info(INFO_HEADER_SIZE)?, info(INFO_HEADER_SIZE + 4)?, info(INFO_HEADER_SIZE + 8)?, alpha]
+        }
+        (COMPRESSION_NONE, 16) => [0x7C00, 0x03E0, 0x001F, 0],
+        (COMPRESSION_NONE, 32) => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+        (COMPRESSION_NONE, 1 | 4 | 8 | 24) | (COMPRESSION_RLE8, 8) | (COMPRESSION_RLE4, 4) => [0; 4],
+        _ => return Err(ImageError::Unsupported),
+    };
+
+    // The palette follows the headers, each entry is blue, green, red and an unused byte.
+    let palette_start = FILE_HEADER_SIZE
+        + header_size
+        + if compression == COMPRESSION_BITFIELDS && header_size == INFO_HEADER_SIZE { 12 } else { 0 };
+    let palette_length = if depth <= 8 { if colours_used == 0 { 1 << depth } else { colours_used.min(256) } } else { 0 };
+    let palette = data.get(palette_start..palette_start + palette_length * 4).ok_or(ImageError::Truncated)?;
+    let palette_colour = |index: usize| -> Rgba {
+        match palette.get(index * 4..index * 4 + 3) {
+            Some(&[b, g, r]) => Rgba::new(r, g, b, 0xFF),
+            // Indexes outside of the palette are black.
+            _ => Rgba::new(0, 0, 0, 0xFF),
+        }
+    };
+
+    let mut pixels = allocate(width, height)?;
+    let row_of = |y: usize| if top_down { y } else { height - 1 - y };
+    let image = data.get(pixel_offset..).ok_or(ImageError::Truncated)?;
+
+    if compression == COMPRESSION_RLE8 || compression == COMPRESSION_RLE4 {
+        // Pixels skipped by the compression stay at the first colour of the palette.
+        let indexes = decode_rle(image, width, height, compression == COMPRESSION_RLE4)?;
+        for (index, pixel) in indexes.iter().zip(pixels.iter_mut()) {
+            *pixel = palette_colour(*index as usize);
+        }
+        if !top_down {
+            for y in 0..height / 2 {
+                let (top, bottom) = pixels.split_at_mut((height - 1 - y) * width);
+                top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+            }
+        }
+        return Ok(Bitmap::new(width, height, pixels).expect("the pixels were allocated for this size"));
+    }
+
+    // Every row is padded to a multiple of 4 bytes.
+    let row_size = (width * depth as usize).div_ceil(32) * 4;
+    for y in 0..height {
+        let row = image.get(y * row_size..(y + 1) * row_size).ok_or(ImageError::Truncated)?;
+        let destination = &mut pixels[row_of(y) * width..(row_of(y) + 1) * width];
+        for (x, pixel) in destination.iter_mut().enumerate() {
+            *pixel = match depth {
+                1 | 4 | 8 => {
+                    let bit = x * depth as usize;
+                    let shift = 8 - depth as usize - bit % 8;
+                    palette_colour(((row[bit / 8] >> shift) & ((1 << depth) - 1) as u8) as usize)
+                }
+                24 => Rgba::new(row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 0xFF),
+                16 => masked_colour(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32, masks),
+                _ => masked_colour(u32::from_le_bytes([row[x * 4], row[x * 4 + 1], row[x * 4 + 2], row[x * 4 + 3]]), masks),
+            };
+        }
+    }
+
+    Ok(Bitmap::new(width, height, pixels).expect("the pixels were allocated for this size"))
+}
+
+/// Extracts the red, green, blue and alpha channels from `value` with `masks`, scaling each to 8
+/// bits. A mask of 0 for alpha makes the colour opaque.
+fn masked_colour(value: u32, masks: [u32; 4]) -> Rgba {
+    let channel = |mask: u32| -> u8 {
+        if mask == 0 {
+            return 0xFF;
+        }
+        let bits = mask.count_ones();
+        let value = (value & mask) >> mask.trailing_zeros();
+        let maximum = (1u64 << bits) - 1;
+        (value as u64 * 0xFF / maximum) as u8
+    };
+    Rgba::new(channel(masks[0]), channel(masks[1]), channel(masks[2]), channel(masks[3]))
+}
+
+/// Decodes RLE8 or RLE4 compressed palette indexes, in the order they are stored (usually from
+/// the bottom row).
+fn decode_rle(data: &[u8], width: usize, height: usize, four_bit: bool) -> Result<Vec<u8>, ImageError> {
+    let mut indexes = Vec::new();
+    indexes.try_reserve_exact(width * height).map_err(|_| ImageError::TooLarge)?;
+    indexes.resize(width * height, 0);
+
+    let (mut x, mut y, mut offset) = (0, 0, 0);
+    let mut set = |x: usize, y: usize, index: u8| {
+        if x < width && y < height {
+            indexes[y * width + x] = index;
+        }
+    };
+    let byte = |offset: usize| data.get(offset).copied().ok_or(ImageError::Truncated);
+
+    loop {
+        let (count, value) = (byte(offset)? as usize, byte(offset + 1)?);
+        offset += 2;
+        if count > 0 {
+            // A run of `count` pixels, in RLE4 alternating between the two halves of `value`.
+            for i in 0..count {
+                let index = if !four_bit { value } else if i % 2 == 0 { value >> 4 } else { value & 0x0F };
+                set(x, y, index);
+                x += 1;
+            }
+            continue;
+        }
+
+        match value {
+            // End of a row.
+            0 => {
+                x = 0;
+                y += 1;
+            }
+            // End of the image.
+            1 => break,
+            // Moves the position right and up.
+            2 => {
+                x += byte(offset)? as usize;
+                y += byte(offset + 1)? as usize;
+                offset += 2;
+            }
+            // `value` pixels stored as they are, padded to a multiple of 2 bytes.
+            length => {
+                let length = length as usize;
+                for i in 0..length {
+                    let index = if four_bit {
+                        let packed = byte(offset + i / 2)?;
+                        if i % 2 == 0 { packed >> 4 } else { packed & 0x0F }
+                    } else {
+                        byte(offset + i)?
+                    };
+                    set(x, y, index);
+                    x += 1;
+                }
+                let bytes = if four_bit { length.div_ceil(2) } else { length };
+                offset += bytes.next_multiple_of(2);
+            }
+        }
+        if y >= height {
+            break;
+        }
+    }
+    Ok(indexes)
+}
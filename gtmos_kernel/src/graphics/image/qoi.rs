@@ -0,0 +1,84 @@
+//! Decoder for the Quite OK Image format.
+//!
+//! ## See also:
+//! * [The QOI File Format Specification](https://qoiformat.org/qoi-specification.pdf)
+
+use crate::drivers::framebuffer::Rgba;
+use super::{allocate, read_u32_be, read_u8, Bitmap, ImageError};
+
+pub const MAGIC: &[u8] = b"qoif";
+
+const HEADER_SIZE: usize = 14;
+
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xC0;
+const OP_MASK: u8 = 0xC0;
+
+/// Decodes a QOI image.
+pub fn decode(data: &[u8]) -> Result<Bitmap, ImageError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ImageError::InvalidMagic);
+    }
+    let width = read_u32_be(data, 4)? as usize;
+    let height = read_u32_be(data, 8)? as usize;
+    let channels = read_u8(data, 12)?;
+    if channels != 3 && channels != 4 {
+        return Err(ImageError::InvalidHeader);
+    }
+    let mut pixels = allocate(width, height)?;
+
+    // Recently seen pixels, indexed by a hash of their colour.
+    let mut seen = [Rgba::new(0, 0, 0, 0); 64];
+    let mut pixel = Rgba::new(0, 0, 0, 0xFF);
+    let mut offset = HEADER_SIZE;
+    let mut index = 0;
+    while index < pixels.len() {
+        let byte = |offset: usize| read_u8(data, offset);
+        let tag = byte(offset)?;
+        offset += 1;
+
+        let mut run = 1;
+        match tag {
+            OP_RGB => {
+                pixel = Rgba { r: byte(offset)?, g: byte(offset + 1)?, b: byte(offset + 2)?, ..pixel };
+                offset += 3;
+            }
+            OP_RGBA => {
+                pixel = Rgba::new(byte(offset)?, byte(offset + 1)?, byte(offset + 2)?, byte(offset + 3)?);
+                offset += 4;
+            }
+            _ => match tag & OP_MASK {
+                OP_INDEX => pixel = seen[(tag & 0x3F) as usize],
+                OP_DIFF => {
+                    // Each difference is stored with a bias of 2.
+                    pixel.r = pixel.r.wrapping_add((tag >> 4) & 0x03).wrapping_sub(2);
+                    pixel.g = pixel.g.wrapping_add((tag >> 2) & 0x03).wrapping_sub(2);
+                    pixel.b = pixel.b.wrapping_add(tag & 0x03).wrapping_sub(2);
+                }
+                OP_LUMA => {
+                    let next = byte(offset)?;
+                    offset += 1;
+                    let green = (tag & 0x3F).wrapping_sub(32);
+                    pixel.r = pixel.r.wrapping_add(green).wrapping_add(next >> 4).wrapping_sub(8);
+                    pixel.g = pixel.g.wrapping_add(green);
+                    pixel.b = pixel.b.wrapping_add(green).wrapping_add(next & 0x0F).wrapping_sub(8);
+                }
+                // The run length is stored with a bias of -1.
+                OP_RUN => run = (tag & 0x3F) as usize + 1,
+                _ => unreachable!("a tag has only two bits"),
+            },
+        }
+
+        let hash = pixel.r as usize * 3 + pixel.g as usize * 5 + pixel.b as usize * 7 + pixel.a as usize * 11;
+        seen[hash % 64] = pixel;
+        let end = (index + run).min(pixels.len());
+        pixels[index..end].fill(pixel);
+        index = end;
+    }
+
+    Ok(Bitmap::new(width, height, pixels).expect("the pixels were allocated for this size"))
+}
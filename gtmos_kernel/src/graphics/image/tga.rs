@@ -0,0 +1,141 @@
+//! Decoder for Truevision TGA images, uncompressed or run-length encoded, with colour mapped,
+//! true colour or greyscale pixels.
+//!
+//! ## See also:
+//! * [Truevision TGA (Wikipedia)](https://en.wikipedia.org/wiki/Truevision_TGA)
+
+use crate::drivers::framebuffer::Rgba;
+use super::{allocate, read_u16_le, read_u8, Bitmap, ImageError};
+
+const HEADER_SIZE: usize = 18;
+
+const TYPE_COLOUR_MAPPED: u8 = 1;
+const TYPE_TRUE_COLOUR: u8 = 2;
+const TYPE_GREYSCALE: u8 = 3;
+/// Added to the image type when the pixels are run-length encoded.
+const TYPE_RLE: u8 = 8;
+
+/// Set in the image descriptor when the first row is the top of the image.
+const DESCRIPTOR_TOP_TO_BOTTOM: u8 = 1 << 5;
+/// Set in the image descriptor when each row goes from right to left.
+const DESCRIPTOR_RIGHT_TO_LEFT: u8 = 1 << 4;
+
+/// The number of alpha bits in each pixel.
+const DESCRIPTOR_ALPHA_BITS: u8 = 0x0F;
+
+/// Set in a run-length packet when it repeats one pixel, otherwise it is followed by raw pixels.
+const PACKET_RUN: u8 = 0x80;
+
+/// Decodes a TGA image.
+pub fn decode(data: &[u8]) -> Result<Bitmap, ImageError> {
+    let id_length = read_u8(data, 0)? as usize;
+    let has_colour_map = read_u8(data, 1)? == 1;
+    let image_type = read_u8(data, 2)?;
+    let map_first = read_u16_le(data, 3)? as usize;
+    let map_length = read_u16_le(data, 5)? as usize;
+    let map_entry_size = read_u8(data, 7)?;
+    let width = read_u16_le(data, 12)? as usize;
+    let height = read_u16_le(data, 14)? as usize;
+    let depth = read_u8(data, 16)?;
+    let descriptor = read_u8(data, 17)?;
+
+    let (kind, compressed) = match image_type {
+        TYPE_COLOUR_MAPPED | TYPE_TRUE_COLOUR | TYPE_GREYSCALE => (image_type, false),
+        t if t > TYPE_RLE && t - TYPE_RLE <= TYPE_GREYSCALE => (t - TYPE_RLE, true),
+        // Type 0 has no image, and there is no magic number to tell a TGA from other data.
+        _ => return Err(ImageError::InvalidMagic),
+    };
+    if kind == TYPE_COLOUR_MAPPED && !has_colour_map {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    let map_start = HEADER_SIZE + id_length;
+    let map_size = if has_colour_map { map_length * (map_entry_size as usize).div_ceil(8) } else { 0 };
+    let colour_map = data.get(map_start..map_start + map_size).ok_or(ImageError::Truncated)?;
+    let pixel_size = (depth as usize).div_ceil(8);
+    if pixel_size == 0 || pixel_size > 4 {
+        return Err(ImageError::Unsupported);
+    }
+
+    // Some images have a fourth byte per pixel which is not alpha, the descriptor says how many
+    // bits of alpha there are.
+    let has_alpha = descriptor & DESCRIPTOR_ALPHA_BITS != 0;
+
+    // Turns the bytes of one pixel in the image into a colour.
+    let colour = |bytes: &[u8]| -> Result<Rgba, ImageError> {
+        let colour = match kind {
+            TYPE_COLOUR_MAPPED => {
+                let index = bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as usize);
+                let entry_size = (map_entry_size as usize).div_ceil(8);
+                let start = index.checked_sub(map_first).ok_or(ImageError::InvalidHeader)? * entry_size;
+                let entry = colour_map.get(start..start + entry_size).ok_or(ImageError::InvalidHeader)?;
+                true_colour(entry)
+            }
+            TYPE_GREYSCALE => Ok(Rgba::new(bytes[0], bytes[0], bytes[0], 0xFF)),
+            _ => true_colour(bytes),
+        }?;
+        Ok(if has_alpha { colour } else { Rgba { a: 0xFF, ..colour } })
+    };
+
+    let mut pixels = allocate(width, height)?;
+    let mut offset = map_start + map_size;
+    let read_pixel = |offset: &mut usize| -> Result<Rgba, ImageError> {
+        let bytes = data.get(*offset..*offset + pixel_size).ok_or(ImageError::Truncated)?;
+        *offset += pixel_size;
+        colour(bytes)
+    };
+
+    // Pixels are stored in the order given by the descriptor, which is bottom to top by default.
+    let count = pixels.len();
+    let position = |index: usize| -> usize {
+        let (x, y) = (index % width, index / width);
+        let x = if descriptor & DESCRIPTOR_RIGHT_TO_LEFT != 0 { width - 1 - x } else { x };
+        let y = if descriptor & DESCRIPTOR_TOP_TO_BOTTOM != 0 { y } else { height - 1 - y };
+        y * width + x
+    };
+
+    let mut index = 0;
+    while index < count {
+        if !compressed {
+            pixels[position(index)] = read_pixel(&mut offset)?;
+            index += 1;
+            continue;
+        }
+
+        let packet = read_u8(data, offset)?;
+        offset += 1;
+        let length = ((packet & !PACKET_RUN) as usize + 1).min(count - index);
+        if packet & PACKET_RUN != 0 {
+            let pixel = read_pixel(&mut offset)?;
+            for index in index..index + length {
+                pixels[position(index)] = pixel;
+            }
+        } else {
+            for index in index..index + length {
+                pixels[position(index)] = read_pixel(&mut offset)?;
+            }
+        }
+        index += length;
+    }
+
+    Ok(Bitmap::new(width, height, pixels).expect("the pixels were allocated for this size"))
+}
+
+/// Decodes a true colour pixel, which is stored as blue, green, red and optionally alpha, or in
+/// 16 bits with 5 bits per colour.
+fn true_colour(bytes: &[u8]) -> Result<Rgba, ImageError> {
+    match *bytes {
+        [low, high] => {
+            let value = u16::from_le_bytes([low, high]);
+            // Scales a 5 bit channel to 8 bits.
+            let channel = |shift: u16| -> u8 {
+                let value = ((value >> shift) & 0x1F) as u8;
+                value << 3 | value >> 2
+            };
+            Ok(Rgba::new(channel(10), channel(5), channel(0), 0xFF))
+        }
+        [b, g, r] => Ok(Rgba::new(r, g, b, 0xFF)),
+        [b, g, r, a] => Ok(Rgba::new(r, g, b, a)),
+        _ => Err(ImageError::Unsupported),
+    }
+}
@@ -0,0 +1,313 @@
+//! Shapes drawn by the [`GraphicsAPI`]: circles, ellipses, polygons, rounded rectangles and lines
+//! with a width.
+//!
+//! ## See also:
+//! * [Midpoint circle algorithm (Wikipedia)](https://en.wikipedia.org/wiki/Midpoint_circle_algorithm)
+//! * [Scanline rendering (Wikipedia)](https://en.wikipedia.org/wiki/Scanline_rendering)
+
+use alloc::vec::Vec;
+
+use crate::drivers::framebuffer::{Pixel, Rgba};
+use super::blend::BlendMode;
+use super::GraphicsAPI;
+
+impl<'a> GraphicsAPI<'a> {
+    /// Draws the outline of a circle centred on (x, y).
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_circle(100, 100, 50, Pixel { b: 0xFF, g: 0xFF, r: 0xFF });
+    /// ```
+    ///
+    /// In this example a white circle with a radius of 50 is drawn around 100, 100.
+    pub fn draw_circle(&mut self, x: usize, y: usize, radius: usize, pixel: Pixel) {
+        let (cx, cy) = (x as isize, y as isize);
+        circle_points(radius, |a, b| {
+            for (dx, dy) in [(a, b), (b, a), (-a, b), (-b, a), (a, -b), (b, -a), (-a, -b), (-b, -a)] {
+                self.plot_signed(cx + dx, cy + dy, pixel);
+            }
+        });
+    }
+
+    /// Draws a filled circle centred on (x, y).
+    pub fn draw_filled_circle(&mut self, x: usize, y: usize, radius: usize, pixel: Pixel) {
+        let (cx, cy) = (x as isize, y as isize);
+        circle_points(radius, |a, b| {
+            self.fill_span(cx - a, cx + a, cy + b, pixel);
+            self.fill_span(cx - a, cx + a, cy - b, pixel);
+            self.fill_span(cx - b, cx + b, cy + a, pixel);
+            self.fill_span(cx - b, cx + b, cy - a, pixel);
+        });
+    }
+
+    /// Draws the outline of an ellipse centred on (x, y), which is `2 * radius_x + 1` pixels wide
+    /// and `2 * radius_y + 1` pixels high.
+    pub fn draw_ellipse(&mut self, x: usize, y: usize, radius_x: usize, radius_y: usize, pixel: Pixel) {
+        let (cx, cy) = (x as isize, y as isize);
+        ellipse_points(radius_x, radius_y, |a, b| {
+            for (dx, dy) in [(a, b), (-a, b), (a, -b), (-a, -b)] {
+                self.plot_signed(cx + dx, cy + dy, pixel);
+            }
+        });
+    }
+
+    /// Draws a filled ellipse centred on (x, y).
+    pub fn draw_filled_ellipse(&mut self, x: usize, y: usize, radius_x: usize, radius_y: usize, pixel: Pixel) {
+        let (cx, cy) = (x as isize, y as isize);
+        ellipse_points(radius_x, radius_y, |a, b| {
+            self.fill_span(cx - a, cx + a, cy + b, pixel);
+            self.fill_span(cx - a, cx + a, cy - b, pixel);
+        });
+    }
+
+    /// Draws the outline of a polygon, the last point is joined back to the first.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_polygon(&[(50, 10), (90, 90), (10, 90)], Pixel { b: 0x00, g: 0xFF, r: 0x00 });
+    /// ```
+    ///
+    /// In this example the outline of a green triangle is drawn.
+    pub fn draw_polygon(&mut self, points: &[(usize, usize)], pixel: Pixel) {
+        for (index, &(x1, y1)) in points.iter().enumerate() {
+            let (x2, y2) = points[(index + 1) % points.len()];
+            self.draw_line(x1, y1, x2, y2, pixel);
+        }
+    }
+
+    /// Fills a polygon one row at a time, using the even-odd rule, so the polygon may be concave or
+    /// cross itself.
+    pub fn draw_filled_polygon(&mut self, points: &[(usize, usize)], pixel: Pixel) {
+        let (Some(top), Some(bottom)) = (points.iter().map(|point| point.1).min(), points.iter().map(|point| point.1).max()) else {
+            return;
+        };
+
+        let mut crossings = Vec::with_capacity(points.len());
+        for y in top..=bottom {
+            let y = y as isize;
+            crossings.clear();
+            for (index, &(x1, y1)) in points.iter().enumerate() {
+                let (x2, y2) = points[(index + 1) % points.len()];
+                let (x1, y1, x2, y2) = (x1 as isize, y1 as isize, x2 as isize, y2 as isize);
+                // Each edge includes its top row but not its bottom row, so that a vertex shared by
+                // two edges is only counted once.
+                if (y1 <= y && y < y2) || (y2 <= y && y < y1) {
+                    crossings.push(x1 + (y - y1) * (x2 - x1) / (y2 - y1));
+                }
+            }
+            crossings.sort_unstable();
+            let mut pairs = crossings.as_slice();
+            while let [start, end, rest @ ..] = pairs {
+                self.fill_span(*start, *end, y, pixel);
+                pairs = rest;
+            }
+        }
+        // The bottom row is not part of any edge, the outline finishes it.
+        self.draw_polygon(points, pixel);
+    }
+
+    /// Draws the outline of a rectangle with corners rounded by `radius`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_rounded_rectangle(10, 10, 120, 40, 8, Pixel { b: 0xFF, g: 0xFF, r: 0xFF });
+    /// ```
+    ///
+    /// In this example a white button outline is drawn.
+    pub fn draw_rounded_rectangle(&mut self, x: usize, y: usize, width: usize, height: usize, radius: usize, pixel: Pixel) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let radius = radius.min((width - 1) / 2).min((height - 1) / 2);
+        let (left, top) = ((x + radius) as isize, (y + radius) as isize);
+        let (right, bottom) = ((x + width - 1 - radius) as isize, (y + height - 1 - radius) as isize);
+
+        self.fill_span(left, right, y as isize, pixel);
+        self.fill_span(left, right, (y + height - 1) as isize, pixel);
+        for row in top..=bottom {
+            self.plot_signed(x as isize, row, pixel);
+            self.plot_signed((x + width - 1) as isize, row, pixel);
+        }
+        circle_points(radius, |a, b| {
+            for (dx, dy) in [(a, b), (b, a)] {
+                self.plot_signed(left - dx, top - dy, pixel);
+                self.plot_signed(right + dx, top - dy, pixel);
+                self.plot_signed(left - dx, bottom + dy, pixel);
+                self.plot_signed(right + dx, bottom + dy, pixel);
+            }
+        });
+    }
+
+    /// Draws a filled rectangle with corners rounded by `radius`.
+    pub fn draw_filled_rounded_rectangle(&mut self, x: usize, y: usize, width: usize, height: usize, radius: usize, pixel: Pixel) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let radius = radius.min((width - 1) / 2).min((height - 1) / 2);
+        let (left, top) = ((x + radius) as isize, (y + radius) as isize);
+        let (right, bottom) = ((x + width - 1 - radius) as isize, (y + height - 1 - radius) as isize);
+
+        self.draw_filled_rectangle(x, top as usize, width, (bottom - top + 1) as usize, pixel);
+        circle_points(radius, |a, b| {
+            for (dx, dy) in [(a, b), (b, a)] {
+                self.fill_span(left - dx, right + dx, top - dy, pixel);
+                self.fill_span(left - dx, right + dx, bottom + dy, pixel);
+            }
+        });
+    }
+
+    /// Draws a line from (x1, y1) to (x2, y2) which is `width` pixels wide, with square ends.
+    ///
+    /// ## Example
+    /// ```rust
+    /// graphics_api.draw_wide_line(10, 10, 200, 80, 5, Pixel { b: 0x00, g: 0x00, r: 0xFF });
+    /// ```
+    ///
+    /// In this example a red line 5 pixels wide is drawn from 10, 10 to 200, 80.
+    pub fn draw_wide_line(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, width: usize, pixel: Pixel) {
+        self.wide_line((x1, y1), (x2, y2), width, pixel.into(), false);
+    }
+
+    /// Draws a line like [`draw_wide_line`](GraphicsAPI::draw_wide_line), but the pixels along its
+    /// edges are blended by how much of them the line covers.
+    pub fn draw_wide_line_antialiased(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, width: usize, colour: Rgba) {
+        self.wide_line((x1, y1), (x2, y2), width, colour, true);
+    }
+
+    fn wide_line(&mut self, (x1, y1): (usize, usize), (x2, y2): (usize, usize), width: usize, colour: Rgba, antialiased: bool) {
+        if width == 0 {
+            return;
+        }
+        let steep = y1.abs_diff(y2) > x1.abs_diff(x2);
+        // Walk along the longer axis from the lower end, drawing a span across the line at each step.
+        let (x1, y1, x2, y2) = if steep { (y1, x1, y2, x2) } else { (x1, y1, x2, y2) };
+        let (x1, y1, x2, y2) = if x1 > x2 { (x2, y2, x1, y1) } else { (x1, y1, x2, y2) };
+        let (major_length, minor_length) = ((x2 - x1) as i64, y2 as i64 - y1 as i64);
+
+        // Positions across the line are fixed point numbers with 8 fractional bits. A span crosses a
+        // sloped line at an angle, so it is longer than the width by `length / major_length`.
+        let length = isqrt(((major_length * major_length + minor_length * minor_length) as u64) << 16) as i64;
+        let half_span = if major_length == 0 { width as i64 * 128 } else { width as i64 * length / major_length / 2 };
+
+        for step in 0..=major_length {
+            let centre = ((y1 as i64) << 8) + if major_length == 0 { 0 } else { step * (minor_length << 8) / major_length } + 128;
+            let (start, end) = (centre - half_span, centre + half_span);
+            let major = x1 as isize + step as isize;
+            let plot = |api: &mut Self, minor: i64, coverage: u8| {
+                let (x, y) = if steep { (minor as isize, major) } else { (major, minor as isize) };
+                if x >= 0 && y >= 0 {
+                    api.blend_pixel(x as usize, y as usize, colour.with_coverage(coverage), BlendMode::SourceOver);
+                }
+            };
+
+            if antialiased {
+                for minor in start.div_euclid(256)..=(end - 1).div_euclid(256) {
+                    let covered = end.min((minor + 1) * 256) - start.max(minor * 256);
+                    plot(self, minor, covered.clamp(0, 255) as u8);
+                }
+            } else {
+                // A pixel is drawn when its centre is inside the span, but never less than one.
+                let first = (start - 128 + 255).div_euclid(256);
+                let last = ((end - 128 + 255).div_euclid(256) - 1).max(first);
+                for minor in first..=last {
+                    plot(self, minor, 0xFF);
+                }
+            }
+        }
+    }
+
+    /// Plots a pixel which may be left of or above the surface, where it is clipped.
+    fn plot_signed(&mut self, x: isize, y: isize, pixel: Pixel) {
+        if x >= 0 && y >= 0 {
+            self.plot_pixel(x as usize, y as usize, pixel);
+        }
+    }
+
+    /// Fills row `y` from `x1` to `x2`, both included.
+    fn fill_span(&mut self, x1: isize, x2: isize, y: isize, pixel: Pixel) {
+        if y < 0 || x2 < 0 || x2 < x1 {
+            return;
+        }
+        let x1 = x1.max(0);
+        self.draw_filled_rectangle(x1 as usize, y as usize, (x2 - x1 + 1) as usize, 1, pixel);
+    }
+}
+
+/// Calls `point` with every point (a, b) of the first octant of a circle around (0, 0), where
+/// `a >= b`. The other octants are found by swapping and negating them.
+fn circle_points(radius: usize, mut point: impl FnMut(isize, isize)) {
+    let (mut a, mut b) = (radius as isize, 0);
+    let mut error = 1 - a;
+    while a >= b {
+        point(a, b);
+        b += 1;
+        if error < 0 {
+            error += 2 * b + 1;
+        } else {
+            a -= 1;
+            error += 2 * (b - a) + 1;
+        }
+    }
+}
+
+/// Calls `point` with every point (a, b) of the first quadrant of an ellipse around (0, 0). The
+/// other quadrants are found by negating them.
+fn ellipse_points(radius_x: usize, radius_y: usize, mut point: impl FnMut(isize, isize)) {
+    // A flat ellipse is a line, which the regions below would reduce to its centre.
+    if radius_y == 0 {
+        (0..=radius_x as isize).for_each(|a| point(a, 0));
+        return;
+    }
+    if radius_x == 0 {
+        (0..=radius_y as isize).for_each(|b| point(0, b));
+        return;
+    }
+
+    let (rx2, ry2) = ((radius_x * radius_x) as i64, (radius_y * radius_y) as i64);
+    let (mut a, mut b) = (0i64, radius_y as i64);
+    let (mut step_a, mut step_b) = (0, 2 * rx2 * b);
+
+    // The first region, where the slope is shallower than -1 and `a` changes every step.
+    let mut decision = ry2 - rx2 * b + rx2 / 4;
+    while step_a < step_b {
+        point(a as isize, b as isize);
+        a += 1;
+        step_a += 2 * ry2;
+        if decision < 0 {
+            decision += ry2 + step_a;
+        } else {
+            b -= 1;
+            step_b -= 2 * rx2;
+            decision += ry2 + step_a - step_b;
+        }
+    }
+
+    // The second region, where `b` changes every step.
+    decision = ry2 * (a * a + a) + ry2 / 4 + rx2 * (b - 1) * (b - 1) - rx2 * ry2;
+    while b >= 0 {
+        point(a as isize, b as isize);
+        b -= 1;
+        step_b -= 2 * rx2;
+        if decision > 0 {
+            decision += rx2 - step_b;
+        } else {
+            a += 1;
+            step_a += 2 * ry2;
+            decision += rx2 - step_b + step_a;
+        }
+    }
+}
+
+/// Returns the square root of `value`, rounded down.
+pub fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    let mut root = value;
+    let mut next = root.div_ceil(2);
+    while next < root {
+        root = next;
+        next = (root + value / root) / 2;
+    }
+    root
+}
@@ -0,0 +1,53 @@
+use super::Rect;
+
+/// A part of the screen which is drawn on with its own coordinates.
+///
+/// The top left corner of the surface's area is (0, 0) for everything drawn on it, and nothing is
+/// drawn outside of its clip rectangle. A surface made inside another surface is placed relative to
+/// it and is clipped by it too, so a widget can not draw outside of the panel it is in.
+///
+/// ## Example
+/// ```rust
+/// graphics_api.with_surface(Rect::new(100, 100, 200, 50), |panel| {
+///     panel.draw_filled_rectangle(0, 0, 1000, 1000, Pixel { b: 0x80, g: 0x80, r: 0x80 });
+///     panel.with_surface(Rect::new(10, 10, 30, 30), |button| {
+///         button.draw_line(0, 0, 100, 100, Pixel { b: 0x00, g: 0x00, r: 0xFF });
+///     });
+/// });
+/// ```
+///
+/// In this example a grey 200 by 50 panel is drawn at 100, 100 on the screen, although the
+/// rectangle is much larger. A red line is then drawn from 110, 110 but stops at 139, 139.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Surface {
+    /// The area of the surface in screen coordinates, its top left corner is the origin.
+    pub area: Rect,
+    /// The part of the screen which can be drawn on, always inside `area`.
+    pub clip: Rect,
+}
+
+impl Surface {
+    /// Creates a surface covering a whole screen of `width` by `height` pixels.
+    pub const fn screen(width: usize, height: usize) -> Self {
+        let area = Rect::new(0, 0, width, height);
+        Surface { area, clip: area }
+    }
+
+    /// Creates a surface for `rect`, which is relative to this surface.
+    pub fn child(&self, rect: Rect) -> Surface {
+        let area = Rect::new(self.area.x + rect.x, self.area.y + rect.y, rect.width, rect.height);
+        Surface { area, clip: self.clip.intersection(&area) }
+    }
+
+    /// Converts a rectangle on this surface into the part of it on the screen which can be drawn
+    /// on.
+    pub fn to_screen(&self, rect: Rect) -> Rect {
+        Rect::new(self.area.x + rect.x, self.area.y + rect.y, rect.width, rect.height).intersection(&self.clip)
+    }
+
+    /// Converts a point on this surface into screen coordinates, or `None` if it is clipped.
+    pub fn point_to_screen(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (x, y) = (self.area.x + x, self.area.y + y);
+        self.clip.contains(x, y).then_some((x, y))
+    }
+}
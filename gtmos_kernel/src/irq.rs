@@ -0,0 +1,184 @@
+//! Module containing the kernel's interrupt request (IRQ) API.
+//!
+//! Drivers attach a [`Handler`] to a legacy IRQ line with [`register_irq`], or directly to an
+//! interrupt vector with [`register_vector`]. The SubSystem points every vector from
+//! [`FIRST_VECTOR`] to [`LAST_VECTOR`] at [`dispatch`], which calls the registered handler and
+//! then tells the [`InterruptController`] that the interrupt has been handled, so handlers never
+//! send an EOI (End Of Interrupt) themselves.
+//!
+//! ## Example
+//! ```rust
+//! fn keyboard_interrupt(_vector: u8) {
+//!     let scancode = unsafe { Port::<u8>::new(0x60).read() };
+//!     // ...
+//! }
+//!
+//! irq::register_irq(1, keyboard_interrupt)?;
+//! ```
+//!
+//! In this example a keyboard driver attaches to IRQ 1, the line of the PS/2 keyboard.
+//!
+//! ## See also:
+//! * [Interrupts (OsDev.org)](https://wiki.osdev.org/Interrupts)
+//! * [IRQ (OsDev.org)](https://wiki.osdev.org/IRQ)
+
+use spin::{Mutex, Once};
+
+/// The first vector which can be used for IRQs, the vectors below it are used by CPU exceptions.
+pub const FIRST_VECTOR: u8 = 32;
+
+/// The last vector which can be used for IRQs. Vector 255 is kept for spurious interrupts, so the
+/// SubSystem never points it at [`dispatch`].
+pub const LAST_VECTOR: u8 = 254;
+
+/// The number of legacy (ISA) IRQ lines.
+pub const LEGACY_IRQ_COUNT: u8 = 16;
+
+/// A function which handles an interrupt, it is given the vector of the interrupt.
+///
+/// Handlers run with interrupts disabled, so they must be short and must not wait for locks which
+/// are held by code running with interrupts enabled.
+pub type Handler = fn(vector: u8);
+
+/// Errors returned when registering a [`Handler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+    /// The vector is used by CPU exceptions or for spurious interrupts.
+    InvalidVector,
+    /// The IRQ line does not exist on this system.
+    InvalidIrq,
+    /// There is already a handler for the vector.
+    AlreadyRegistered,
+    /// The SubSystem has not set an [`InterruptController`] yet.
+    NoController,
+}
+
+/// The interrupt controller of a SubSystem, such as the 8259 PICs or the APICs on x86_64.
+pub trait InterruptController: Sync {
+    /// Returns the vector on which interrupts from the legacy IRQ line `irq` arrive.
+    fn vector_for_irq(&self, irq: u8) -> Option<u8>;
+
+    /// Starts delivering interrupts from the legacy IRQ line `irq` to `vector`.
+    fn enable_irq(&self, irq: u8, vector: u8);
+
+    /// Stops delivering interrupts from the legacy IRQ line `irq`.
+    fn disable_irq(&self, irq: u8);
+
+    /// Tells the controller that the interrupt on `vector` has been handled.
+    fn end_of_interrupt(&self, vector: u8);
+
+    /// Stops this CPU from taking interrupts, and returns whether it was taking them before.
+    fn disable_interrupts(&self) -> bool;
+
+    /// Lets this CPU take interrupts again.
+    fn enable_interrupts(&self);
+}
+
+static CONTROLLER: Once<&'static dyn InterruptController> = Once::new();
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_HANDLER: Mutex<Option<Handler>> = Mutex::new(None);
+
+/// The dispatch table, indexed by vector. Every entry has its own lock, so registering a handler
+/// never blocks interrupts on other vectors.
+static HANDLERS: [Mutex<Option<Handler>>; 256] = [NO_HANDLER; 256];
+
+/// Sets the interrupt controller used to route IRQs and acknowledge interrupts. This is called by
+/// the SubSystem while it initialises, only the first call has an effect.
+pub fn set_controller(controller: &'static dyn InterruptController) {
+    CONTROLLER.call_once(|| controller);
+}
+
+/// Returns the interrupt controller, or `None` if the SubSystem has not set one.
+pub fn controller() -> Option<&'static dyn InterruptController> {
+    CONTROLLER.get().copied()
+}
+
+/// Runs `f` with interrupts disabled on this CPU, then enables them again if they were enabled.
+///
+/// A lock which is also taken by interrupt handlers must be held with interrupts disabled,
+/// otherwise a handler which interrupts the holder waits for the lock forever. Before the
+/// SubSystem has set an [`InterruptController`] no IRQs are delivered, so `f` is just called.
+///
+/// ## Example
+/// ```rust
+/// irq::without_interrupts(|| {
+///     let mut queue = QUEUE.lock();
+///     // ...
+/// });
+/// ```
+///
+/// In this example a lock shared with an interrupt handler is held without the handler running.
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let Some(controller) = controller() else {
+        return f();
+    };
+    let enabled = controller.disable_interrupts();
+    let result = f();
+    if enabled {
+        controller.enable_interrupts();
+    }
+    result
+}
+
+/// Attaches `handler` to `vector`.
+pub fn register_vector(vector: u8, handler: Handler) -> Result<(), IrqError> {
+    if !(FIRST_VECTOR..=LAST_VECTOR).contains(&vector) {
+        return Err(IrqError::InvalidVector);
+    }
+
+    let mut slot = HANDLERS[vector as usize].lock();
+    if slot.is_some() {
+        return Err(IrqError::AlreadyRegistered);
+    }
+    *slot = Some(handler);
+    Ok(())
+}
+
+/// Removes the handler of `vector` and returns it.
+pub fn unregister_vector(vector: u8) -> Option<Handler> {
+    HANDLERS[vector as usize].lock().take()
+}
+
+/// Returns `true` if a handler is attached to `vector`.
+pub fn is_registered(vector: u8) -> bool {
+    HANDLERS[vector as usize].lock().is_some()
+}
+
+/// Attaches `handler` to the legacy IRQ line `irq` and unmasks the line. Returns the vector on which
+/// the interrupts arrive.
+pub fn register_irq(irq: u8, handler: Handler) -> Result<u8, IrqError> {
+    if irq >= LEGACY_IRQ_COUNT {
+        return Err(IrqError::InvalidIrq);
+    }
+    let controller = controller().ok_or(IrqError::NoController)?;
+    let vector = controller.vector_for_irq(irq).ok_or(IrqError::InvalidIrq)?;
+
+    register_vector(vector, handler)?;
+    controller.enable_irq(irq, vector);
+    Ok(vector)
+}
+
+/// Masks the legacy IRQ line `irq` and removes its handler.
+pub fn unregister_irq(irq: u8) -> Option<Handler> {
+    let controller = controller()?;
+    let vector = controller.vector_for_irq(irq)?;
+    controller.disable_irq(irq);
+    unregister_vector(vector)
+}
+
+/// Runs the handler registered for `vector`, then acknowledges the interrupt.
+///
+/// This is called by the SubSystem's interrupt entry points. If the handler is being registered
+/// or removed at the moment the interrupt arrives, the interrupt is acknowledged without calling
+/// it.
+pub fn dispatch(vector: u8) {
+    let handler = HANDLERS[vector as usize].try_lock().and_then(|slot| *slot);
+    if let Some(handler) = handler {
+        handler(vector);
+    }
+
+    if let Some(controller) = controller() {
+        controller.end_of_interrupt(vector);
+    }
+}
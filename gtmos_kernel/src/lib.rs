@@ -11,11 +11,20 @@
 #![feature(custom_test_frameworks)]
 #![test_runner(crate::test_runner)]
 #![reexport_test_harness_main = "test_main"]
+#![feature(alloc_error_handler)]
 
+extern crate alloc;
+
+pub mod acpi;
+pub mod devices;
 pub mod drivers;
 pub mod graphics;
+pub mod irq;
 pub mod platform;
 pub mod console;
+pub mod logger;
+pub mod memory;
+pub mod ring_buffer;
 
 use core::panic::PanicInfo;
 
@@ -54,6 +63,13 @@ pub fn test_panic_handler(info: &PanicInfo) -> ! {
 }
 
 
+/// Called when an allocation on the kernel heap fails, the panic handler then reports the failed
+/// allocation instead of the kernel silently hanging.
+#[alloc_error_handler]
+fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
+    panic!("allocation error: {:?}", layout)
+}
+
 #[cfg(test)]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
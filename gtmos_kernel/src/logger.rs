@@ -0,0 +1,349 @@
+//! The kernel logger, which receives the records of the [`log`] crate's macros.
+//!
+//! Every record is stamped with the time since boot, the CPU which logged it and its level, kept
+//! in a ring of the last [`DMESG_LENGTH`] records (which is read with [`dmesg`]), and passed to
+//! every [`Sink`], such as [`SERIAL`] and [`CONSOLE`].
+//!
+//! Which records are logged is chosen with `log=` on the kernel command line, followed by a list
+//! of directives separated by commas. A level on its own sets the level of every module, and
+//! `module=level` sets the level of a module and the modules inside it. The directive with the
+//! longest matching module is used, and records are logged at [`DEFAULT_LEVEL`] when there is no
+//! directive for them.
+//!
+//! ## Example
+//! ```rust
+//! logger::initialise("log=info,gtmos_kernel::drivers=debug,gtmos_kernel_x86_64::apic=off")?;
+//! logger::add_sink(&logger::SERIAL)?;
+//! log::debug!("Found {} PS/2 ports", ports);
+//! ```
+//!
+//! In this example the debug record is logged if it is made in a driver, and written to serial
+//! as `[    0.054925] CPU 0 DEBUG gtmos_kernel::drivers::i8042: Found 2 PS/2 ports`.
+//!
+//! ## See also:
+//! * [The `log` crate](https://docs.rs/log)
+//! * [dmesg (Wikipedia)](https://en.wikipedia.org/wiki/Dmesg)
+
+use core::fmt::{self, Write};
+use core::str::FromStr;
+use core::time::Duration;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use spin::{Mutex, Once};
+
+use crate::console::{self, terminals};
+use crate::irq;
+
+/// The number of records kept by [`dmesg`].
+pub const DMESG_LENGTH: usize = 256;
+
+/// The longest message kept for a record, in bytes. Longer messages are cut short.
+pub const MESSAGE_LENGTH: usize = 256;
+
+/// The longest module name kept for a record, in bytes.
+pub const TARGET_LENGTH: usize = 48;
+
+/// The most sinks which can be added with [`add_sink`].
+pub const MAX_SINKS: usize = 4;
+
+/// The most `module=level` directives read from the command line, any more are ignored.
+pub const MAX_FILTERS: usize = 16;
+
+/// The level of modules which are not given one on the command line.
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Gives the logger the time and the CPU number to stamp on records, which depend on the
+/// SubSystem.
+pub trait Clock: Sync {
+    /// The time since the kernel started.
+    fn uptime(&self) -> Duration;
+
+    /// The number of the CPU which is running the caller.
+    fn cpu(&self) -> u32;
+}
+
+/// Somewhere records are written to, such as serial or the console.
+///
+/// Sinks are called with interrupts enabled unless the record was logged with them disabled, so
+/// they must lock anything they share with interrupt handlers with
+/// [`irq::without_interrupts`].
+pub trait Sink: Sync {
+    fn write(&self, entry: &Entry);
+}
+
+/// Errors returned when setting up the logger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoggerError {
+    /// Another logger has already been given to the `log` crate.
+    AlreadyInitialised,
+    /// There are already [`MAX_SINKS`] sinks.
+    TooManySinks,
+}
+
+/// A string of up to `N` bytes, which cuts off anything written past its end.
+#[derive(Clone, Copy)]
+struct Text<const N: usize> {
+    bytes: [u8; N],
+    length: usize,
+}
+
+impl<const N: usize> Text<N> {
+    const fn new() -> Self {
+        Text { bytes: [0; N], length: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are written, so the bytes are always valid UTF-8.
+        core::str::from_utf8(&self.bytes[..self.length]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> fmt::Write for Text<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let length = c.len_utf8();
+            if self.length + length > N {
+                break;
+            }
+            c.encode_utf8(&mut self.bytes[self.length..]);
+            self.length += length;
+        }
+        Ok(())
+    }
+}
+
+/// A record kept by the logger.
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub uptime: Duration,
+    pub cpu: u32,
+    pub level: Level,
+    target: Text<TARGET_LENGTH>,
+    message: Text<MESSAGE_LENGTH>,
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry {
+        uptime: Duration::ZERO,
+        cpu: 0,
+        level: Level::Info,
+        target: Text::new(),
+        message: Text::new(),
+    };
+
+    /// The module which logged the record.
+    pub fn target(&self) -> &str {
+        self.target.as_str()
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{:5}.{:06}] CPU {} {:<5} {}: {}",
+            self.uptime.as_secs(),
+            self.uptime.subsec_micros(),
+            self.cpu,
+            self.level,
+            self.target(),
+            self.message()
+        )
+    }
+}
+
+/// A ring of the last `N` records.
+struct Dmesg<const N: usize> {
+    entries: [Entry; N],
+    /// The index the next entry is written to.
+    next: usize,
+    length: usize,
+}
+
+impl<const N: usize> Dmesg<N> {
+    const fn new() -> Self {
+        Dmesg { entries: [Entry::EMPTY; N], next: 0, length: 0 }
+    }
+
+    /// Adds `entry`, replacing the oldest entry once the ring is full.
+    fn push(&mut self, entry: Entry) {
+        self.entries[self.next] = entry;
+        self.next = (self.next + 1) % N;
+        self.length = (self.length + 1).min(N);
+    }
+
+    /// Returns the entries from the oldest to the newest.
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        let start = (self.next + N - self.length) % N;
+        (0..self.length).map(move |i| &self.entries[(start + i) % N])
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Filter {
+    module: &'static str,
+    level: LevelFilter,
+}
+
+/// The levels chosen on the command line.
+struct Filters {
+    default: LevelFilter,
+    modules: [Filter; MAX_FILTERS],
+    count: usize,
+}
+
+impl Filters {
+    /// Reads the `log=` option of `command_line`. Directives with an unknown level are ignored.
+    fn parse(command_line: &'static str) -> Self {
+        let mut filters = Filters {
+            default: DEFAULT_LEVEL,
+            modules: [Filter { module: "", level: DEFAULT_LEVEL }; MAX_FILTERS],
+            count: 0,
+        };
+        let directives = command_line.split_whitespace().filter_map(|option| option.strip_prefix("log="));
+        for directive in directives.flat_map(|directives| directives.split(',')) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    if let (Ok(level), true) = (LevelFilter::from_str(level), filters.count < MAX_FILTERS) {
+                        filters.modules[filters.count] = Filter { module, level };
+                        filters.count += 1;
+                    }
+                }
+                None => {
+                    if let Ok(level) = LevelFilter::from_str(directive) {
+                        filters.default = level;
+                    }
+                }
+            }
+        }
+        filters
+    }
+
+    /// Returns the level of the module `target`.
+    fn level(&self, target: &str) -> LevelFilter {
+        let matches = |module: &str| {
+            target.strip_prefix(module).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+        self.modules[..self.count]
+            .iter()
+            .filter(|filter| matches(filter.module))
+            .max_by_key(|filter| filter.module.len())
+            .map_or(self.default, |filter| filter.level)
+    }
+
+    /// The most detailed level of any module.
+    fn max_level(&self) -> LevelFilter {
+        self.modules[..self.count].iter().map(|filter| filter.level).fold(self.default, Ord::max)
+    }
+}
+
+struct KernelLogger;
+
+static LOGGER: KernelLogger = KernelLogger;
+static FILTERS: Once<Filters> = Once::new();
+static CLOCK: Once<&'static dyn Clock> = Once::new();
+static DMESG: Mutex<Dmesg<DMESG_LENGTH>> = Mutex::new(Dmesg::new());
+static SINKS: Mutex<[Option<&'static dyn Sink>; MAX_SINKS]> = Mutex::new([None; MAX_SINKS]);
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = FILTERS.get().map_or(DEFAULT_LEVEL, |filters| filters.level(metadata.target()));
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let clock = CLOCK.get();
+        let mut entry = Entry {
+            uptime: clock.map_or(Duration::ZERO, |clock| clock.uptime()),
+            cpu: clock.map_or(0, |clock| clock.cpu()),
+            level: record.level(),
+            ..Entry::EMPTY
+        };
+        let _ = entry.target.write_str(record.target());
+        let _ = entry.message.write_fmt(*record.args());
+
+        // The sinks are copied out, so a sink may log without waiting for the lock.
+        let sinks = irq::without_interrupts(|| {
+            DMESG.lock().push(entry);
+            *SINKS.lock()
+        });
+        for sink in sinks.iter().flatten() {
+            sink.write(&entry);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Makes the kernel logger the one used by the `log` crate, with the levels from the `log=` option
+/// of `command_line`.
+pub fn initialise(command_line: &'static str) -> Result<(), LoggerError> {
+    let filters = FILTERS.call_once(|| Filters::parse(command_line));
+    log::set_logger(&LOGGER).map_err(|_| LoggerError::AlreadyInitialised)?;
+    log::set_max_level(filters.max_level());
+    Ok(())
+}
+
+/// Sets the clock used to stamp records. This is called by the SubSystem while it initialises,
+/// only the first call has an effect. Records logged before it is set are stamped with 0.
+pub fn set_clock(clock: &'static dyn Clock) {
+    CLOCK.call_once(|| clock);
+}
+
+/// Adds a sink, which is given every record logged from now on.
+pub fn add_sink(sink: &'static dyn Sink) -> Result<(), LoggerError> {
+    irq::without_interrupts(|| {
+        let mut sinks = SINKS.lock();
+        let slot = sinks.iter_mut().find(|slot| slot.is_none()).ok_or(LoggerError::TooManySinks)?;
+        *slot = Some(sink);
+        Ok(())
+    })
+}
+
+/// Calls `f` with every record in the ring, from the oldest to the newest.
+///
+/// ## Example
+/// ```rust
+/// logger::dmesg(|entry| serial_println!("{}", entry));
+/// ```
+///
+/// In this example the kept records are written to serial again, such as for a `dmesg` command.
+pub fn dmesg(mut f: impl FnMut(&Entry)) {
+    irq::without_interrupts(|| DMESG.lock().iter().for_each(&mut f));
+}
+
+/// Writes records to serial.
+pub struct SerialSink;
+
+pub static SERIAL: SerialSink = SerialSink;
+
+impl Sink for SerialSink {
+    fn write(&self, entry: &Entry) {
+        crate::serial_println!("{}", entry);
+    }
+}
+
+/// Writes records to the kernel log terminal, coloured by their level.
+pub struct ConsoleSink;
+
+pub static CONSOLE: ConsoleSink = ConsoleSink;
+
+impl Sink for ConsoleSink {
+    fn write(&self, entry: &Entry) {
+        let colour = match entry.level {
+            Level::Error => "\x1b[31m",
+            Level::Warn => "\x1b[33m",
+            Level::Info => "",
+            Level::Debug | Level::Trace => "\x1b[90m",
+        };
+        console::with_terminal(terminals::KERNEL_LOG, |console| writeln!(console, "{}{}\x1b[0m", colour, entry));
+    }
+}
@@ -0,0 +1,229 @@
+use core::fmt;
+
+use spin::Mutex;
+
+use super::frame;
+use super::paging::{MapError, PageFlags, PageSize, VirtualMemory};
+
+/// The maximum number of regions which can be registered at the same time.
+///
+/// The region table lives in a static array rather than on the heap, so a page fault can be
+/// resolved even if it happens while the heap allocator is locked.
+pub const MAX_REGIONS: usize = 32;
+
+const PAGE_SIZE: u64 = PageSize::Size4KiB.bytes();
+
+/// A page fault, decoded from the architecture specific fault information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageFault {
+    /// The virtual address which was accessed.
+    pub address: u64,
+    /// The address of the instruction which caused the fault.
+    pub instruction_pointer: u64,
+    /// `true` if the page was present, so the fault was caused by a protection violation.
+    pub present: bool,
+    /// `true` if the access was a write, `false` if it was a read.
+    pub write: bool,
+    /// `true` if the access was made from user mode.
+    pub user: bool,
+    /// `true` if the access was an instruction fetch.
+    pub instruction_fetch: bool,
+}
+
+impl fmt::Display for PageFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = if self.user { "user" } else { "kernel" };
+        let access = if self.instruction_fetch {
+            "instruction fetch from"
+        } else if self.write {
+            "write to"
+        } else {
+            "read from"
+        };
+        let page = if self.present { "protected" } else { "non-present" };
+        write!(
+            f,
+            "{} {} {} page at {:#x} (instruction pointer {:#x})",
+            mode, access, page, self.address, self.instruction_pointer
+        )
+    }
+}
+
+/// Describes how the pages of a [`Region`] are backed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Memory which is backed by a zeroed frame the first time a page is touched.
+    Anonymous,
+    /// A stack which grows down. Pages are backed when they are touched, except for the lowest
+    /// page of the region which is a guard page, so touching it reports a stack overflow.
+    Stack,
+    /// Memory which must never be touched, for example the space between two stacks.
+    Guard,
+}
+
+/// A range of virtual memory which the page fault handler knows how to back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// The first address of the region, aligned to 4 KiB.
+    pub start: u64,
+    /// The address after the last byte of the region, aligned to 4 KiB.
+    pub end: u64,
+    pub kind: RegionKind,
+    /// The flags used when pages of the region are mapped.
+    pub flags: PageFlags,
+    /// A name used in fault reports.
+    pub name: &'static str,
+}
+
+impl Region {
+    fn contains(&self, address: u64) -> bool {
+        self.start <= address && address < self.end
+    }
+}
+
+/// Errors returned when registering a [`Region`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionError {
+    /// The start or end of the region is not aligned to 4 KiB, or the region is empty.
+    InvalidRange,
+    /// The region overlaps a region which is already registered.
+    Overlap,
+    /// There are already [`MAX_REGIONS`] regions registered.
+    TableFull,
+}
+
+/// Reasons why a page fault could not be resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultError {
+    /// The address is not part of any registered region.
+    NoRegion,
+    /// The address is in a guard page of the named region.
+    Guard(&'static str),
+    /// The page is present, or the access is not allowed by the flags of the named region.
+    ProtectionViolation(&'static str),
+    /// There is no free frame to back the page with.
+    OutOfMemory,
+    /// The page could not be mapped.
+    Map(MapError),
+}
+
+impl fmt::Display for FaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultError::NoRegion => write!(f, "the address is not part of any memory region"),
+            FaultError::Guard(name) => write!(f, "the address is in the guard page of {}", name),
+            FaultError::ProtectionViolation(name) => {
+                write!(f, "the access is not allowed in {}", name)
+            }
+            FaultError::OutOfMemory => write!(f, "there are no free frames left"),
+            FaultError::Map(error) => write!(f, "the page could not be mapped: {:?}", error),
+        }
+    }
+}
+
+static REGIONS: Mutex<[Option<Region>; MAX_REGIONS]> = Mutex::new([None; MAX_REGIONS]);
+
+/// Registers a region so that page faults inside of it are resolved by [`handle_page_fault`].
+///
+/// ## Example
+/// ```rust
+/// register_region(Region {
+///     start: 0x_6666_0000_0000,
+///     end: 0x_6666_0010_0000,
+///     kind: RegionKind::Anonymous,
+///     flags: PageFlags::WRITABLE | PageFlags::NO_EXECUTE,
+///     name: "scratch buffer",
+/// })?;
+/// ```
+///
+/// In this example 1 MiB of memory is reserved, but frames are only allocated for the pages which
+/// are actually touched.
+pub fn register_region(region: Region) -> Result<(), RegionError> {
+    if region.start >= region.end || (region.start | region.end) & (PAGE_SIZE - 1) != 0 {
+        return Err(RegionError::InvalidRange);
+    }
+
+    let mut regions = REGIONS.lock();
+    if regions
+        .iter()
+        .flatten()
+        .any(|other| region.start < other.end && other.start < region.end)
+    {
+        return Err(RegionError::Overlap);
+    }
+
+    let slot = regions.iter_mut().find(|slot| slot.is_none()).ok_or(RegionError::TableFull)?;
+    *slot = Some(region);
+    Ok(())
+}
+
+/// Removes the region starting at `start` from the region table and returns it. Pages which were
+/// already backed stay mapped.
+pub fn unregister_region(start: u64) -> Option<Region> {
+    REGIONS
+        .lock()
+        .iter_mut()
+        .find(|slot| slot.is_some_and(|region| region.start == start))?
+        .take()
+}
+
+/// Returns the region containing `address`, if there is one.
+pub fn region_at(address: u64) -> Option<Region> {
+    REGIONS.lock().iter().flatten().find(|region| region.contains(address)).copied()
+}
+
+/// Tries to resolve a page fault by backing the faulting page with a new zeroed frame.
+///
+/// This is called by the SubSystem's page fault handler. If an error is returned the access was
+/// invalid and the SubSystem should report it, together with the [`PageFault`].
+pub fn handle_page_fault(
+    fault: &PageFault,
+    virtual_memory: &mut dyn VirtualMemory,
+) -> Result<(), FaultError> {
+    let region = region_at(fault.address).ok_or(FaultError::NoRegion)?;
+    let page = fault.address & !(PAGE_SIZE - 1);
+
+    match region.kind {
+        RegionKind::Guard => return Err(FaultError::Guard(region.name)),
+        RegionKind::Stack if page == region.start => return Err(FaultError::Guard(region.name)),
+        RegionKind::Anonymous | RegionKind::Stack => {}
+    }
+
+    if fault.present
+        || (fault.write && !region.flags.contains(PageFlags::WRITABLE))
+        || (fault.user && !region.flags.contains(PageFlags::USER))
+        || (fault.instruction_fetch && region.flags.contains(PageFlags::NO_EXECUTE))
+    {
+        return Err(FaultError::ProtectionViolation(region.name));
+    }
+
+    let frame = frame::allocate_frame().ok_or(FaultError::OutOfMemory)?;
+    let result = back_page(virtual_memory, page, frame.start_address(), region.flags);
+    if result.is_err() {
+        let _ = frame::deallocate_frame(frame);
+    }
+    result
+}
+
+/// Maps `page` to the frame at `physical_address` and fills it with zeros. The page is mapped as
+/// writable while it is cleared, then it gets the flags of its region.
+fn back_page(
+    virtual_memory: &mut dyn VirtualMemory,
+    page: u64,
+    physical_address: u64,
+    flags: PageFlags,
+) -> Result<(), FaultError> {
+    virtual_memory
+        .map(page, physical_address, PageSize::Size4KiB, flags | PageFlags::WRITABLE)
+        .map_err(FaultError::Map)?;
+
+    // SAFETY: the page was just mapped as writable and is not used by anything else yet.
+    unsafe { core::ptr::write_bytes(page as *mut u8, 0, PAGE_SIZE as usize) };
+
+    if !flags.contains(PageFlags::WRITABLE) {
+        virtual_memory
+            .protect(page, PageSize::Size4KiB, flags)
+            .map_err(FaultError::Map)?;
+    }
+    Ok(())
+}
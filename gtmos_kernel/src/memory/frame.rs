@@ -0,0 +1,351 @@
+use core::slice;
+
+use spin::Mutex;
+
+/// The size of a physical frame in bytes.
+pub const FRAME_SIZE: u64 = 4096;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// The type of memory described by a [`MemoryRegion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRegionKind {
+    /// Free memory which the kernel may use.
+    Usable,
+    /// Memory used by the firmware, the bootloader or a device. It is never handed out.
+    Reserved,
+}
+
+/// A region of physical memory from `start` (inclusive) to `end` (exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    pub kind: MemoryRegionKind,
+}
+
+/// A 4 KiB frame of physical memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PhysFrame {
+    start: u64,
+}
+
+impl PhysFrame {
+    /// Returns the frame which contains the physical address `address`.
+    pub fn containing_address(address: u64) -> Self {
+        PhysFrame { start: align_down(address) }
+    }
+
+    /// Returns the physical address of the first byte of the frame.
+    pub fn start_address(&self) -> u64 {
+        self.start
+    }
+}
+
+/// Frame counters reported by a [`FrameAllocator`].
+///
+/// The counters cover every frame between the lowest and the highest usable address of the
+/// memory map, so holes in the memory map are counted as reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameStats {
+    /// The number of frames managed by the allocator.
+    pub total: usize,
+    /// The number of frames which can currently be allocated.
+    pub free: usize,
+    /// The number of frames which can never be allocated, either because the memory map does not
+    /// mark them as usable or because they hold the allocator's own bitmaps.
+    pub reserved: usize,
+}
+
+impl FrameStats {
+    /// The number of frames which are currently allocated.
+    pub fn used(&self) -> usize {
+        self.total - self.free - self.reserved
+    }
+}
+
+/// Errors returned by a [`FrameAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The memory map does not contain a usable region big enough to hold the allocator's bitmaps.
+    NoUsableMemory,
+    /// The frame lies outside of the memory managed by the allocator.
+    OutOfRange,
+    /// The frame is reserved, so it can not be freed.
+    Reserved,
+    /// The frame is not currently allocated.
+    NotAllocated,
+}
+
+/// A bitmap based allocator for physical frames.
+///
+/// The allocator keeps two bitmaps with one bit per frame: one marking frames which are in use
+/// (allocated or reserved) and one marking frames which are reserved. Both bitmaps are stored in
+/// the first usable region which is big enough to hold them, so no heap is needed.
+pub struct FrameAllocator {
+    used: &'static mut [u64],
+    reserved: &'static mut [u64],
+    /// The physical address of the first managed frame.
+    base: u64,
+    frame_count: usize,
+    free_count: usize,
+    reserved_count: usize,
+    /// The index of the bitmap word where the next search starts.
+    next_word: usize,
+}
+
+impl FrameAllocator {
+    /// Creates a frame allocator from a memory map.
+    ///
+    /// `physical_memory_offset` is the virtual address at which all physical memory is mapped, it
+    /// is used to access the bitmaps.
+    ///
+    /// ## Safety
+    /// The caller must make sure that all physical memory is mapped at `physical_memory_offset` and
+    /// that the regions marked as [`MemoryRegionKind::Usable`] are really unused. Only one
+    /// allocator may be created from the same memory map.
+    pub unsafe fn new<I>(regions: I, physical_memory_offset: u64) -> Result<Self, FrameError>
+    where
+        I: Iterator<Item = MemoryRegion> + Clone,
+    {
+        let usable = regions
+            .filter(|region| region.kind == MemoryRegionKind::Usable)
+            .map(|region| (align_up(region.start), align_down(region.end)))
+            .filter(|(start, end)| start < end);
+
+        let base = usable.clone().map(|(start, _)| start).min().ok_or(FrameError::NoUsableMemory)?;
+        let end = usable.clone().map(|(_, end)| end).max().ok_or(FrameError::NoUsableMemory)?;
+        let frame_count = ((end - base) / FRAME_SIZE) as usize;
+        let words = frame_count.div_ceil(BITS_PER_WORD);
+        let bitmap_size = align_up((2 * words * core::mem::size_of::<u64>()) as u64);
+
+        let bitmap_start = usable
+            .clone()
+            .find(|(start, end)| end - start >= bitmap_size)
+            .map(|(start, _)| start)
+            .ok_or(FrameError::NoUsableMemory)?;
+
+        let storage = slice::from_raw_parts_mut(
+            (physical_memory_offset + bitmap_start) as *mut u64,
+            2 * words,
+        );
+        // Everything starts out reserved, the usable regions are released below.
+        storage.fill(u64::MAX);
+        let (used, reserved) = storage.split_at_mut(words);
+
+        let mut allocator = FrameAllocator {
+            used,
+            reserved,
+            base,
+            frame_count,
+            free_count: 0,
+            reserved_count: frame_count,
+            next_word: 0,
+        };
+
+        for (start, end) in usable {
+            allocator.release_range(start, end);
+        }
+        allocator.reserve_range(bitmap_start, bitmap_start + bitmap_size);
+
+        Ok(allocator)
+    }
+
+    /// Allocates a single frame, returns `None` if there are no free frames left.
+    pub fn allocate(&mut self) -> Option<PhysFrame> {
+        let words = self.used.len();
+        for i in 0..words {
+            let word = (self.next_word + i) % words;
+            if self.used[word] != u64::MAX {
+                let bit = (!self.used[word]).trailing_zeros() as usize;
+                self.used[word] |= 1 << bit;
+                self.free_count -= 1;
+                self.next_word = word;
+                return Some(self.frame_at(word * BITS_PER_WORD + bit));
+            }
+        }
+        None
+    }
+
+    /// Allocates `count` physically contiguous frames and returns the first one. This is useful for
+    /// DMA buffers, which devices access without going through the page tables.
+    pub fn allocate_contiguous(&mut self, count: usize) -> Option<PhysFrame> {
+        if count == 0 {
+            return None;
+        }
+
+        let mut run_start = 0;
+        let mut run_length = 0;
+        for index in 0..self.frame_count {
+            if self.is_set(index, false) {
+                run_length = 0;
+                run_start = index + 1;
+                continue;
+            }
+
+            run_length += 1;
+            if run_length == count {
+                for frame in run_start..run_start + count {
+                    self.mark(frame, false);
+                }
+                self.free_count -= count;
+                return Some(self.frame_at(run_start));
+            }
+        }
+        None
+    }
+
+    /// Frees a frame which was returned by [`FrameAllocator::allocate`] or
+    /// [`FrameAllocator::allocate_contiguous`].
+    pub fn deallocate(&mut self, frame: PhysFrame) -> Result<(), FrameError> {
+        let index = self.index_of(frame).ok_or(FrameError::OutOfRange)?;
+        if self.is_set(index, true) {
+            return Err(FrameError::Reserved);
+        }
+        if !self.is_set(index, false) {
+            return Err(FrameError::NotAllocated);
+        }
+
+        let word = index_word(index);
+        self.used[word] &= !index_mask(index);
+        self.free_count += 1;
+        if word < self.next_word {
+            self.next_word = word;
+        }
+        Ok(())
+    }
+
+    /// Marks all frames overlapping the physical range from `start` to `end` as reserved, so they
+    /// are never handed out. Frames outside of the managed memory are ignored.
+    pub fn reserve_range(&mut self, start: u64, end: u64) {
+        let mut address = align_down(start);
+        while address < end {
+            if let Some(index) = self.index_of(PhysFrame { start: address }) {
+                if !self.is_set(index, true) {
+                    if !self.is_set(index, false) {
+                        self.free_count -= 1;
+                    }
+                    self.mark(index, true);
+                    self.reserved_count += 1;
+                }
+            }
+            address += FRAME_SIZE;
+        }
+    }
+
+    /// Returns the current frame counters.
+    pub fn stats(&self) -> FrameStats {
+        FrameStats {
+            total: self.frame_count,
+            free: self.free_count,
+            reserved: self.reserved_count,
+        }
+    }
+
+    fn release_range(&mut self, start: u64, end: u64) {
+        let mut address = start;
+        while address < end {
+            if let Some(index) = self.index_of(PhysFrame { start: address }) {
+                if self.is_set(index, true) {
+                    let (word, mask) = (index_word(index), index_mask(index));
+                    self.used[word] &= !mask;
+                    self.reserved[word] &= !mask;
+                    self.reserved_count -= 1;
+                    self.free_count += 1;
+                }
+            }
+            address += FRAME_SIZE;
+        }
+    }
+
+    fn is_set(&self, index: usize, reserved: bool) -> bool {
+        let bitmap = if reserved { &self.reserved } else { &self.used };
+        bitmap[index_word(index)] & index_mask(index) != 0
+    }
+
+    fn mark(&mut self, index: usize, reserved: bool) {
+        let (word, mask) = (index_word(index), index_mask(index));
+        self.used[word] |= mask;
+        if reserved {
+            self.reserved[word] |= mask;
+        }
+    }
+
+    fn index_of(&self, frame: PhysFrame) -> Option<usize> {
+        let index = (frame.start.checked_sub(self.base)? / FRAME_SIZE) as usize;
+        if index < self.frame_count {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    fn frame_at(&self, index: usize) -> PhysFrame {
+        PhysFrame { start: self.base + index as u64 * FRAME_SIZE }
+    }
+}
+
+#[inline]
+fn index_word(index: usize) -> usize {
+    index / BITS_PER_WORD
+}
+
+#[inline]
+fn index_mask(index: usize) -> u64 {
+    1 << (index % BITS_PER_WORD)
+}
+
+#[inline]
+fn align_down(address: u64) -> u64 {
+    address & !(FRAME_SIZE - 1)
+}
+
+#[inline]
+fn align_up(address: u64) -> u64 {
+    align_down(address + FRAME_SIZE - 1)
+}
+
+/// The kernel's global frame allocator, it is empty until [`initialise`] is called.
+static FRAME_ALLOCATOR: Mutex<Option<FrameAllocator>> = Mutex::new(None);
+
+/// Creates the global frame allocator from the bootloader's memory map.
+///
+/// ## Safety
+/// See [`FrameAllocator::new`]. This function must only be called once.
+pub unsafe fn initialise<I>(regions: I, physical_memory_offset: u64) -> Result<FrameStats, FrameError>
+where
+    I: Iterator<Item = MemoryRegion> + Clone,
+{
+    let allocator = FrameAllocator::new(regions, physical_memory_offset)?;
+    let stats = allocator.stats();
+    *FRAME_ALLOCATOR.lock() = Some(allocator);
+    Ok(stats)
+}
+
+/// Allocates a frame from the global frame allocator.
+pub fn allocate_frame() -> Option<PhysFrame> {
+    FRAME_ALLOCATOR.lock().as_mut()?.allocate()
+}
+
+/// Allocates `count` physically contiguous frames from the global frame allocator.
+pub fn allocate_contiguous_frames(count: usize) -> Option<PhysFrame> {
+    FRAME_ALLOCATOR.lock().as_mut()?.allocate_contiguous(count)
+}
+
+/// Frees a frame which was allocated from the global frame allocator.
+pub fn deallocate_frame(frame: PhysFrame) -> Result<(), FrameError> {
+    FRAME_ALLOCATOR
+        .lock()
+        .as_mut()
+        .ok_or(FrameError::OutOfRange)?
+        .deallocate(frame)
+}
+
+/// Returns the counters of the global frame allocator, or all zeros before it is initialised.
+pub fn frame_stats() -> FrameStats {
+    FRAME_ALLOCATOR
+        .lock()
+        .as_ref()
+        .map(FrameAllocator::stats)
+        .unwrap_or_default()
+}
@@ -0,0 +1,55 @@
+use linked_list_allocator::LockedHeap;
+
+use super::frame;
+use super::paging::{MapError, PageFlags, PageSize, VirtualMemory};
+
+/// The virtual address at which the kernel heap starts.
+pub const HEAP_START: usize = 0x_4444_4444_0000;
+
+/// The size of the kernel heap in bytes (16 MiB).
+pub const HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+/// The allocator used by `alloc` types such as [`alloc::vec::Vec`] and [`alloc::boxed::Box`]. It is
+/// empty, so every allocation fails, until [`initialise`] is called.
+#[global_allocator]
+static ALLOCATOR: LockedHeap = LockedHeap::empty();
+
+/// Usage counters of the kernel heap, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    pub size: usize,
+    pub used: usize,
+    pub free: usize,
+}
+
+/// Maps every page of the kernel heap to a newly allocated frame, then hands the heap to the
+/// global allocator.
+///
+/// ## Safety
+/// The frame allocator must be initialised, the heap region must not be used for anything else and
+/// this function must only be called once.
+pub unsafe fn initialise(virtual_memory: &mut dyn VirtualMemory) -> Result<(), MapError> {
+    let page_size = PageSize::Size4KiB.bytes() as usize;
+    for page in (HEAP_START..HEAP_START + HEAP_SIZE).step_by(page_size) {
+        let frame = frame::allocate_frame().ok_or(MapError::FrameAllocationFailed)?;
+        virtual_memory.map(
+            page as u64,
+            frame.start_address(),
+            PageSize::Size4KiB,
+            PageFlags::WRITABLE | PageFlags::NO_EXECUTE,
+        )?;
+    }
+
+    ALLOCATOR.lock().init(HEAP_START as *mut u8, HEAP_SIZE);
+    Ok(())
+}
+
+/// Returns the usage counters of the kernel heap.
+pub fn heap_stats() -> HeapStats {
+    let heap = ALLOCATOR.lock();
+    HeapStats {
+        size: heap.size(),
+        used: heap.used(),
+        free: heap.free(),
+    }
+}
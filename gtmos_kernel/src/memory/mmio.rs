@@ -0,0 +1,49 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::paging::{MapError, PageFlags, PageSize, VirtualMemory};
+
+/// The virtual address at which the MMIO window starts.
+pub const MMIO_START: u64 = 0x_7777_0000_0000;
+
+/// The size of the MMIO window in bytes (1 GiB).
+pub const MMIO_SIZE: u64 = 1024 * 1024 * 1024;
+
+const PAGE_SIZE: u64 = PageSize::Size4KiB.bytes();
+
+/// The next unused address in the MMIO window. Mappings are never removed, devices keep their
+/// registers mapped for as long as the kernel runs.
+static NEXT: AtomicU64 = AtomicU64::new(MMIO_START);
+
+/// Maps `size` bytes of device registers starting at `physical_address` into the MMIO window and
+/// returns the virtual address of `physical_address`.
+///
+/// The pages are mapped writable, non-executable and with caching disabled, so every access
+/// reaches the device.
+///
+/// ## Example
+/// ```rust
+/// let local_apic = mmio::map(virtual_memory, 0xFEE0_0000, 4096)?;
+/// ```
+///
+/// In this example the registers of the local APIC are mapped, `local_apic` can then be used with
+/// volatile reads and writes.
+pub fn map(
+    virtual_memory: &mut dyn VirtualMemory,
+    physical_address: u64,
+    size: u64,
+) -> Result<u64, MapError> {
+    let first_frame = physical_address & !(PAGE_SIZE - 1);
+    let offset = physical_address - first_frame;
+    let length = (offset + size).div_ceil(PAGE_SIZE) * PAGE_SIZE;
+
+    let start = NEXT.fetch_add(length, Ordering::Relaxed);
+    if start + length > MMIO_START + MMIO_SIZE {
+        return Err(MapError::OutOfAddressSpace);
+    }
+
+    let flags = PageFlags::WRITABLE | PageFlags::NO_EXECUTE | PageFlags::CACHE_DISABLE;
+    for page in (0..length).step_by(PAGE_SIZE as usize) {
+        virtual_memory.map(start + page, first_frame + page, PageSize::Size4KiB, flags)?;
+    }
+    Ok(start + offset)
+}
@@ -0,0 +1,44 @@
+//! Module containing the kernel's memory management.
+//!
+//! Physical memory is handed out in 4 KiB frames by the [frame allocator](frame). The memory map
+//! used to build it comes from the bootloader, so the SubSystem is responsible for converting it
+//! into [`frame::MemoryRegion`]s before handing it to the kernel.
+//!
+//! Virtual memory is managed through the [`paging::VirtualMemory`] trait, which every SubSystem
+//! implements on top of its own page tables. The [kernel heap](heap) is mapped through it at boot,
+//! after which the `alloc` crate (`Vec`, `Box`, `String`, `BTreeMap`, ...) can be used everywhere
+//! in the kernel.
+//!
+//! Page faults are resolved through the [region table](fault), which backs anonymous memory and
+//! stacks with frames the first time they are touched. Device registers are mapped uncached into
+//! the [MMIO window](mmio).
+//!
+//! ## See also:
+//! * [Page Frame Allocation (OsDev.org)](https://wiki.osdev.org/Page_Frame_Allocation)
+//! * [Paging (OsDev.org)](https://wiki.osdev.org/Paging)
+//! * [Heap (OsDev.org)](https://wiki.osdev.org/Heap)
+
+pub mod fault;
+pub mod frame;
+pub mod heap;
+pub mod mmio;
+pub mod paging;
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The virtual address at which all physical memory is mapped.
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+/// Tells the kernel where the SubSystem has mapped all physical memory.
+pub fn set_physical_memory_offset(offset: u64) {
+    PHYSICAL_MEMORY_OFFSET.store(offset, Ordering::Relaxed);
+}
+
+/// Returns the virtual address through which the physical address `physical_address` can be
+/// accessed.
+///
+/// The mapping is cached, so it should be used for tables in normal memory (like the ACPI tables)
+/// and not for device registers, which should be mapped with [`mmio::map`] instead.
+pub fn physical_to_virtual(physical_address: u64) -> u64 {
+    PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed) + physical_address
+}
@@ -0,0 +1,135 @@
+use core::ops::{BitOr, BitOrAssign};
+
+/// The size of a page which can be mapped through [`VirtualMemory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    /// A normal 4 KiB page.
+    Size4KiB,
+    /// A 2 MiB huge page.
+    Size2MiB,
+    /// A 1 GiB huge page, which needs CPU support on x86_64.
+    Size1GiB,
+}
+
+impl PageSize {
+    /// Returns the size of the page in bytes.
+    pub const fn bytes(self) -> u64 {
+        match self {
+            PageSize::Size4KiB => 4096,
+            PageSize::Size2MiB => 2 * 1024 * 1024,
+            PageSize::Size1GiB => 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Flags describing how a mapped page may be accessed.
+///
+/// A page mapped without any flags is present, read-only, executable, cached and only accessible
+/// by the kernel. Flags can be combined with `|`.
+///
+/// ## Example
+/// ```rust
+/// let flags = PageFlags::WRITABLE | PageFlags::NO_EXECUTE;
+/// ```
+///
+/// In this example the flags describe a page for data, such as the kernel heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PageFlags(u8);
+
+impl PageFlags {
+    /// The page can be written to.
+    pub const WRITABLE: PageFlags = PageFlags(1 << 0);
+    /// Code in the page can not be executed.
+    pub const NO_EXECUTE: PageFlags = PageFlags(1 << 1);
+    /// The page can be accessed from user mode.
+    pub const USER: PageFlags = PageFlags(1 << 2);
+    /// Accesses to the page bypass the CPU caches, this is needed for memory mapped I/O.
+    pub const CACHE_DISABLE: PageFlags = PageFlags(1 << 3);
+
+    /// Returns flags with nothing set.
+    pub const fn empty() -> Self {
+        PageFlags(0)
+    }
+
+    /// Returns `true` if all flags in `other` are also set in `self`.
+    pub const fn contains(self, other: PageFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PageFlags {
+    type Output = PageFlags;
+
+    fn bitor(self, rhs: PageFlags) -> PageFlags {
+        PageFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PageFlags {
+    fn bitor_assign(&mut self, rhs: PageFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Errors returned by a [`VirtualMemory`] implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// The virtual or physical address is not aligned to the page size.
+    NotAligned,
+    /// The page is already mapped.
+    AlreadyMapped,
+    /// The page is not mapped.
+    NotMapped,
+    /// The page is part of a mapping with a different page size.
+    SizeMismatch,
+    /// A frame for a new page table could not be allocated.
+    FrameAllocationFailed,
+    /// There is no unused virtual address space left for the mapping.
+    OutOfAddressSpace,
+    /// The page tables have not been set up yet.
+    Uninitialised,
+}
+
+/// The result of translating a virtual address with [`VirtualMemory::translate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Translation {
+    /// The physical address the virtual address translates to.
+    pub physical_address: u64,
+    /// The size of the page containing the virtual address.
+    pub size: PageSize,
+    /// The flags of the page containing the virtual address.
+    pub flags: PageFlags,
+}
+
+/// An architecture independent interface to the active page tables.
+///
+/// Every SubSystem implements this trait on top of its own page table format, so the kernel can map
+/// memory mapped I/O, user pages and guard pages without knowing about the architecture.
+pub trait VirtualMemory {
+    /// Maps the page starting at `virtual_address` to the frame starting at `physical_address`.
+    /// Both addresses must be aligned to `size`.
+    fn map(
+        &mut self,
+        virtual_address: u64,
+        physical_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError>;
+
+    /// Unmaps the page starting at `virtual_address` and returns the physical address it was mapped
+    /// to. The frame itself is not freed.
+    fn unmap(&mut self, virtual_address: u64, size: PageSize) -> Result<u64, MapError>;
+
+    /// Changes the flags of the page starting at `virtual_address`.
+    fn protect(
+        &mut self,
+        virtual_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError>;
+
+    /// Translates a virtual address into a physical address, returns `None` if the address is not
+    /// mapped.
+    fn translate(&self, virtual_address: u64) -> Option<Translation>;
+}
+
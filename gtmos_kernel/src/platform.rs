@@ -1,12 +1,37 @@
-use core::{cell::RefCell, mem};
-use crate::console::Console;
-pub trait SubSystem {
+//! Module containing the kernel's access to the SubSystem, the platform dependant API it runs on.
+//!
+//! The SubSystem is given to [`set_platform`] once, while the kernel starts. It is then kept for as
+//! long as the kernel runs, and [`with_sub_system`] lends it out behind a lock, so it may be used
+//! any number of times, from any CPU and from interrupt handlers.
+//!
+//! ## Example
+//! ```rust
+//! let platform = Platform::new(X86_64SubSystem::new());
+//! unsafe { memory::initialise(boot_info) };
+//! platform::set_platform(platform);
+//!
+//! platform::with_sub_system(|sub_system| sub_system.virtual_memory().translate(address));
+//! ```
+//!
+//! In this example the SubSystem is set up, then kept once the heap is ready, and used to look up
+//! the physical address of `address`.
+//!
+//! ## See also:
+//! * [Platform Abstraction](./../docs/design/kernel/platform-abstraction/index.md)
+
+use alloc::boxed::Box;
+use spin::{Mutex, Once};
+
+use crate::irq;
+use crate::memory::paging::VirtualMemory;
+
+pub trait SubSystem: Send {
+    /// Sets up the CPU, and registers the devices of the platform with
+    /// [`devices::register`](crate::devices::register).
     fn initialise(&self);
     fn halt(&self);
-    fn write(&mut self, dest: &str, data: &str);
-    // fn read(&self, dest: &str) -> &str;
-    fn set_console(&mut self, console: Option<Console<'static>>);
-    fn get_console(&mut self) -> Option<&mut Console<'static>>;
+    /// Gives access to the active page tables.
+    fn virtual_memory(&mut self) -> &mut dyn VirtualMemory;
 }
 
 pub struct Platform<T: SubSystem> {
@@ -24,35 +49,57 @@ impl<T: SubSystem> Platform<T> {
     }
 }
 
-// Global static reference to the platform
-static mut PLATFORM: Option<RefCell<Option<&'static mut dyn SubSystem>>> = None;
+/// Errors returned by [`set_platform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformError {
+    /// A SubSystem has already been set, it is kept until the kernel stops.
+    AlreadySet,
+}
+
+/// The SubSystem given to [`set_platform`], which is leaked so it lives as long as the kernel.
+static SUB_SYSTEM: Once<Mutex<&'static mut dyn SubSystem>> = Once::new();
 
-// Function to set the static reference in the main crate
-#[allow(mutable_transmutes)]
-pub fn set_platform<T: SubSystem>(platform: Platform<T>) {
-    unsafe {
-        if PLATFORM.is_none() {
-            let static_ref: &'static mut dyn SubSystem =
-                mem::transmute(&platform.sub_system as &dyn SubSystem);
-            PLATFORM = Some(RefCell::new(Some(static_ref)));
-        }
+/// Keeps the SubSystem of `platform` for the rest of the time the kernel runs, so it can be used
+/// with [`with_sub_system`]. Only the first call has an effect, later calls return
+/// [`PlatformError::AlreadySet`].
+///
+/// The SubSystem is moved onto the heap, so the kernel heap must be set up first.
+pub fn set_platform<T: SubSystem + 'static>(platform: Platform<T>) -> Result<(), PlatformError> {
+    let mut platform = Some(platform);
+    SUB_SYSTEM.call_once(|| {
+        let platform = platform.take().expect("call_once runs this once");
+        Mutex::new(Box::leak(Box::new(platform.sub_system)))
+    });
+    match platform {
+        Some(_) => Err(PlatformError::AlreadySet),
+        None => Ok(()),
     }
 }
 
-pub fn get_sub_system() -> Option<&'static mut dyn SubSystem> {
-    unsafe {
-        let r: Option<&'static mut dyn SubSystem> = PLATFORM
-            .as_mut()
-            .and_then(|p| {
-                // Take out the inner value and replace it with None temporarily
-                let inner_ref = p.borrow_mut().take()?;
+/// Returns `true` once a SubSystem has been given to [`set_platform`].
+pub fn has_platform() -> bool {
+    SUB_SYSTEM.is_completed()
+}
 
-                // Convert the reference to 'static
-                let static_ref: &'static mut (dyn SubSystem + 'static) = mem::transmute(inner_ref);
+/// Calls `f` with the SubSystem, or returns `None` if [`set_platform`] has not been called yet.
+///
+/// The SubSystem is locked with interrupts disabled, so interrupt handlers may use it as well. `f`
+/// should be short, as interrupts wait until it returns.
+pub fn with_sub_system<R>(f: impl FnOnce(&mut dyn SubSystem) -> R) -> Option<R> {
+    let sub_system = SUB_SYSTEM.get()?;
+    Some(irq::without_interrupts(|| f(&mut **sub_system.lock())))
+}
 
-                // Create a reference with 'static lifetime
-                Some(static_ref)
-            });
-        return r;
+/// Stops the kernel with [`SubSystem::halt`]. If no SubSystem has been set the CPU waits in a loop
+/// instead.
+///
+/// The SubSystem stays locked, but interrupts are still taken while halted, so interrupt handlers
+/// must not call [`with_sub_system`] after this.
+pub fn halt() -> ! {
+    if let Some(sub_system) = SUB_SYSTEM.get() {
+        sub_system.lock().halt();
+    }
+    loop {
+        core::hint::spin_loop();
     }
 }
@@ -0,0 +1,100 @@
+//! Module containing a lock-free ring buffer, used to pass data from interrupt handlers to the rest
+//! of the kernel.
+//!
+//! ## See also:
+//! * [Circular buffer (Wikipedia)](https://en.wikipedia.org/wiki/Circular_buffer)
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A fixed size queue which one producer and one consumer can use at the same time without a lock.
+///
+/// The producer (usually an interrupt handler) calls [`RingBuffer::push`] and the consumer calls
+/// [`RingBuffer::pop`]. Neither ever waits, so an interrupt handler can not deadlock on the queue.
+/// Only one producer and one consumer may use the queue at a time.
+///
+/// ## Example
+/// ```rust
+/// static SCANCODES: RingBuffer<u8, 64> = RingBuffer::new();
+///
+/// // In the interrupt handler:
+/// let _ = SCANCODES.push(scancode);
+///
+/// // Somewhere else:
+/// while let Some(scancode) = SCANCODES.pop() {
+///     // ...
+/// }
+/// ```
+///
+/// In this example an interrupt handler passes scancodes to the code which decodes them. Scancodes
+/// which arrive while the queue is full are dropped.
+pub struct RingBuffer<T: Copy, const N: usize> {
+    buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// The number of values ever popped, the next value is read from `head % N`.
+    head: AtomicUsize,
+    /// The number of values ever pushed, the next value is written to `tail % N`.
+    tail: AtomicUsize,
+}
+
+// SAFETY: the producer only writes slots which the consumer has released, and the consumer only
+// reads slots which the producer has published, the atomics order these accesses.
+unsafe impl<T: Copy + Send, const N: usize> Sync for RingBuffer<T, N> {}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    pub const fn new() -> Self {
+        RingBuffer {
+            buffer: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds `value` to the end of the queue. If the queue is full `value` is handed back.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= N {
+            return Err(value);
+        }
+
+        // SAFETY: the slot is not readable by the consumer until `tail` is published below.
+        unsafe { (*self.buffer.get())[tail % N].write(value) };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Removes the value at the front of the queue and returns it.
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        // SAFETY: the producer published this slot and does not write it again until `head` moves.
+        let value = unsafe { (*self.buffer.get())[head % N].assume_init() };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns the number of values in the queue.
+    pub fn len(&self) -> usize {
+        self.tail.load(Ordering::Acquire).wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of values the queue can hold.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Copy, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
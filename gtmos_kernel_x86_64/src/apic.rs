@@ -0,0 +1,260 @@
+//! Support for the local APIC and the I/O APICs, which replace the legacy 8259 PICs.
+//!
+//! Every processor has a local APIC which receives interrupts and has its own timer. Interrupts
+//! from devices arrive at an I/O APIC, which forwards them to a local APIC through its redirection
+//! table. Both are found through the ACPI MADT.
+//!
+//! ## See also:
+//! * [APIC (OsDev.org)](https://wiki.osdev.org/APIC)
+//! * [IOAPIC (OsDev.org)](https://wiki.osdev.org/IOAPIC)
+//! * [APIC timer (OsDev.org)](https://wiki.osdev.org/APIC_timer)
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use gtmos_kernel::acpi::madt::{Madt, MadtEntry};
+use gtmos_kernel::memory::mmio;
+use gtmos_kernel::memory::paging::{MapError, VirtualMemory};
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+/// The vector of spurious interrupts from the local APIC. They must not be acknowledged.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// How many times per second the local APIC timer fires.
+pub const TIMER_FREQUENCY: u32 = 100;
+
+/// The frequency of the 8253/8254 PIT, which is used to measure the speed of the local APIC timer.
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+// Local APIC registers, as offsets from its base address.
+const REGISTER_ID: u64 = 0x20;
+const REGISTER_TASK_PRIORITY: u64 = 0x80;
+const REGISTER_END_OF_INTERRUPT: u64 = 0xB0;
+const REGISTER_SPURIOUS: u64 = 0xF0;
+const REGISTER_LVT_TIMER: u64 = 0x320;
+const REGISTER_TIMER_INITIAL_COUNT: u64 = 0x380;
+const REGISTER_TIMER_CURRENT_COUNT: u64 = 0x390;
+const REGISTER_TIMER_DIVIDE: u64 = 0x3E0;
+
+const SPURIOUS_APIC_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+const TIMER_DIVIDE_BY_16: u32 = 0b0011;
+
+// I/O APIC registers.
+const IO_APIC_VERSION: u32 = 0x01;
+const IO_APIC_REDIRECTION_TABLE: u32 = 0x10;
+
+const REDIRECTION_ACTIVE_LOW: u32 = 1 << 13;
+const REDIRECTION_LEVEL_TRIGGERED: u32 = 1 << 15;
+const REDIRECTION_MASKED: u32 = 1 << 16;
+
+/// The virtual address of the local APIC's registers, or 0 while the 8259 PICs are used.
+static LOCAL_APIC: AtomicU64 = AtomicU64::new(0);
+
+static IO_APICS: Mutex<IoApics> = Mutex::new(IoApics {
+    io_apics: Vec::new(),
+    overrides: [None; 16],
+});
+
+/// Returns `true` if interrupts are handled by the APICs rather than the 8259 PICs.
+pub fn is_enabled() -> bool {
+    LOCAL_APIC.load(Ordering::Relaxed) != 0
+}
+
+/// Returns the ID of the current processor's local APIC.
+pub fn local_apic_id() -> u8 {
+    (unsafe { read_local_apic(REGISTER_ID) } >> 24) as u8
+}
+
+/// Tells the local APIC that the current interrupt has been handled.
+pub fn end_of_interrupt() {
+    unsafe { write_local_apic(REGISTER_END_OF_INTERRUPT, 0) };
+}
+
+unsafe fn read_local_apic(register: u64) -> u32 {
+    let base = LOCAL_APIC.load(Ordering::Relaxed);
+    core::ptr::read_volatile((base + register) as *const u32)
+}
+
+unsafe fn write_local_apic(register: u64, value: u32) {
+    let base = LOCAL_APIC.load(Ordering::Relaxed);
+    core::ptr::write_volatile((base + register) as *mut u32, value);
+}
+
+/// Maps and enables the local APIC and the I/O APICs listed in the MADT, then starts the local
+/// APIC timer on `timer_vector`.
+///
+/// All I/O APIC inputs start out masked, use [`route_irq`] to connect an ISA IRQ to a vector. The
+/// caller must mask the 8259 PICs.
+///
+/// ## Safety
+/// This function must only be called once, with interrupts disabled.
+pub unsafe fn initialise(
+    madt: &Madt,
+    virtual_memory: &mut dyn VirtualMemory,
+    timer_vector: u8,
+) -> Result<(), MapError> {
+    // Map everything first, so nothing has been changed if a mapping fails.
+    let local_apic = mmio::map(virtual_memory, madt.local_apic_physical_address(), 4096)?;
+    let mut io_apics = IO_APICS.lock();
+    for entry in madt.entries() {
+        match entry {
+            MadtEntry::IoApic { address, gsi_base, .. } => {
+                let base = mmio::map(virtual_memory, address as u64, 4096)?;
+                let mut io_apic = IoApic { base, gsi_base, redirection_entries: 0 };
+                io_apic.redirection_entries = ((io_apic.read(IO_APIC_VERSION) >> 16) & 0xFF) + 1;
+                io_apics.io_apics.push(io_apic);
+            }
+            MadtEntry::InterruptSourceOverride { bus: 0, source, gsi, flags } if source < 16 => {
+                io_apics.overrides[source as usize] = Some(IsaOverride {
+                    gsi,
+                    // Polarity is in bits 0-1 and the trigger mode in bits 2-3, 0b11 means active
+                    // low and level triggered. ISA interrupts default to active high and edge.
+                    active_low: flags & 0b11 == 0b11,
+                    level_triggered: (flags >> 2) & 0b11 == 0b11,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for io_apic in io_apics.io_apics.iter() {
+        for index in 0..io_apic.redirection_entries {
+            io_apic.write_redirection(index, REDIRECTION_MASKED, 0);
+        }
+    }
+
+    LOCAL_APIC.store(local_apic, Ordering::Relaxed);
+    write_local_apic(REGISTER_TASK_PRIORITY, 0);
+    write_local_apic(REGISTER_SPURIOUS, SPURIOUS_APIC_ENABLE | SPURIOUS_VECTOR as u32);
+
+    let ticks_per_10ms = calibrate_timer();
+    write_local_apic(REGISTER_TIMER_DIVIDE, TIMER_DIVIDE_BY_16);
+    write_local_apic(REGISTER_LVT_TIMER, LVT_TIMER_PERIODIC | timer_vector as u32);
+    write_local_apic(REGISTER_TIMER_INITIAL_COUNT, ticks_per_10ms * 100 / TIMER_FREQUENCY);
+    Ok(())
+}
+
+/// Measures how many times the local APIC timer counts down in 10 ms (with a divider of 16) by
+/// waiting for channel 2 of the PIT, which runs at a known frequency.
+unsafe fn calibrate_timer() -> u32 {
+    let mut gate: Port<u8> = Port::new(0x61);
+    let mut command: Port<u8> = Port::new(0x43);
+    let mut channel_2: Port<u8> = Port::new(0x42);
+
+    // Enable the gate of channel 2, but keep the PC speaker switched off.
+    let value = gate.read();
+    gate.write((value & !0b10) | 0b01);
+
+    // Channel 2, low byte then high byte, mode 0 (interrupt on terminal count).
+    command.write(0b1011_0000);
+    let count = PIT_FREQUENCY / 100;
+    channel_2.write(count as u8);
+    channel_2.write((count >> 8) as u8);
+
+    write_local_apic(REGISTER_TIMER_DIVIDE, TIMER_DIVIDE_BY_16);
+    write_local_apic(REGISTER_LVT_TIMER, LVT_MASKED);
+    write_local_apic(REGISTER_TIMER_INITIAL_COUNT, u32::MAX);
+
+    // The output of channel 2 goes high once the count reaches zero.
+    while gate.read() & 0b10_0000 == 0 {
+        core::hint::spin_loop();
+    }
+
+    let elapsed = u32::MAX - read_local_apic(REGISTER_TIMER_CURRENT_COUNT);
+    write_local_apic(REGISTER_TIMER_INITIAL_COUNT, 0);
+    gate.write(value);
+    elapsed
+}
+
+/// Connects the ISA IRQ `irq` to `vector` on the current processor, following the interrupt source
+/// overrides from the MADT. Returns `false` if no I/O APIC handles the IRQ.
+pub fn route_irq(irq: u8, vector: u8) -> bool {
+    let io_apics = IO_APICS.lock();
+    let (gsi, mut flags) = io_apics.gsi_for(irq);
+    flags |= vector as u32;
+    let destination = (local_apic_id() as u32) << 24;
+
+    match io_apics.io_apic_for(gsi) {
+        Some(io_apic) => {
+            unsafe { io_apic.write_redirection(gsi - io_apic.gsi_base, flags, destination) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Disconnects the ISA IRQ `irq`, so its interrupts are no longer delivered.
+pub fn mask_irq(irq: u8) {
+    let io_apics = IO_APICS.lock();
+    let (gsi, _) = io_apics.gsi_for(irq);
+    if let Some(io_apic) = io_apics.io_apic_for(gsi) {
+        unsafe { io_apic.write_redirection(gsi - io_apic.gsi_base, REDIRECTION_MASKED, 0) };
+    }
+}
+
+/// An ISA IRQ which is connected to a different GSI, or which has a non-standard trigger mode.
+#[derive(Debug, Clone, Copy)]
+struct IsaOverride {
+    gsi: u32,
+    active_low: bool,
+    level_triggered: bool,
+}
+
+struct IoApics {
+    io_apics: Vec<IoApic>,
+    overrides: [Option<IsaOverride>; 16],
+}
+
+impl IoApics {
+    /// Returns the GSI of an ISA IRQ and the redirection flags it needs.
+    fn gsi_for(&self, irq: u8) -> (u32, u32) {
+        match self.overrides.get(irq as usize).copied().flatten() {
+            Some(isa_override) => {
+                let mut flags = 0;
+                if isa_override.active_low {
+                    flags |= REDIRECTION_ACTIVE_LOW;
+                }
+                if isa_override.level_triggered {
+                    flags |= REDIRECTION_LEVEL_TRIGGERED;
+                }
+                (isa_override.gsi, flags)
+            }
+            None => (irq as u32, 0),
+        }
+    }
+
+    fn io_apic_for(&self, gsi: u32) -> Option<&IoApic> {
+        self.io_apics
+            .iter()
+            .find(|io_apic| (io_apic.gsi_base..io_apic.gsi_base + io_apic.redirection_entries).contains(&gsi))
+    }
+}
+
+struct IoApic {
+    /// The virtual address of the I/O APIC's registers.
+    base: u64,
+    /// The first GSI handled by this I/O APIC.
+    gsi_base: u32,
+    redirection_entries: u32,
+}
+
+impl IoApic {
+    unsafe fn read(&self, register: u32) -> u32 {
+        core::ptr::write_volatile(self.base as *mut u32, register);
+        core::ptr::read_volatile((self.base + 0x10) as *const u32)
+    }
+
+    unsafe fn write(&self, register: u32, value: u32) {
+        core::ptr::write_volatile(self.base as *mut u32, register);
+        core::ptr::write_volatile((self.base + 0x10) as *mut u32, value);
+    }
+
+    unsafe fn write_redirection(&self, index: u32, low: u32, high: u32) {
+        // Mask the entry while it is half written.
+        self.write(IO_APIC_REDIRECTION_TABLE + index * 2, REDIRECTION_MASKED);
+        self.write(IO_APIC_REDIRECTION_TABLE + index * 2 + 1, high);
+        self.write(IO_APIC_REDIRECTION_TABLE + index * 2, low);
+    }
+}
@@ -1,9 +1,15 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
 use lazy_static::lazy_static;
+use log::Level;
 use pic8259::ChainedPics;
 use spin;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use gtmos_kernel::irq::{self, InterruptController};
+use gtmos_kernel::logger::Clock;
+use gtmos_kernel::memory::fault::{self, PageFault};
 
-use crate::gdt;
+use crate::{apic, gdt, paging};
 
 pub const PIC_1_OFFSET: u8 = 32;
 pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
@@ -16,18 +22,30 @@ pub enum InterruptIndex {
 }
 
 impl InterruptIndex {
-    fn as_u8(self) -> u8 {
+    pub fn as_u8(self) -> u8 {
         self as u8
     }
-
-    fn as_usize(self) -> usize {
-        usize::from(self.as_u8())
-    }
 }
 
 pub static PICS: spin::Mutex<ChainedPics> =
     spin::Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
 
+/// The interrupt controller given to [`gtmos_kernel::irq`].
+pub static CONTROLLER: X86_64InterruptController = X86_64InterruptController;
+
+/// The interrupt entry point for `VECTOR`, which hands the interrupt to
+/// [`gtmos_kernel::irq::dispatch`].
+extern "x86-interrupt" fn trampoline<const VECTOR: u8>(_stack_frame: InterruptStackFrame) {
+    irq::dispatch(VECTOR);
+}
+
+/// Points each of the given IDT entries at its [`trampoline`].
+macro_rules! set_trampolines {
+    ($idt:ident, $($vector:literal),* $(,)?) => {
+        $($idt[$vector].set_handler_fn(trampoline::<$vector>);)*
+    };
+}
+
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
@@ -57,8 +75,23 @@ lazy_static! {
         idt.hv_injection_exception.set_handler_fn(hypervisor_injection_exception_handler);
         idt.vmm_communication_exception.set_handler_fn(vmm_communication_exception_handler);
         idt.security_exception.set_handler_fn(security_exception_handler);
-        idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
-        // idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+        set_trampolines!(idt,
+            32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
+            48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
+            64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79,
+            80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95,
+            96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111,
+            112, 113, 114, 115, 116, 117, 118, 119, 120, 121, 122, 123, 124, 125, 126, 127,
+            128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 139, 140, 141, 142, 143,
+            144, 145, 146, 147, 148, 149, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159,
+            160, 161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175,
+            176, 177, 178, 179, 180, 181, 182, 183, 184, 185, 186, 187, 188, 189, 190, 191,
+            192, 193, 194, 195, 196, 197, 198, 199, 200, 201, 202, 203, 204, 205, 206, 207,
+            208, 209, 210, 211, 212, 213, 214, 215, 216, 217, 218, 219, 220, 221, 222, 223,
+            224, 225, 226, 227, 228, 229, 230, 231, 232, 233, 234, 235, 236, 237, 238, 239,
+            240, 241, 242, 243, 244, 245, 246, 247, 248, 249, 250, 251, 252, 253, 254,
+        );
+        idt[apic::SPURIOUS_VECTOR as usize].set_handler_fn(spurious_interrupt_handler);
         idt
     };
 }
@@ -67,16 +100,159 @@ pub fn init_idt() {
     IDT.load();
 }
 
+/// Sets up the 8259 PICs and hands [`CONTROLLER`] to [`gtmos_kernel::irq`], then attaches the
+/// timer. Every IRQ line except the timer (and the cascade to the second PIC) starts out masked
+/// until a driver registers a handler for it.
+///
+/// ## Safety
+/// This function must only be called once, with interrupts disabled.
+pub unsafe fn initialise_irq() {
+    let mut pics = PICS.lock();
+    pics.initialize();
+    pics.write_masks(!0b0000_0101, 0xFF);
+    drop(pics);
+
+    irq::set_controller(&CONTROLLER);
+    irq::register_vector(InterruptIndex::Timer.as_u8(), timer_interrupt)
+        .expect("The timer vector is already in use");
+}
+
+/// Chooses the interrupt controller. If the ACPI tables have a MADT the 8259 PICs are masked and
+/// the local APIC and I/O APICs are used, otherwise the 8259 PICs (which were set up by
+/// [`X86_64SubSystem::initialise`](crate::system::X86_64SubSystem)) stay in use. IRQ lines which
+/// already have a handler, such as COM1's, keep working after the switch.
+///
+/// ## Safety
+/// This function must only be called once, after the kernel's memory management is set up.
+pub unsafe fn initialise_controller(rsdp_address: Option<u64>) {
+    let Some(rsdp_address) = rsdp_address else {
+        log::warn!("No RSDP, using the 8259 PICs");
+        return;
+    };
+    let madt = gtmos_kernel::acpi::initialise(rsdp_address).and_then(|tables| tables.madt());
+    let madt = match madt {
+        Ok(madt) => madt,
+        Err(error) => {
+            log::warn!("No MADT ({:?}), using the 8259 PICs", error);
+            return;
+        }
+    };
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut page_table = paging::PAGE_TABLE.lock();
+        let Some(page_table) = page_table.as_mut() else {
+            log::warn!("No page tables, using the 8259 PICs");
+            return;
+        };
+
+        match apic::initialise(&madt, page_table, InterruptIndex::Timer.as_u8()) {
+            Ok(()) => {
+                // Mask every line of the 8259 PICs, they stay remapped so a spurious interrupt
+                // from them does not look like an exception.
+                PICS.lock().disable();
+                // Every I/O APIC input starts out masked, so the lines which drivers attached to
+                // while the 8259 PICs were in use are routed again. IRQ 0 stays masked, as the
+                // local APIC timer replaces the PIT.
+                for irq in 1..irq::LEGACY_IRQ_COUNT {
+                    let vector = PIC_1_OFFSET + irq;
+                    if irq::is_registered(vector) {
+                        CONTROLLER.enable_irq(irq, vector);
+                    }
+                }
+                log::info!(
+                    "Using the APIC (local APIC {}), timer at {} Hz",
+                    apic::local_apic_id(),
+                    apic::TIMER_FREQUENCY
+                );
+            }
+            Err(error) => log::error!(
+                "Could not map the APIC ({:?}), using the 8259 PICs",
+                error
+            ),
+        }
+    });
+}
+
+/// Routes IRQs through the 8259 PICs, or through the I/O APICs once [`initialise_controller`] has
+/// switched to them. Legacy IRQ `n` always arrives on vector `PIC_1_OFFSET + n`.
+pub struct X86_64InterruptController;
+
+impl InterruptController for X86_64InterruptController {
+    fn vector_for_irq(&self, irq: u8) -> Option<u8> {
+        (irq < irq::LEGACY_IRQ_COUNT).then_some(PIC_1_OFFSET + irq)
+    }
+
+    fn enable_irq(&self, irq: u8, vector: u8) {
+        if apic::is_enabled() {
+            apic::route_irq(irq, vector);
+        } else {
+            set_pic_mask(irq, false);
+        }
+    }
+
+    fn disable_irq(&self, irq: u8) {
+        if apic::is_enabled() {
+            apic::mask_irq(irq);
+        } else {
+            set_pic_mask(irq, true);
+        }
+    }
+
+    fn end_of_interrupt(&self, vector: u8) {
+        if apic::is_enabled() {
+            apic::end_of_interrupt();
+        } else if (PIC_1_OFFSET..PIC_1_OFFSET + irq::LEGACY_IRQ_COUNT).contains(&vector) {
+            unsafe { PICS.lock().notify_end_of_interrupt(vector) };
+        }
+    }
+
+    fn disable_interrupts(&self) -> bool {
+        let enabled = x86_64::instructions::interrupts::are_enabled();
+        x86_64::instructions::interrupts::disable();
+        enabled
+    }
+
+    fn enable_interrupts(&self) {
+        x86_64::instructions::interrupts::enable();
+    }
+}
+
+/// Masks or unmasks one IRQ line of the 8259 PICs.
+fn set_pic_mask(irq: u8, masked: bool) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut pics = PICS.lock();
+        let mut masks = unsafe { pics.read_masks() };
+        let (pic, bit) = ((irq / 8) as usize, irq % 8);
+        if masked {
+            masks[pic] |= 1 << bit;
+        } else {
+            masks[pic] &= !(1 << bit);
+        }
+        unsafe { pics.write_masks(masks[0], masks[1]) };
+    });
+}
+
+/// Logs an exception which the kernel carries on after, with where it happened.
+fn log_exception(level: Level, name: &str, stack_frame: &InterruptStackFrame) {
+    log::log!(
+        level,
+        "{} at {:#x}, stack pointer {:#x}",
+        name,
+        stack_frame.instruction_pointer.as_u64(),
+        stack_frame.stack_pointer.as_u64()
+    );
+}
+
 extern "x86-interrupt" fn division_error_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: DIVISION ERROR\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Division error", &stack_frame);
 }
 
 extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: DEBUG\n{:#?}", stack_frame);
+    log_exception(Level::Warn, "Debug exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn non_maskable_interrupt_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: NON-MASKABLE INTERRUPT\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Non-maskable interrupt", &stack_frame);
 }
 
 /// Handles a breakpoint exception. Breakpoint exceptions are generated by debuggers to pause the code.
@@ -89,66 +265,98 @@ extern "x86-interrupt" fn non_maskable_interrupt_handler(stack_frame: InterruptS
 /// In this example the [`x86_64::instructions::interrupts::int3`] function from the [`x86_64`] crate is
 /// used to generate a breakpoint exception.
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+    log_exception(Level::Warn, "Breakpoint", &stack_frame);
 }
 
 extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: OVERFLOW\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Overflow", &stack_frame);
 }
 
 extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: BOUND RANGE EXCEEDED\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Bound range exceeded", &stack_frame);
 }
 
 extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: INVALID OPCODE\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Invalid opcode", &stack_frame);
 }
 
 extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: DEVICE NOT AVAILABLE\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Device not available", &stack_frame);
 }
 
 /// Handles a double fault exception.
 ///
 /// ## Example
 /// ```rust
-/// unsafe {
-///     *(0xdeadbeef as *mut u8) = 42;
-/// };
+/// fn stack_overflow() {
+///     stack_overflow();
+/// }
+/// stack_overflow();
 /// ```
 ///
-/// In this example a page fault occurs, but there is not page fault handler yet so a double fault
-/// occurs.
+/// In this example the kernel stack overflows, so the CPU can not push the page fault's interrupt
+/// stack frame and a double fault occurs. The double fault handler runs on its own stack.
 extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) -> !{
     panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
 extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: INVALID TSS\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Invalid TSS", &stack_frame);
 }
 
 extern "x86-interrupt" fn segment_not_present_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: SEGMENT NOT PRESENT\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Segment not present", &stack_frame);
 }
 
 extern "x86-interrupt" fn stack_segment_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: STACK SEGMENT FAULT\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Stack segment fault", &stack_frame);
 }
 
 extern "x86-interrupt" fn general_protection_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: GENERAL PROTECTION FAULT\n{:#?}", stack_frame);
+    log_exception(Level::Error, "General protection fault", &stack_frame);
 }
 
-extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, _error_code: PageFaultErrorCode) {
-    gtmos_kernel::serial_println!("EXCEPTION: PAGE FAULT\n{:#?}", stack_frame);
+/// Handles a page fault exception.
+///
+/// The faulting address is read from the CR2 register and handed to the kernel's region table,
+/// which backs the page if it belongs to a lazily allocated region. Any other page fault is a bug,
+/// so the kernel panics with a report of the fault instead of returning and faulting again.
+extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+    use x86_64::registers::control::Cr2;
+
+    let page_fault = PageFault {
+        address: Cr2::read().as_u64(),
+        instruction_pointer: stack_frame.instruction_pointer.as_u64(),
+        present: error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION),
+        write: error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE),
+        user: error_code.contains(PageFaultErrorCode::USER_MODE),
+        instruction_fetch: error_code.contains(PageFaultErrorCode::INSTRUCTION_FETCH),
+    };
+
+    // The page tables are locked if the fault happened while they were being changed, waiting for
+    // the lock would never finish.
+    let Some(mut page_table) = paging::PAGE_TABLE.try_lock() else {
+        panic!("EXCEPTION: PAGE FAULT\n{}\nThe page tables were locked\n{:#?}", page_fault, stack_frame);
+    };
+    let Some(page_table) = page_table.as_mut() else {
+        panic!("EXCEPTION: PAGE FAULT\n{}\nThe page tables were not set up\n{:#?}", page_fault, stack_frame);
+    };
+    let result = fault::handle_page_fault(&page_fault, page_table);
+
+    if let Err(error) = result {
+        panic!(
+            "EXCEPTION: PAGE FAULT\n{}\n{}\nError code: {:?}\n{:#?}",
+            page_fault, error, error_code, stack_frame
+        );
+    }
 }
 
 extern "x86-interrupt" fn x87_floating_point_exception_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: X87 FLOATING POINT EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "x87 floating point exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn alignment_check_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: ALIGNMENT CHECK\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Alignment check", &stack_frame);
 }
 
 extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
@@ -156,34 +364,61 @@ extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame
 }
 
 extern "x86-interrupt" fn simd_floating_point_exception_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: SIMD FLOATING POINT EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "SIMD floating point exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn virtualisation_exception_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: VIRTUALISATION EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Virtualisation exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn hypervisor_injection_exception_handler(stack_frame: InterruptStackFrame) {
-    gtmos_kernel::serial_println!("EXCEPTION: HYPERVISOR INJECTION EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Hypervisor injection exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn vmm_communication_exception_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: VMM COMMUNICATION EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "VMM communication exception", &stack_frame);
 }
 
 extern "x86-interrupt" fn security_exception_handler(stack_frame: InterruptStackFrame, _error_code: u64) {
-    gtmos_kernel::serial_println!("EXCEPTION: SECURITY EXCEPTION\n{:#?}", stack_frame);
+    log_exception(Level::Error, "Security exception", &stack_frame);
+}
+
+/// Handles an interrupt from the timer, which is the local APIC timer or, without an APIC, the
+/// Intel 8253 timer. Each interrupt moves the uptime on by one period of the timer.
+fn timer_interrupt(_vector: u8) {
+    let period = if apic::is_enabled() {
+        1_000_000_000 / apic::TIMER_FREQUENCY as u64
+    } else {
+        PIT_PERIOD
+    };
+    UPTIME.fetch_add(period, Ordering::Relaxed);
 }
 
-/// Handles an interrupt from the Intel 8253 timer.
-extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame){
-    gtmos_kernel::serial_print!(".");
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+/// The time since the timer was started in nanoseconds, counted by [`timer_interrupt`].
+static UPTIME: AtomicU64 = AtomicU64::new(0);
+
+/// The period of the Intel 8253 timer in nanoseconds, which interrupts each time it counts down
+/// from 65536.
+const PIT_PERIOD: u64 = 65536 * 1_000_000_000 / apic::PIT_FREQUENCY as u64;
+
+/// The clock given to [`gtmos_kernel::logger`], counted by the timer interrupt.
+pub static CLOCK: X86_64Clock = X86_64Clock;
+
+pub struct X86_64Clock;
+
+impl Clock for X86_64Clock {
+    fn uptime(&self) -> Duration {
+        Duration::from_nanos(UPTIME.load(Ordering::Relaxed))
+    }
+
+    fn cpu(&self) -> u32 {
+        if apic::is_enabled() { apic::local_apic_id() as u32 } else { 0 }
     }
 }
 
+/// Handles a spurious interrupt from the local APIC. These must not be acknowledged.
+extern "x86-interrupt" fn spurious_interrupt_handler(_stack_frame: InterruptStackFrame) {}
+
 #[test_case]
 fn test_breakpoint_exception() {
     // invoke a breakpoint exception
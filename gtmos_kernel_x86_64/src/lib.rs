@@ -12,6 +12,22 @@
 #![reexport_test_harness_main = "test_main"]
 #![feature(abi_x86_interrupt)]
 
+extern crate alloc;
+
+use bootloader_api::config::{BootloaderConfig, Mapping};
+
+pub mod apic;
 pub mod interrupts;
 pub mod system;
 pub mod gdt;
+pub mod memory;
+pub mod paging;
+
+/// The bootloader configuration used by the kernel and its tests. It asks the bootloader to map all
+/// physical memory into the kernel's address space, the kernel uses this mapping to access the
+/// frames it allocates.
+pub static BOOTLOADER_CONFIG: BootloaderConfig = {
+    let mut config = BootloaderConfig::new_default();
+    config.mappings.physical_memory = Some(Mapping::Dynamic);
+    config
+};
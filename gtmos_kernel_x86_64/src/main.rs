@@ -15,36 +15,53 @@
 use core::panic::PanicInfo;
 use gtmos_kernel;
 use gtmos_kernel::graphics::GraphicsAPI;
-use gtmos_kernel::platform::{Platform, set_platform, get_sub_system};
+use gtmos_kernel::platform::{self, Platform, set_platform};
 use gtmos_kernel_x86_64::system::X86_64SubSystem;
 
 
 
 static mut GRAPHICS_API: Option<GraphicsAPI> = None;
 
-bootloader_api::entry_point!(kernel_main);
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
 
 #[cfg(not(test))]
 fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
     use core::cell::RefCell;
-    use gtmos_kernel::{drivers::framebuffer::Pixel, console::Console};
+    use gtmos_kernel::{drivers::framebuffer::Pixel, drivers::keyboard, drivers::mouse, console::{self, terminals::{self, Terminals}}, devices, logger::{self, Sink}};
 
     let platform = Platform::new(X86_64SubSystem::new());
-    set_platform(platform);
-
-    if let Some(my_cpu) = get_sub_system() {
+    // The bootloader does not pass a command line, so it is chosen when the kernel is built.
+    logger::initialise(option_env!("GTMOS_CMDLINE").unwrap_or("")).expect("The logger was already initialised");
+    logger::add_sink(&logger::SERIAL).expect("There is no room for the serial log");
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    // The SubSystem is kept on the heap, so it is set once the heap is ready.
+    set_platform(platform).expect("The platform was already set");
+    unsafe { gtmos_kernel_x86_64::interrupts::initialise_controller(boot_info.rsdp_addr.into_option()) };
+    let (screen_width, screen_height) = boot_info.framebuffer.as_ref()
+        .map_or((0, 0), |framebuffer| (framebuffer.info().width, framebuffer.info().height));
+    initialise_input(screen_width, screen_height);
+    // Everything printed to the console is also sent to serial.
+    console::set_serial_mirror(true);
+
+    if platform::has_platform() {
         if let Some(framebuffer) = boot_info.framebuffer.as_mut() {
             let width = {framebuffer.info().width};
             let height = {framebuffer.info().height};
             let fb_mem = RefCell::new(gtmos_kernel::drivers::framebuffer::FramebufferMemory {
                 width: width,
                 height: height,
+                stride: framebuffer.info().stride,
                 bytes_per_pixel: framebuffer.info().bytes_per_pixel,
+                pixel_format: pixel_format(framebuffer.info().pixel_format),
                 buffer: framebuffer.buffer_mut(),
             });
             // Create graphics_api instance inside the block
             unsafe {
                 GRAPHICS_API = Some(GraphicsAPI::new(fb_mem));
+                // The heap was set up by `memory::initialise`, so the back buffer can be allocated.
+                if let Some(api) = GRAPHICS_API.as_mut() {
+                    api.enable_back_buffer();
+                }
             }
 
             // Fill the entire framebuffer with a teal colour.
@@ -55,16 +72,22 @@ fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
                         g: 0x80,
                         r: 0x00,
                     });
+                    api.present();
                 }
             }
 
-            // Set the my_subsystem directly
-            if let Some(my_subsystem) = get_sub_system() {
-                unsafe {
-                    let console_option = Some(Console::new(GRAPHICS_API.as_mut().unwrap(), 2));
-                    my_subsystem.set_console(console_option.map(|c| c));
+            // Show virtual terminals on the framebuffer, the shell is written to by `println!`.
+            unsafe {
+                let mut terminals = Terminals::new(GRAPHICS_API.as_mut().unwrap(), 2);
+                for console in terminals.iter_mut() {
+                    console.set_default_colours(Pixel { r: 0xFF, g: 0xFF, b: 0xFF }, Pixel { r: 0, g: 0x80, b: 0x80 });
                 }
+                console::set_terminals(Some(terminals));
             }
+            terminals::register_devices().expect("The terminals are already registered");
+            // The records logged before there were terminals are copied from the dmesg ring.
+            logger::dmesg(|entry| logger::CONSOLE.write(entry));
+            logger::add_sink(&logger::CONSOLE).expect("There is no room for the console log");
         }
         // if let Some(platform_from_get) = get_platform::<X86_64Cpu>() {
         //     // platform_from_get.console = _console;
@@ -77,40 +100,78 @@ fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
         //         console.write_str("Agj", text_colour, background_colour);
         //     }
         // }
-        if let Some(system) = get_sub_system() {
-            if let Some(console) = system.get_console() {
-                let text_colour = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
-                let background_colour = Pixel { r: 0, g: 0x80, b: 0x80 };
-                console.write_str("A", text_colour, background_colour);
-                console.write_str("A\n", text_colour, background_colour);
-                console.write_str("Agj", text_colour, background_colour);
+        gtmos_kernel::println!("\x1b[1;33mAAAgj\x1b[0m");
+        gtmos_kernel::println!("Welcome to GT-MOS!\nGT-MOS is (c) 2023 Samuel Hulme, All rights reserved.");
+        gtmos_kernel::println!("Hello World{}", "!");
+    }
+    devices::for_each(|id, _device| log::info!("Device: {}", id));
+    loop {
+        // Echo typed characters to serial until there is a console to send them to.
+        while let Some(event) = keyboard::read_event() {
+            // Alt+F1 to Alt+F6 switch terminal, and Shift+PageUp and Shift+PageDown browse the
+            // scrollback.
+            if console::with_terminals(|terminals| terminals.handle_key(&event)) == Some(true) {
+                continue;
+            }
+            if let Some(character) = event.character {
+                gtmos_kernel::serial_print!("{}", character);
             }
         }
+        // The graphics API was lent to the terminals, so the cursor is moved through them.
+        while let Some(event) = mouse::read_event() {
+            console::with_terminals(|terminals| terminals.move_cursor(event.x, event.y));
+        }
+        x86_64::instructions::hlt();
+    }
+}
 
-        // gtmos_kernel::println!("AAAgj");
-        gtmos_kernel::serial_println!("Welcome to GT-MOS!\nGT-MOS is (c) 2023 Samuel Hulme, All rights reserved.");
-        // gtmos_kernel::println!("Welcome to GT-MOS!\nGT-MOS is (c) 2023 Samuel Hulme, All rights reserved.");
-        gtmos_kernel::serial_println!("Hello World{}", "!");
-
-
-        my_cpu.halt();
+/// Sets up the PS/2 controller and the keyboard and mouse attached to it. The mouse pointer is kept
+/// inside a screen of `width` by `height` pixels.
+#[cfg(not(test))]
+fn initialise_input(width: usize, height: usize) {
+    use gtmos_kernel::drivers::{i8042, keyboard, mouse};
+
+    let controller = match unsafe { i8042::initialise() } {
+        Ok(controller) => controller,
+        Err(error) => {
+            log::warn!("PS/2 controller: {:?}", error);
+            return;
+        }
+    };
+    if let Err(error) = unsafe { keyboard::initialise(&controller) } {
+        log::warn!("PS/2 keyboard: {:?}", error);
     }
-    loop {
-        x86_64::instructions::hlt();
+    if let Err(error) = unsafe { mouse::initialise(&controller, width, height) } {
+        log::warn!("PS/2 mouse: {:?}", error);
+    }
+}
+
+/// Converts the bootloader's pixel format into the kernel's.
+#[cfg(not(test))]
+fn pixel_format(format: bootloader_api::info::PixelFormat) -> gtmos_kernel::drivers::framebuffer::PixelFormat {
+    use bootloader_api::info::PixelFormat as BootPixelFormat;
+    use gtmos_kernel::drivers::framebuffer::PixelFormat;
+
+    match format {
+        BootPixelFormat::Rgb => PixelFormat::Rgb,
+        BootPixelFormat::Bgr => PixelFormat::Bgr,
+        BootPixelFormat::U8 => PixelFormat::U8,
+        BootPixelFormat::Unknown { red_position, green_position, blue_position } => {
+            PixelFormat::Unknown { red_position, green_position, blue_position }
+        }
+        // Most firmware uses BGR, so it is the best guess for formats added in newer bootloaders.
+        _ => PixelFormat::Bgr,
     }
 }
 
 #[cfg(test)]
-pub(crate) fn kernel_main(_boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+pub(crate) fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
     let platform = Platform::new(X86_64SubSystem::new());
-    set_platform(platform);
-    if let Some(my_cpu) = get_sub_system() {
-        test_main();
-        my_cpu.halt();
-    }
-    loop {
-        x86_64::instructions::hlt();
-    }
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    unsafe { gtmos_kernel_x86_64::interrupts::initialise_controller(boot_info.rsdp_addr.into_option()) };
+    test_main();
+    platform::halt()
 }
 
 
@@ -0,0 +1,64 @@
+use bootloader_api::BootInfo;
+use bootloader_api::info::{MemoryRegionKind as BootMemoryRegionKind, MemoryRegions};
+use gtmos_kernel::memory::frame::{self, FrameError, FrameStats, MemoryRegion, MemoryRegionKind};
+use gtmos_kernel::memory::heap;
+
+use crate::paging;
+
+/// Sets up the kernel's memory management from the information given to us by the bootloader.
+///
+/// This creates the frame allocator, takes over the bootloader's page tables and maps the kernel
+/// heap, so it must be called before anything uses the `alloc` crate. The bootloader must have
+/// been configured with [`BOOTLOADER_CONFIG`](crate::BOOTLOADER_CONFIG).
+///
+/// ## Safety
+/// This function must only be called once.
+pub unsafe fn initialise(boot_info: &BootInfo) {
+    let physical_memory_offset = boot_info.physical_memory_offset
+        .into_option()
+        .expect("The bootloader did not map physical memory");
+
+    gtmos_kernel::memory::set_physical_memory_offset(physical_memory_offset);
+
+    match initialise_frame_allocator(&boot_info.memory_regions, physical_memory_offset) {
+        Ok(stats) => log::info!(
+            "Physical memory: {} frames, {} free, {} reserved",
+            stats.total,
+            stats.free,
+            stats.reserved
+        ),
+        Err(error) => panic!("Could not create the frame allocator: {:?}", error),
+    }
+
+    paging::initialise(physical_memory_offset);
+
+    let mut page_table = paging::PAGE_TABLE.lock();
+    let page_table = page_table.as_mut().expect("The page tables were not initialised");
+    if let Err(error) = heap::initialise(page_table) {
+        panic!("Could not map the kernel heap: {:?}", error);
+    }
+}
+
+/// Converts the bootloader's memory map and creates the kernel's global frame allocator from it.
+///
+/// Only regions which the bootloader reports as usable are handed out, memory used by the
+/// bootloader itself (page tables, boot info, kernel stack) and by the firmware stays reserved.
+///
+/// ## Safety
+/// `physical_memory_offset` must be the offset of the bootloader's physical memory mapping, and
+/// this function must only be called once.
+pub unsafe fn initialise_frame_allocator(
+    memory_regions: &MemoryRegions,
+    physical_memory_offset: u64,
+) -> Result<FrameStats, FrameError> {
+    let regions = memory_regions.iter().map(|region| MemoryRegion {
+        start: region.start,
+        end: region.end,
+        kind: if region.kind == BootMemoryRegionKind::Usable {
+            MemoryRegionKind::Usable
+        } else {
+            MemoryRegionKind::Reserved
+        },
+    });
+    frame::initialise(regions, physical_memory_offset)
+}
@@ -0,0 +1,242 @@
+use gtmos_kernel::memory::frame;
+use gtmos_kernel::memory::paging::{MapError, PageFlags, PageSize, Translation, VirtualMemory};
+use spin::Mutex;
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::mapper::{
+    FlagUpdateError, MapToError, MappedFrame, TranslateResult, UnmapError,
+};
+use x86_64::structures::paging::{
+    FrameAllocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame, Size1GiB,
+    Size2MiB, Size4KiB, Translate,
+};
+use x86_64::{PhysAddr, VirtAddr};
+
+/// The kernel's page tables, they are empty until [`initialise`] is called.
+///
+/// [`X86_64SubSystem`](crate::system::X86_64SubSystem) implements
+/// [`VirtualMemory`] on top of this.
+pub static PAGE_TABLE: Mutex<Option<X86_64PageTable>> = Mutex::new(None);
+
+/// Takes the active level 4 page table, which was set up by the bootloader, and stores it in
+/// [`PAGE_TABLE`].
+///
+/// ## Safety
+/// All physical memory must be mapped at `physical_memory_offset` and this function must only be
+/// called once.
+pub unsafe fn initialise(physical_memory_offset: u64) {
+    let physical_memory_offset = VirtAddr::new(physical_memory_offset);
+    let level_4_table = active_level_4_table(physical_memory_offset);
+    *PAGE_TABLE.lock() = Some(X86_64PageTable {
+        mapper: OffsetPageTable::new(level_4_table, physical_memory_offset),
+    });
+}
+
+/// Returns a mutable reference to the active level 4 page table.
+///
+/// ## Safety
+/// All physical memory must be mapped at `physical_memory_offset`, and this function must not be
+/// called again while the returned reference is alive.
+unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut PageTable {
+    let (level_4_table_frame, _) = Cr3::read();
+    let virtual_address = physical_memory_offset + level_4_table_frame.start_address().as_u64();
+    &mut *virtual_address.as_mut_ptr()
+}
+
+/// The x86_64 4-level page tables, accessed through the bootloader's physical memory mapping.
+pub struct X86_64PageTable {
+    mapper: OffsetPageTable<'static>,
+}
+
+impl VirtualMemory for X86_64PageTable {
+    fn map(
+        &mut self,
+        virtual_address: u64,
+        physical_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError> {
+        let flags = to_page_table_flags(flags);
+        let virtual_address = VirtAddr::new(virtual_address);
+        let physical_address = PhysAddr::new(physical_address);
+
+        match size {
+            PageSize::Size4KiB => {
+                let page = Page::<Size4KiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let frame = PhysFrame::<Size4KiB>::from_start_address(physical_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flush = unsafe { self.mapper.map_to(page, frame, flags, &mut KernelFrameAllocator) }
+                    .map_err(from_map_to_error)?;
+                flush.flush();
+            }
+            PageSize::Size2MiB => {
+                let page = Page::<Size2MiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let frame = PhysFrame::<Size2MiB>::from_start_address(physical_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flush = unsafe { self.mapper.map_to(page, frame, flags, &mut KernelFrameAllocator) }
+                    .map_err(from_map_to_error)?;
+                flush.flush();
+            }
+            PageSize::Size1GiB => {
+                let page = Page::<Size1GiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let frame = PhysFrame::<Size1GiB>::from_start_address(physical_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flush = unsafe { self.mapper.map_to(page, frame, flags, &mut KernelFrameAllocator) }
+                    .map_err(from_map_to_error)?;
+                flush.flush();
+            }
+        }
+        Ok(())
+    }
+
+    fn unmap(&mut self, virtual_address: u64, size: PageSize) -> Result<u64, MapError> {
+        let virtual_address = VirtAddr::new(virtual_address);
+
+        match size {
+            PageSize::Size4KiB => {
+                let page = Page::<Size4KiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let (frame, flush) = self.mapper.unmap(page).map_err(from_unmap_error)?;
+                flush.flush();
+                Ok(frame.start_address().as_u64())
+            }
+            PageSize::Size2MiB => {
+                let page = Page::<Size2MiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let (frame, flush) = self.mapper.unmap(page).map_err(from_unmap_error)?;
+                flush.flush();
+                Ok(frame.start_address().as_u64())
+            }
+            PageSize::Size1GiB => {
+                let page = Page::<Size1GiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let (frame, flush) = self.mapper.unmap(page).map_err(from_unmap_error)?;
+                flush.flush();
+                Ok(frame.start_address().as_u64())
+            }
+        }
+    }
+
+    fn protect(
+        &mut self,
+        virtual_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError> {
+        let virtual_address = VirtAddr::new(virtual_address);
+
+        match size {
+            PageSize::Size4KiB => {
+                let page = Page::<Size4KiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flush = unsafe { self.mapper.update_flags(page, to_page_table_flags(flags)) }
+                    .map_err(from_flag_update_error)?;
+                flush.flush();
+            }
+            PageSize::Size2MiB => {
+                let page = Page::<Size2MiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flags = to_page_table_flags(flags) | PageTableFlags::HUGE_PAGE;
+                let flush = unsafe { self.mapper.update_flags(page, flags) }
+                    .map_err(from_flag_update_error)?;
+                flush.flush();
+            }
+            PageSize::Size1GiB => {
+                let page = Page::<Size1GiB>::from_start_address(virtual_address)
+                    .map_err(|_| MapError::NotAligned)?;
+                let flags = to_page_table_flags(flags) | PageTableFlags::HUGE_PAGE;
+                let flush = unsafe { self.mapper.update_flags(page, flags) }
+                    .map_err(from_flag_update_error)?;
+                flush.flush();
+            }
+        }
+        Ok(())
+    }
+
+    fn translate(&self, virtual_address: u64) -> Option<Translation> {
+        match self.mapper.translate(VirtAddr::new(virtual_address)) {
+            TranslateResult::Mapped { frame, offset, flags } => {
+                let size = match frame {
+                    MappedFrame::Size4KiB(_) => PageSize::Size4KiB,
+                    MappedFrame::Size2MiB(_) => PageSize::Size2MiB,
+                    MappedFrame::Size1GiB(_) => PageSize::Size1GiB,
+                };
+                Some(Translation {
+                    physical_address: frame.start_address().as_u64() + offset,
+                    size,
+                    flags: from_page_table_flags(flags),
+                })
+            }
+            TranslateResult::NotMapped | TranslateResult::InvalidFrameAddress(_) => None,
+        }
+    }
+}
+
+fn to_page_table_flags(flags: PageFlags) -> PageTableFlags {
+    let mut page_table_flags = PageTableFlags::PRESENT;
+    if flags.contains(PageFlags::WRITABLE) {
+        page_table_flags |= PageTableFlags::WRITABLE;
+    }
+    if flags.contains(PageFlags::NO_EXECUTE) {
+        page_table_flags |= PageTableFlags::NO_EXECUTE;
+    }
+    if flags.contains(PageFlags::USER) {
+        page_table_flags |= PageTableFlags::USER_ACCESSIBLE;
+    }
+    if flags.contains(PageFlags::CACHE_DISABLE) {
+        page_table_flags |= PageTableFlags::NO_CACHE;
+    }
+    page_table_flags
+}
+
+fn from_page_table_flags(page_table_flags: PageTableFlags) -> PageFlags {
+    let mut flags = PageFlags::empty();
+    if page_table_flags.contains(PageTableFlags::WRITABLE) {
+        flags |= PageFlags::WRITABLE;
+    }
+    if page_table_flags.contains(PageTableFlags::NO_EXECUTE) {
+        flags |= PageFlags::NO_EXECUTE;
+    }
+    if page_table_flags.contains(PageTableFlags::USER_ACCESSIBLE) {
+        flags |= PageFlags::USER;
+    }
+    if page_table_flags.contains(PageTableFlags::NO_CACHE) {
+        flags |= PageFlags::CACHE_DISABLE;
+    }
+    flags
+}
+
+fn from_map_to_error<S: x86_64::structures::paging::PageSize>(error: MapToError<S>) -> MapError {
+    match error {
+        MapToError::FrameAllocationFailed => MapError::FrameAllocationFailed,
+        MapToError::ParentEntryHugePage => MapError::SizeMismatch,
+        MapToError::PageAlreadyMapped(_) => MapError::AlreadyMapped,
+    }
+}
+
+fn from_unmap_error(error: UnmapError) -> MapError {
+    match error {
+        UnmapError::ParentEntryHugePage => MapError::SizeMismatch,
+        UnmapError::PageNotMapped | UnmapError::InvalidFrameAddress(_) => MapError::NotMapped,
+    }
+}
+
+fn from_flag_update_error(error: FlagUpdateError) -> MapError {
+    match error {
+        FlagUpdateError::PageNotMapped => MapError::NotMapped,
+        FlagUpdateError::ParentEntryHugePage => MapError::SizeMismatch,
+    }
+}
+
+/// Hands out frames from the kernel's global frame allocator to the `x86_64` crate's mapper, which
+/// needs them for new page tables.
+struct KernelFrameAllocator;
+
+unsafe impl FrameAllocator<Size4KiB> for KernelFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        let frame = frame::allocate_frame()?;
+        Some(PhysFrame::containing_address(PhysAddr::new(frame.start_address())))
+    }
+}
@@ -1,7 +1,14 @@
-use gtmos_kernel::{platform::SubSystem, drivers::framebuffer::Pixel, console::Console};
+use gtmos_kernel::{platform::SubSystem, logger};
+use gtmos_kernel::devices::{self, CharDevice, Device, DeviceError};
+use gtmos_kernel::drivers::serial;
+use gtmos_kernel::drivers::port::{self, PortIo};
+use gtmos_kernel::irq;
+use gtmos_kernel::ring_buffer::RingBuffer;
+use x86_64::instructions::port::Port;
+use gtmos_kernel::memory::paging::{MapError, PageFlags, PageSize, Translation, VirtualMemory};
 use uart_16550::SerialPort;
 use spin::Mutex;
-use crate::{interrupts, gdt};
+use crate::{interrupts, gdt, paging};
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -15,14 +22,74 @@ lazy_static! {
     };
 }
 
+/// The legacy IRQ line of COM1.
+const SERIAL1_IRQ: u8 = 4;
 
-pub struct X86_64SubSystem {
-    pub console: Option<Console<'static>>,
+/// The bytes received on [`SERIAL1`] which have not been read yet.
+static SERIAL1_RECEIVED: RingBuffer<u8, 1024> = RingBuffer::new();
+
+/// Moves the received bytes from the UART's FIFO into [`SERIAL1_RECEIVED`]. [`SerialPort::init`]
+/// enables the receive interrupt, which fires when the FIFO is 14 bytes full or when bytes have
+/// been waiting for a few character times.
+fn serial1_interrupt(_vector: u8) {
+    let mut line_status: Port<u8> = Port::new(0x3F8 + 5);
+    let mut data: Port<u8> = Port::new(0x3F8);
+    // Bit 0 of the line status is set while there is a received byte.
+    while unsafe { line_status.read() } & 1 != 0 {
+        // Bytes are dropped while the buffer is full.
+        let _ = SERIAL1_RECEIVED.push(unsafe { data.read() });
+    }
+}
+
+/// Gives [`SERIAL1`] to the kernel as the [`serial::DEVICE_NAME`] character device.
+pub static SERIAL1_DEVICE: Serial1Device = Serial1Device;
+
+pub struct Serial1Device;
+
+impl CharDevice for Serial1Device {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, DeviceError> {
+        let mut length = 0;
+        while length < buffer.len() {
+            match SERIAL1_RECEIVED.pop() {
+                Some(byte) => buffer[length] = byte,
+                None => break,
+            }
+            length += 1;
+        }
+        Ok(length)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize, DeviceError> {
+        irq::without_interrupts(|| {
+            let mut serial_port = SERIAL1.lock();
+            for byte in data {
+                serial_port.send(*byte);
+            }
+        });
+        Ok(data.len())
+    }
 }
 
+/// Gives the drivers in the kernel crate access to the I/O ports.
+pub static PORT_IO: X86_64PortIo = X86_64PortIo;
+
+pub struct X86_64PortIo;
+
+impl PortIo for X86_64PortIo {
+    unsafe fn read_u8(&self, port: u16) -> u8 {
+        Port::new(port).read()
+    }
+
+    unsafe fn write_u8(&self, port: u16, value: u8) {
+        Port::new(port).write(value);
+    }
+}
+
+pub struct X86_64SubSystem {}
+
 impl X86_64SubSystem {
     pub fn new() -> Self {
-        let system = X86_64SubSystem { console: None };
+        let system = X86_64SubSystem {};
         system.initialise();
         return system;
     }
@@ -31,8 +98,13 @@ impl X86_64SubSystem {
 impl SubSystem for X86_64SubSystem {
     fn initialise(&self) {
         gdt::initialise();
+        port::set_port_io(&PORT_IO);
         interrupts::init_idt();
-        unsafe { interrupts::PICS.lock().initialize() }
+        logger::set_clock(&interrupts::CLOCK);
+        unsafe { interrupts::initialise_irq() }
+        lazy_static::initialize(&SERIAL1);
+        irq::register_irq(SERIAL1_IRQ, serial1_interrupt).expect("IRQ 4 is already in use");
+        devices::register(serial::DEVICE_NAME, Device::Char(&SERIAL1_DEVICE)).expect("COM1 is already registered");
         x86_64::instructions::interrupts::enable();
     }
 
@@ -42,40 +114,49 @@ impl SubSystem for X86_64SubSystem {
         }
     }
 
-    fn write(&mut self, dest: &str, data: &str) {
-        use x86_64::instructions::interrupts;
-        if dest == "serial" {
-            use core::fmt::Write;
-
-            interrupts::without_interrupts(|| {
-                SERIAL1
-                    .lock()
-                    .write_str(data)
-                    .expect("Printing to serial failed");
-            });
-        }
-        if dest == "vga_console" {
-            let text_colour = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
-            let background_colour = Pixel { r: 0, g: 0x80, b: 0x80 };
-            if let Some(console) = self.get_console() {
-                interrupts::without_interrupts(|| {
-                console.write_str(data, text_colour, background_colour);
-                });
-            }
-        }
+    fn virtual_memory(&mut self) -> &mut dyn VirtualMemory {
+        self
     }
+}
 
-    // fn read(&self, dest: &str) -> &str {
-    //     return "";
-    // }
-
+/// Gives the kernel access to the page tables in [`paging::PAGE_TABLE`].
+impl VirtualMemory for X86_64SubSystem {
+    fn map(
+        &mut self,
+        virtual_address: u64,
+        physical_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError> {
+        paging::PAGE_TABLE
+            .lock()
+            .as_mut()
+            .ok_or(MapError::Uninitialised)?
+            .map(virtual_address, physical_address, size, flags)
+    }
 
-    fn set_console(&mut self, console: Option<Console<'static>>) {
-        self.console = console;
+    fn unmap(&mut self, virtual_address: u64, size: PageSize) -> Result<u64, MapError> {
+        paging::PAGE_TABLE
+            .lock()
+            .as_mut()
+            .ok_or(MapError::Uninitialised)?
+            .unmap(virtual_address, size)
     }
 
-    fn get_console(&mut self) -> Option<&mut Console<'static>> {
-        return self.console.as_mut()
+    fn protect(
+        &mut self,
+        virtual_address: u64,
+        size: PageSize,
+        flags: PageFlags,
+    ) -> Result<(), MapError> {
+        paging::PAGE_TABLE
+            .lock()
+            .as_mut()
+            .ok_or(MapError::Uninitialised)?
+            .protect(virtual_address, size, flags)
     }
 
+    fn translate(&self, virtual_address: u64) -> Option<Translation> {
+        paging::PAGE_TABLE.lock().as_ref()?.translate(virtual_address)
+    }
 }
@@ -0,0 +1,144 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::acpi::fadt::{Fadt, BOOT_ARCHITECTURE_8042, RESET_REGISTER_SUPPORTED};
+use gtmos_kernel::acpi::hpet::Hpet;
+use gtmos_kernel::acpi::madt::{Madt, MadtEntry, PCAT_COMPAT};
+use gtmos_kernel::acpi::mcfg::Mcfg;
+use gtmos_kernel::acpi::{checksum_is_valid, GenericAddress, SDT_HEADER_SIZE};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_checksum() {
+    let mut bytes = [1u8, 2, 3, 0];
+    assert!(!checksum_is_valid(&bytes));
+    bytes[3] = 0u8.wrapping_sub(6);
+    assert!(checksum_is_valid(&bytes));
+}
+
+#[test_case]
+fn test_madt_entries() {
+    let mut table = [0u8; SDT_HEADER_SIZE + 8 + 8 + 12 + 10];
+    table[0..4].copy_from_slice(b"APIC");
+    let length = table.len() as u32;
+    table[4..8].copy_from_slice(&length.to_le_bytes());
+    let body = &mut table[SDT_HEADER_SIZE..];
+    body[0..4].copy_from_slice(&0xFEE0_0000u32.to_le_bytes());
+    body[4..8].copy_from_slice(&PCAT_COMPAT.to_le_bytes());
+    // Processor 0 with local APIC 0, enabled.
+    body[8..16].copy_from_slice(&[0, 8, 0, 0, 1, 0, 0, 0]);
+    // I/O APIC 1 at 0xFEC00000 handling GSIs from 0.
+    body[16..28].copy_from_slice(&[1, 12, 1, 0, 0x00, 0x00, 0xC0, 0xFE, 0, 0, 0, 0]);
+    // ISA IRQ 0 (the PIT) is connected to GSI 2.
+    body[28..38].copy_from_slice(&[2, 10, 0, 0, 2, 0, 0, 0, 0, 0]);
+
+    let madt = Madt::parse(&table).unwrap();
+    assert!(madt.has_8259());
+    assert_eq!(madt.local_apic_physical_address(), 0xFEE0_0000);
+
+    let mut entries = madt.entries();
+    let local_apic = MadtEntry::LocalApic { processor_id: 0, apic_id: 0, flags: 1 };
+    assert_eq!(entries.next(), Some(local_apic));
+    let io_apic = MadtEntry::IoApic { id: 1, address: 0xFEC0_0000, gsi_base: 0 };
+    assert_eq!(entries.next(), Some(io_apic));
+    assert_eq!(
+        entries.next(),
+        Some(MadtEntry::InterruptSourceOverride { bus: 0, source: 0, gsi: 2, flags: 0 })
+    );
+    assert_eq!(entries.next(), None);
+}
+
+#[test_case]
+fn test_fadt_prefers_extended_addresses() {
+    let mut table = [0u8; 244];
+    table[0..4].copy_from_slice(b"FACP");
+    table[4..8].copy_from_slice(&244u32.to_le_bytes());
+    table[8] = 6;
+    table[40..44].copy_from_slice(&0x1000u32.to_le_bytes());
+    table[140..148].copy_from_slice(&0x2000u64.to_le_bytes());
+    table[46] = 9;
+    // PM1a control block at I/O port 0x604 with 2 bytes, and no 64 bit version.
+    table[64..68].copy_from_slice(&0x604u32.to_le_bytes());
+    table[89] = 2;
+    table[109] = BOOT_ARCHITECTURE_8042 as u8;
+    table[112..116].copy_from_slice(&RESET_REGISTER_SUPPORTED.to_le_bytes());
+    // Reset register at I/O port 0xCF9.
+    table[116..120].copy_from_slice(&[1, 8, 0, 1]);
+    table[120..128].copy_from_slice(&0xCF9u64.to_le_bytes());
+    table[128] = 0x06;
+
+    let fadt = Fadt::parse(&table).unwrap();
+    assert_eq!(fadt.dsdt_address, 0x2000);
+    assert_eq!(fadt.sci_interrupt, 9);
+    assert!(fadt.has_8042());
+    assert_eq!(
+        fadt.pm1a_control_block,
+        Some(GenericAddress::io_port(0x604, 2).unwrap())
+    );
+    assert_eq!(fadt.pm1b_control_block, None);
+    assert_eq!(fadt.reset_register.map(|register| register.address), Some(0xCF9));
+    assert_eq!(fadt.reset_value, 0x06);
+}
+
+#[test_case]
+fn test_hpet() {
+    let mut table = [0u8; SDT_HEADER_SIZE + 20];
+    table[0..4].copy_from_slice(b"HPET");
+    table[4..8].copy_from_slice(&(SDT_HEADER_SIZE as u32 + 20).to_le_bytes());
+    // Revision 1, 3 comparators, 64 bit counter, legacy replacement, vendor 0x8086.
+    table[36..40].copy_from_slice(&0x8086_A201u32.to_le_bytes());
+    table[40..44].copy_from_slice(&[0, 64, 0, 0]);
+    table[44..52].copy_from_slice(&0xFED0_0000u64.to_le_bytes());
+    table[53..55].copy_from_slice(&128u16.to_le_bytes());
+
+    let hpet = Hpet::parse(&table).unwrap();
+    assert_eq!(hpet.hardware_revision, 1);
+    assert_eq!(hpet.comparator_count, 3);
+    assert!(hpet.counter_64_bit);
+    assert!(hpet.legacy_replacement);
+    assert_eq!(hpet.pci_vendor_id, 0x8086);
+    assert_eq!(hpet.base_address.address, 0xFED0_0000);
+    assert_eq!(hpet.minimum_tick, 128);
+}
+
+#[test_case]
+fn test_mcfg() {
+    // The header is followed by 8 reserved bytes and one 16 byte entry.
+    let mut table = [0u8; SDT_HEADER_SIZE + 8 + 16];
+    table[0..4].copy_from_slice(b"MCFG");
+    let length = table.len() as u32;
+    table[4..8].copy_from_slice(&length.to_le_bytes());
+    // Segment group 0, buses 0 to 255, at 0xB0000000.
+    table[44..52].copy_from_slice(&0xB000_0000u64.to_le_bytes());
+    table[55] = 255;
+
+    let mcfg = Mcfg::parse(&table).unwrap();
+    let entry = mcfg.find(0, 1).unwrap();
+    let address = 0xB000_0000 + (1 << 20) + (2 << 15) + (3 << 12);
+    assert_eq!(entry.configuration_address(1, 2, 3), Some(address));
+    assert_eq!(entry.configuration_address(0, 32, 0), None);
+    assert_eq!(mcfg.find(1, 0), None);
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::acpi::{self, madt::MadtEntry};
+use gtmos_kernel::logger::Clock;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::apic;
+use gtmos_kernel_x86_64::interrupts::CLOCK;
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    let rsdp_address = boot_info.rsdp_addr.into_option();
+    unsafe { gtmos_kernel_x86_64::interrupts::initialise_controller(rsdp_address) };
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_madt_is_found() {
+    // QEMU's pc and q35 machines both have a MADT with one I/O APIC.
+    let madt = acpi::tables().unwrap().madt().unwrap();
+    assert!(madt.entries().any(|entry| matches!(entry, MadtEntry::IoApic { .. })));
+    assert!(apic::is_enabled());
+    assert_eq!(CLOCK.cpu(), apic::local_apic_id() as u32);
+}
+
+#[test_case]
+fn test_local_apic_timer_ticks() {
+    let start = CLOCK.uptime();
+    // Every tick of the timer wakes the CPU up.
+    for _ in 0..3 {
+        x86_64::instructions::hlt();
+    }
+    assert!(CLOCK.uptime() > start);
+}
@@ -0,0 +1,193 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use core::cell::RefCell;
+use core::panic::PanicInfo;
+use gtmos_kernel::console::Console;
+use gtmos_kernel::console::ansi::{palette, Action, Attributes, Parser};
+use gtmos_kernel::console::grid::{Cell, Grid};
+use gtmos_kernel::console::terminals::{Terminals, KERNEL_LOG, SHELL};
+use gtmos_kernel::drivers::framebuffer::{FramebufferMemory, Pixel, PixelFormat};
+use gtmos_kernel::drivers::keyboard::{KeyCode, KeyEvent, KeyState, Modifiers};
+use gtmos_kernel::graphics::GraphicsAPI;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+fn line(grid: &Grid, offset: usize, row: usize) -> [char; 2] {
+    let line = grid.line(offset, row);
+    [line[0].character, line[1].character]
+}
+
+#[test_case]
+fn test_scrollback_ring() {
+    let mut grid = Grid::new(2, 2, 2);
+    for character in ['a', 'b', 'c', 'd'] {
+        grid.set(0, 1, Cell { character, ..Cell::BLANK });
+        grid.scroll_up(Cell::BLANK);
+    }
+    // Only the last two lines which scrolled off the screen are kept.
+    assert_eq!(grid.history_length(), 2);
+    assert_eq!(line(&grid, 5, 0), ['b', ' ']);
+    assert_eq!(line(&grid, 2, 1), ['c', ' ']);
+    assert_eq!(line(&grid, 0, 0), ['d', ' ']);
+
+    grid.fill(0, 1, 2, 1, Cell { character: 'e', ..Cell::BLANK });
+    assert_eq!(line(&grid, 0, 1), ['e', 'e']);
+}
+
+#[test_case]
+fn test_resize_keeps_lines() {
+    let mut grid = Grid::new(3, 2, 4);
+    grid.set(2, 0, Cell { character: 'a', ..Cell::BLANK });
+    grid.set(0, 1, Cell { character: 'b', ..Cell::BLANK });
+    grid.scroll_up(Cell::BLANK);
+
+    assert_eq!(grid.resize(2, 3, 1), 2);
+    assert_eq!((grid.columns(), grid.rows(), grid.history_length()), (2, 3, 0));
+    assert_eq!(line(&grid, 0, 0), [' ', ' ']);
+    assert_eq!(line(&grid, 0, 1), ['b', ' ']);
+}
+
+#[test_case]
+fn test_parse_and_select_graphic_rendition() {
+    let mut parser = Parser::new();
+    let mut actions = [None; 4];
+    let mut count = 0;
+    for c in "a\x1b[1;38;5;196m\r\x1b[?25l".chars() {
+        if let Some(action) = parser.advance(c) {
+            actions[count] = Some(action);
+            count += 1;
+        }
+    }
+    assert_eq!(count, 4);
+    assert_eq!(actions[0], Some(Action::Print('a')));
+    assert_eq!(actions[2], Some(Action::Control('\r')));
+
+    let Some(Action::Csi(csi)) = actions[1] else { panic!("expected a control sequence") };
+    assert_eq!((csi.command, csi.private), ('m', false));
+    let mut attributes = Attributes::DEFAULT;
+    attributes.select_graphic_rendition(csi.parameters());
+    let white = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+    let black = Pixel { r: 0, g: 0, b: 0 };
+    assert_eq!(attributes.colours(white, black), (Pixel { r: 0xFF, g: 0, b: 0 }, black));
+
+    let Some(Action::Csi(csi)) = actions[3] else { panic!("expected a control sequence") };
+    assert_eq!((csi.command, csi.private, csi.parameter(0, 1)), ('l', true, 25));
+
+    attributes.select_graphic_rendition(&[0, 31, 7]);
+    assert_eq!(attributes.colours(white, black), (black, palette(1)));
+}
+
+#[test_case]
+fn test_terminals_draw_only_when_shown() {
+    let buffer = vec![0; 64 * 32 * 4].leak();
+    let framebuffer = FramebufferMemory {
+        buffer,
+        width: 64,
+        height: 32,
+        stride: 64,
+        bytes_per_pixel: 4,
+        pixel_format: PixelFormat::Rgb,
+    };
+    let graphics_api = Box::leak(Box::new(GraphicsAPI::new(RefCell::new(framebuffer))));
+    let mut terminals = Terminals::new(graphics_api, 1);
+    assert_eq!(terminals.active(), SHELL);
+
+    // A red block is written to the kernel log, which is not shown yet.
+    terminals.get(KERNEL_LOG).unwrap().write_str("\x1b[41m ");
+    assert!(terminals.get(KERNEL_LOG).unwrap().detach().is_none());
+
+    let alt_f2 = KeyEvent {
+        code: KeyCode::F2,
+        state: KeyState::Pressed,
+        modifiers: Modifiers { left_alt: true, ..Modifiers::default() },
+        character: None,
+    };
+    assert!(terminals.handle_key(&alt_f2));
+    assert_eq!(terminals.active(), KERNEL_LOG);
+
+    let graphics_api = terminals.get(KERNEL_LOG).unwrap().detach().unwrap();
+    assert_eq!(graphics_api.get_pixel(0, 0), Some(Pixel { r: 0xAA, g: 0, b: 0 }));
+    assert_eq!(graphics_api.get_pixel(8, 0), Some(Pixel { r: 0, g: 0, b: 0 }));
+}
+
+#[test_case]
+fn test_scrolling_moves_lines() {
+    let buffer = vec![0; 64 * 32 * 4].leak();
+    let framebuffer = FramebufferMemory {
+        buffer,
+        width: 64,
+        height: 32,
+        stride: 64,
+        bytes_per_pixel: 4,
+        pixel_format: PixelFormat::Rgb,
+    };
+    let graphics_api = Box::leak(Box::new(GraphicsAPI::new(RefCell::new(framebuffer))));
+    graphics_api.enable_back_buffer();
+    let mut console = Console::new(graphics_api, 1);
+    assert_eq!(console.rows(), 4);
+
+    // A red block on the second line is moved to the top by scrolling once.
+    console.write_str("\n\x1b[41m \x1b[0m\n\n\n");
+    let red = Pixel { r: 0xAA, g: 0, b: 0 };
+    let black = Pixel { r: 0, g: 0, b: 0 };
+    let graphics_api = console.detach().unwrap();
+    assert_eq!(graphics_api.get_pixel(0, 0), Some(red));
+    assert_eq!(graphics_api.get_pixel(7, 7), Some(red));
+    assert_eq!(graphics_api.get_pixel(0, 8), Some(black));
+    assert_eq!(graphics_api.get_pixel(0, 24), Some(black));
+}
+
+#[test_case]
+fn test_scrolling_draws_from_the_grid() {
+    let buffer = vec![0; 64 * 32 * 4].leak();
+    let framebuffer = FramebufferMemory {
+        buffer,
+        width: 64,
+        height: 32,
+        stride: 64,
+        bytes_per_pixel: 4,
+        pixel_format: PixelFormat::Rgb,
+    };
+    let graphics_api = Box::leak(Box::new(GraphicsAPI::new(RefCell::new(framebuffer))));
+    graphics_api.enable_back_buffer();
+    let mut console = Console::new(graphics_api, 1);
+
+    // Whatever was drawn over the console is replaced by the lines in the grid once it scrolls.
+    console.write_str("\n\x1b[41m \x1b[0m");
+    let green = Pixel { r: 0, g: 0xAA, b: 0 };
+    console.graphics_api().unwrap().draw_filled_rectangle(0, 0, 64, 32, green);
+    console.write_str("\n\n\n");
+    let red = Pixel { r: 0xAA, g: 0, b: 0 };
+    let black = Pixel { r: 0, g: 0, b: 0 };
+    let graphics_api = console.detach().unwrap();
+    assert_eq!(graphics_api.get_pixel(0, 0), Some(red));
+    assert_eq!(graphics_api.get_pixel(8, 0), Some(black));
+    assert_eq!(graphics_api.get_pixel(0, 8), Some(black));
+    assert_eq!(graphics_api.get_pixel(63, 31), Some(black));
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::cell::RefCell;
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::framebuffer::{FramebufferMemory, Pixel, PixelFormat};
+use gtmos_kernel::graphics::GraphicsAPI;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+const SIZE: usize = 32;
+const GREY: Pixel = Pixel { r: 0x80, g: 0x80, b: 0x80 };
+const RED: Pixel = Pixel { r: 0xFF, g: 0, b: 0 };
+
+#[test_case]
+fn test_cursor_is_drawn_on_the_screen_only() {
+    let mut buffer = [0u8; SIZE * SIZE];
+    let memory = FramebufferMemory {
+        buffer: &mut buffer,
+        width: SIZE,
+        height: SIZE,
+        stride: SIZE,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    let mut graphics_api = GraphicsAPI::new(RefCell::new(memory));
+    graphics_api.enable_back_buffer();
+    graphics_api.draw_filled_rectangle(0, 0, SIZE, SIZE, GREY);
+    graphics_api.present();
+
+    graphics_api.move_cursor(2, 2);
+    graphics_api.move_cursor(15, 5);
+    assert_eq!(graphics_api.cursor().position(), (15, 5));
+    // Drawing under the cursor goes to the back buffer, and the cursor is drawn over it again.
+    graphics_api.draw_filled_rectangle(10, 0, 20, 10, RED);
+    graphics_api.present();
+    assert_eq!(graphics_api.get_pixel(15, 5), Some(RED));
+    drop(graphics_api);
+
+    let grey = PixelFormat::U8.encode(GREY)[0];
+    let red = PixelFormat::U8.encode(RED)[0];
+    // The old place of the cursor was put back.
+    assert_eq!(buffer[2 * SIZE + 2], grey);
+    // The outline at the tip, the fill below it, and the transparent part of the first row.
+    assert_eq!(buffer[5 * SIZE + 15], 0x00);
+    assert_eq!(buffer[7 * SIZE + 16], 0xFF);
+    assert_eq!(buffer[5 * SIZE + 20], red);
+}
+
+#[test_case]
+fn test_cursor_saves_the_screen_without_a_back_buffer() {
+    let mut buffer = [0u8; SIZE * SIZE];
+    let memory = FramebufferMemory {
+        buffer: &mut buffer,
+        width: SIZE,
+        height: SIZE,
+        stride: SIZE,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    let mut graphics_api = GraphicsAPI::new(RefCell::new(memory));
+    graphics_api.draw_filled_rectangle(0, 0, SIZE, SIZE, GREY);
+
+    graphics_api.move_cursor(2, 2);
+    graphics_api.move_cursor(15, 5);
+    // Drawing under the cursor changes the saved pixels, and the cursor stays on top.
+    graphics_api.draw_filled_rectangle(10, 0, 20, 10, RED);
+    assert_eq!(graphics_api.get_pixel(15, 5), Some(RED));
+    drop(graphics_api);
+
+    let grey = PixelFormat::U8.encode(GREY)[0];
+    let red = PixelFormat::U8.encode(RED)[0];
+    assert_eq!(buffer[2 * SIZE + 2], grey);
+    assert_eq!(buffer[5 * SIZE + 15], 0x00);
+    assert_eq!(buffer[7 * SIZE + 16], 0xFF);
+    assert_eq!(buffer[5 * SIZE + 20], red);
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::devices::{self, CharDevice, Device, DeviceClass, DeviceError};
+use gtmos_kernel::drivers::serial;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+struct Null;
+
+impl CharDevice for Null {
+    fn read(&self, _buffer: &mut [u8]) -> Result<usize, DeviceError> {
+        Ok(0)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize, DeviceError> {
+        Ok(data.len())
+    }
+}
+
+static NULL: Null = Null;
+
+#[test_case]
+fn test_registry() {
+    let id = devices::register("null", Device::Char(&NULL)).unwrap();
+    assert_eq!(id.name(), "null");
+    let again = devices::register("null", Device::Char(&NULL));
+    assert_eq!(again.unwrap_err(), DeviceError::AlreadyRegistered);
+    assert_eq!(devices::register("", Device::Char(&NULL)).unwrap_err(), DeviceError::InvalidName);
+
+    assert_eq!(devices::lookup(DeviceClass::Char, "null").unwrap().class(), DeviceClass::Char);
+    assert_eq!(devices::char_device("null").and_then(|null| null.write(b"abc")), Ok(3));
+    assert_eq!(devices::lookup(DeviceClass::Char, "nul").unwrap_err(), DeviceError::NotFound);
+    assert_eq!(
+        devices::block_device("null").err(),
+        Some(DeviceError::WrongClass { expected: DeviceClass::Block, found: DeviceClass::Char })
+    );
+
+    assert!(devices::unregister(&id).is_some());
+    assert_eq!(devices::lookup(DeviceClass::Char, "null").unwrap_err(), DeviceError::NotFound);
+}
+
+#[test_case]
+fn test_sub_system_registers_serial() {
+    let mut found = false;
+    devices::for_each(|id, device| {
+        found |= id.name() == serial::DEVICE_NAME && device.class() == DeviceClass::Char;
+    });
+    assert!(found);
+}
@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::memory::frame::{
+    FrameAllocator, FrameError, FrameStats, MemoryRegion, MemoryRegionKind, PhysFrame,
+};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+/// Creates an allocator for a small fake memory map whose bitmaps live in a static buffer.
+fn test_allocator() -> FrameAllocator {
+    static mut STORAGE: [u64; 512] = [0; 512];
+    let regions = [
+        MemoryRegion { start: 0x0000, end: 0x1000, kind: MemoryRegionKind::Reserved },
+        MemoryRegion { start: 0x1000, end: 0x9000, kind: MemoryRegionKind::Usable },
+        MemoryRegion { start: 0x9000, end: 0xA000, kind: MemoryRegionKind::Reserved },
+        MemoryRegion { start: 0xA000, end: 0xC000, kind: MemoryRegionKind::Usable },
+    ];
+    unsafe {
+        // Physical address 0x1000 (where the bitmaps go) maps to the start of `STORAGE`.
+        let offset = core::ptr::addr_of_mut!(STORAGE) as u64 - 0x1000;
+        FrameAllocator::new(regions.iter().copied(), offset).unwrap()
+    }
+}
+
+#[test_case]
+fn test_frame_allocator_stats() {
+    let allocator = test_allocator();
+    // 0x1000..0xC000 is managed, 0x9000 is a hole and 0x1000 holds the bitmaps.
+    assert_eq!(allocator.stats(), FrameStats { total: 11, free: 9, reserved: 2 });
+}
+
+#[test_case]
+fn test_frame_allocator_allocate_and_free() {
+    let mut allocator = test_allocator();
+    let mut frames = [PhysFrame::containing_address(0); 9];
+    for frame in frames.iter_mut() {
+        *frame = allocator.allocate().unwrap();
+        assert_ne!(frame.start_address(), 0x1000);
+        assert_ne!(frame.start_address(), 0x9000);
+    }
+    assert_eq!(allocator.allocate(), None);
+    assert_eq!(allocator.stats().used(), 9);
+
+    assert_eq!(allocator.deallocate(frames[3]), Ok(()));
+    assert_eq!(allocator.deallocate(frames[3]), Err(FrameError::NotAllocated));
+    assert_eq!(allocator.allocate(), Some(frames[3]));
+
+    let hole = PhysFrame::containing_address(0x9abc);
+    assert_eq!(allocator.deallocate(hole), Err(FrameError::Reserved));
+    let outside = PhysFrame::containing_address(0x20000);
+    assert_eq!(allocator.deallocate(outside), Err(FrameError::OutOfRange));
+}
+
+#[test_case]
+fn test_frame_allocator_contiguous() {
+    let mut allocator = test_allocator();
+    // The hole at 0x9000 splits the usable memory into runs of 7 and 2 frames.
+    assert_eq!(allocator.allocate_contiguous(8), None);
+    let run = allocator.allocate_contiguous(7).unwrap();
+    assert_eq!(run.start_address(), 0x2000);
+    assert_eq!(allocator.stats().free, 2);
+}
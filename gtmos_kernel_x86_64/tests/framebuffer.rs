@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::framebuffer::{
+    Framebuffer, FramebufferIndex, FramebufferMemory, Pixel, PixelFormat,
+};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_pixel_formats() {
+    let pixel = Pixel { r: 0x11, g: 0x22, b: 0x33 };
+    assert_eq!(PixelFormat::Rgb.encode(pixel)[..3], [0x11, 0x22, 0x33]);
+    assert_eq!(PixelFormat::Bgr.encode(pixel)[..3], [0x33, 0x22, 0x11]);
+
+    let bitmask = PixelFormat::Unknown { red_position: 11, green_position: 5, blue_position: 0 };
+    let decoded = bitmask.decode(&bitmask.encode(Pixel { r: 0x1F, g: 0x3F, b: 0x1F })[..2]);
+    assert_eq!((decoded.r, decoded.g, decoded.b), (0x1F, 0x3F, 0x1F));
+}
+
+#[test_case]
+fn test_stride_is_used() {
+    let mut buffer = [0u8; 4 * 3 * 2];
+    let mut fb = FramebufferMemory {
+        buffer: &mut buffer,
+        width: 2,
+        height: 2,
+        stride: 3,
+        bytes_per_pixel: 4,
+        pixel_format: PixelFormat::Bgr,
+    };
+    Framebuffer::set_pixel(&mut fb, Pixel { r: 0xFF, g: 0, b: 0 }, FramebufferIndex { x: 0, y: 1 });
+    assert_eq!(buffer[12..16], [0, 0, 0xFF, 0]);
+}
+
+#[test_case]
+fn test_fill_and_move_rect() {
+    let mut buffer = [0u8; 5 * 4];
+    let mut fb = FramebufferMemory {
+        buffer: &mut buffer,
+        width: 4,
+        height: 4,
+        stride: 5,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    let white = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+    // Only the part inside the framebuffer is filled, the padding of each row is left alone.
+    Framebuffer::fill_rect(&mut fb, 1, 1, 10, 10, white);
+    assert_eq!(fb.buffer[5..10], [0, 0xFF, 0xFF, 0xFF, 0]);
+
+    // Moving the rows down by one overlaps the source.
+    Framebuffer::blit_from(&mut fb, 0, 0, 2, 1, &[Pixel { r: 1, g: 1, b: 1 }, white]);
+    assert_eq!(Framebuffer::move_rect(&mut fb, 0, 0, 4, 4, 0, 1), (4, 3));
+    assert_eq!(fb.buffer[5..10], [1, 0xFF, 0, 0, 0]);
+    assert_eq!(fb.buffer[10..15], [0, 0xFF, 0xFF, 0xFF, 0]);
+}
+
+#[test_case]
+fn test_rects_are_clipped_to_a_short_buffer() {
+    // The buffer ends in the middle of the third row.
+    let mut buffer = [0u8; 5 * 2 + 3];
+    let mut fb = FramebufferMemory {
+        buffer: &mut buffer,
+        width: 4,
+        height: 4,
+        stride: 5,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    let white = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+    // Only the rows which fit are drawn, and a row which does not fit is not drawn at all.
+    Framebuffer::fill_rect(&mut fb, 0, 0, 4, 4, white);
+    assert_eq!(fb.buffer[10..13], [0, 0, 0]);
+    Framebuffer::fill_rect(&mut fb, 0, 2, 3, 2, white);
+    assert_eq!(fb.buffer[10..13], [0xFF, 0xFF, 0xFF]);
+    Framebuffer::blit_from(&mut fb, 0, 1, 4, 3, &[Pixel { r: 1, g: 1, b: 1 }; 12]);
+    assert_eq!(fb.buffer[5..10], [1, 1, 1, 1, 0]);
+    assert_eq!(Framebuffer::move_rect(&mut fb, 0, 0, 4, 4, 0, 1), (4, 1));
+}
@@ -0,0 +1,128 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::cell::RefCell;
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::framebuffer::{FramebufferMemory, Pixel, PixelFormat, Rgba};
+use gtmos_kernel::graphics::back_buffer::{DirtyRegion, MAX_DIRTY_RECTS};
+use gtmos_kernel::graphics::blend::BlendMode;
+use gtmos_kernel::graphics::font::{Font, PsfError, PsfFont};
+use gtmos_kernel::graphics::surface::Surface;
+use gtmos_kernel::graphics::{GraphicsAPI, Rect};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+/// The magic number at the start of a PSF1 font.
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+/// The size of the header of a PSF1 font, the glyphs follow it.
+const PSF1_HEADER_SIZE: usize = 4;
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_dirty_rects_merge() {
+    let mut dirty = DirtyRegion::new();
+    dirty.add(Rect::new(0, 0, 1, 1));
+    dirty.add(Rect::new(1, 1, 1, 1));
+    dirty.add(Rect::new(10, 10, 2, 2));
+    assert_eq!(dirty.rects().len(), 2);
+
+    // Joining the two rectangles merges all three.
+    dirty.add(Rect::new(2, 2, 8, 8));
+    assert_eq!(dirty.rects(), [Rect::new(0, 0, 12, 12)]);
+
+    dirty.clear();
+    for i in 0..=MAX_DIRTY_RECTS {
+        dirty.add(Rect::new(i * 4, 0, 1, 1));
+    }
+    assert_eq!(dirty.rects(), [Rect::new(0, 0, MAX_DIRTY_RECTS * 4 + 1, 1)]);
+}
+
+#[test_case]
+fn test_nested_surfaces() {
+    let screen = Surface::screen(100, 100);
+    let panel = screen.child(Rect::new(50, 50, 100, 100));
+    assert_eq!(panel.clip, Rect::new(50, 50, 50, 50));
+
+    let button = panel.child(Rect::new(40, 0, 20, 20));
+    assert_eq!(button.area, Rect::new(90, 50, 20, 20));
+    assert_eq!(button.clip, Rect::new(90, 50, 10, 20));
+    assert_eq!(button.point_to_screen(5, 5), Some((95, 55)));
+    assert_eq!(button.point_to_screen(15, 5), None);
+    assert_eq!(button.to_screen(Rect::new(0, 10, 100, 100)), Rect::new(90, 60, 10, 10));
+}
+
+#[test_case]
+fn test_surface_clips_drawing() {
+    let mut buffer = [0u8; 8 * 8];
+    let memory = FramebufferMemory {
+        buffer: &mut buffer,
+        width: 8,
+        height: 8,
+        stride: 8,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    let mut graphics_api = GraphicsAPI::new(RefCell::new(memory));
+    let white = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+    graphics_api.with_surface(Rect::new(2, 2, 4, 4), |panel| {
+        panel.with_surface(Rect::new(2, 2, 10, 10), |inner| {
+            assert_eq!(inner.get_width(), 10);
+            inner.draw_filled_rectangle(0, 0, 100, 100, white);
+            inner.draw_line(0, 0, 0, 5, white);
+        });
+    });
+
+    assert_eq!(graphics_api.get_pixel(4, 4), Some(white));
+    assert_eq!(graphics_api.get_pixel(5, 5), Some(white));
+    assert_eq!(graphics_api.get_pixel(6, 6).map(|pixel| pixel.r), Some(0));
+    assert_eq!(graphics_api.get_pixel(4, 6).map(|pixel| pixel.r), Some(0));
+}
+
+#[test_case]
+fn test_blend_modes() {
+    let grey = Pixel { r: 0x80, g: 0x80, b: 0x80 };
+    let white = Rgba::new(0xFF, 0xFF, 0xFF, 0xFF);
+
+    assert_eq!(BlendMode::SourceOver.blend(white, grey), white.pixel());
+    assert_eq!(BlendMode::SourceOver.blend(Rgba { a: 0, ..white }, grey), grey);
+    assert_eq!(BlendMode::SourceOver.blend(Rgba::new(0, 0, 0, 0x80), grey).r, 0x40);
+    assert_eq!(BlendMode::Additive.blend(white, grey), white.pixel());
+    let multiplied = BlendMode::Multiply.blend(Rgba::new(0x80, 0xFF, 0, 0xFF), grey);
+    assert_eq!(multiplied, Pixel { r: 0x40, g: 0x80, b: 0 });
+}
+
+#[test_case]
+fn test_parse_psf1_without_table() {
+    // A 256 glyph PSF1 font with 2 rows per glyph, where glyph `A` is a bar in the second row.
+    let mut data = [0u8; PSF1_HEADER_SIZE + 256 * 2];
+    data[..4].copy_from_slice(&[PSF1_MAGIC[0], PSF1_MAGIC[1], 0, 2]);
+    data[PSF1_HEADER_SIZE + 'A' as usize * 2 + 1] = 0b1000_0001;
+
+    let font = PsfFont::parse(&data).unwrap();
+    assert_eq!((font.width(), font.height(), font.glyph_count()), (8, 2, 256));
+    let glyph = font.glyph('A').unwrap();
+    assert!(glyph.is_set(0, 1) && glyph.is_set(7, 1) && !glyph.is_set(1, 1) && !glyph.is_set(0, 0));
+    assert!(font.glyph('€').is_none());
+    assert_eq!(PsfFont::parse(&data[..100]).unwrap_err(), PsfError::Truncated);
+    assert_eq!(PsfFont::parse(&[0; 4]).unwrap_err(), PsfError::InvalidMagic);
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+use gtmos_kernel::memory::heap::{heap_stats, HEAP_SIZE};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_simple_allocation() {
+    let heap_value_1 = Box::new(41);
+    let heap_value_2 = Box::new(13);
+    assert_eq!(*heap_value_1, 41);
+    assert_eq!(*heap_value_2, 13);
+}
+
+#[test_case]
+fn test_large_vec() {
+    let n = 1000;
+    let mut vec = Vec::new();
+    for i in 0..n {
+        vec.push(i);
+    }
+    assert_eq!(vec.iter().sum::<u64>(), (n - 1) * n / 2);
+}
+
+#[test_case]
+fn test_many_boxes() {
+    // Allocates more memory than the heap holds in total, so freed memory must be reused.
+    for i in 0..HEAP_SIZE / 8 {
+        let x = Box::new(i);
+        assert_eq!(*x, i);
+    }
+}
+
+#[test_case]
+fn test_string_and_btree_map() {
+    let mut map = BTreeMap::new();
+    map.insert(String::from("serial"), 1);
+    map.insert(String::from("vga_console"), 2);
+    assert_eq!(map.get("vga_console"), Some(&2));
+    assert_eq!(map.len(), 2);
+}
+
+#[test_case]
+fn test_heap_stats() {
+    let before = heap_stats().used;
+    let boxed = Box::new([0u8; 256]);
+    assert!(heap_stats().used >= before + boxed.len());
+    drop(boxed);
+    assert_eq!(heap_stats().used, before);
+}
@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::framebuffer::Rgba;
+use gtmos_kernel::graphics::image::{self, ImageError};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+const RED: Rgba = Rgba::new(0xFF, 0, 0, 0xFF);
+const BLUE: Rgba = Rgba::new(0, 0, 0xFF, 0xFF);
+
+#[test_case]
+fn test_qoi() {
+    let mut data = Vec::from(*b"qoif\0\0\0\x02\0\0\0\x01\x04\0");
+    // A red pixel, then a run of one more.
+    data.extend_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xC0]);
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+
+    let bitmap = image::decode(&data).unwrap();
+    assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+    assert_eq!(bitmap.pixels(), [RED, RED]);
+    assert_eq!(image::decode(&data[..16]), Err(ImageError::Truncated));
+}
+
+#[test_case]
+fn test_tga() {
+    // A 2x2 true colour image stored from the bottom row, in blue, green, red order.
+    let mut data = Vec::from([0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0]);
+    data.extend_from_slice(&[0xFF, 0, 0, 0xFF, 0, 0, 0, 0, 0xFF, 0, 0, 0xFF]);
+    let bitmap = image::decode(&data).unwrap();
+    assert_eq!(bitmap.pixels(), [RED, RED, BLUE, BLUE]);
+
+    // The same image, run-length encoded.
+    let mut data = Vec::from([0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0]);
+    data.extend_from_slice(&[0x81, 0xFF, 0, 0, 0x81, 0, 0, 0xFF]);
+    assert_eq!(image::decode(&data).unwrap(), bitmap);
+}
+
+#[test_case]
+fn test_bmp() {
+    // A 2x2 24 bit bitmap, its rows are stored from the bottom and padded to 8 bytes.
+    let mut data = Vec::from(*b"BM");
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&54u32.to_le_bytes());
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&2u32.to_le_bytes());
+    data.extend_from_slice(&2u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&24u16.to_le_bytes());
+    data.extend_from_slice(&[0; 24]);
+    data.extend_from_slice(&[0xFF, 0, 0, 0xFF, 0, 0, 0, 0]);
+    data.extend_from_slice(&[0, 0, 0xFF, 0, 0, 0xFF, 0, 0]);
+
+    let bitmap = image::decode(&data).unwrap();
+    assert_eq!(bitmap.pixels(), [RED, RED, BLUE, BLUE]);
+    assert_eq!(bitmap.get(2, 0), None);
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use gtmos_kernel::irq::{self, IrqError};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_register_vector() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    fn handler(vector: u8) {
+        assert_eq!(vector, 0xF0);
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    assert_eq!(irq::register_vector(14, handler), Err(IrqError::InvalidVector));
+    assert_eq!(irq::register_vector(255, handler), Err(IrqError::InvalidVector));
+    assert_eq!(irq::register_vector(0xF0, handler), Ok(()));
+    assert_eq!(irq::register_vector(0xF0, handler), Err(IrqError::AlreadyRegistered));
+
+    irq::dispatch(0xF0);
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+    assert!(irq::unregister_vector(0xF0).is_some());
+    irq::dispatch(0xF0);
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+}
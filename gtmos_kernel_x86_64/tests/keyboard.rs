@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::keyboard::scancode::{ScancodeDecoder, ScancodeSet};
+use gtmos_kernel::drivers::keyboard::{keymap, KeyCode, KeyState, Keyboard, Modifiers};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel::ring_buffer::RingBuffer;
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_decode_set_1() {
+    let mut decoder = ScancodeDecoder::new(ScancodeSet::Set1);
+    assert_eq!(decoder.decode(0x1E), Some((KeyCode::A, KeyState::Pressed)));
+    assert_eq!(decoder.decode(0x9E), Some((KeyCode::A, KeyState::Released)));
+    assert_eq!(decoder.decode(0xE0), None);
+    assert_eq!(decoder.decode(0x48), Some((KeyCode::ArrowUp, KeyState::Pressed)));
+    assert_eq!(decoder.decode(0xAA), Some((KeyCode::LeftShift, KeyState::Released)));
+}
+
+#[test_case]
+fn test_decode_set_2() {
+    let mut decoder = ScancodeDecoder::new(ScancodeSet::Set2);
+    assert_eq!(decoder.decode(0x1C), Some((KeyCode::A, KeyState::Pressed)));
+    assert_eq!(decoder.decode(0xF0), None);
+    assert_eq!(decoder.decode(0x1C), Some((KeyCode::A, KeyState::Released)));
+    assert_eq!(decoder.decode(0xE0), None);
+    assert_eq!(decoder.decode(0xF0), None);
+    assert_eq!(decoder.decode(0x11), Some((KeyCode::RightAlt, KeyState::Released)));
+
+    // Pause is reported once, the rest of its sequence is skipped.
+    let pause = [0xE1, 0x14, 0x77, 0xE1, 0xF0, 0x14, 0xF0, 0x77];
+    let events = pause.iter().filter_map(|byte| decoder.decode(*byte)).count();
+    assert_eq!(events, 1);
+    assert_eq!(decoder.decode(0x1C), Some((KeyCode::A, KeyState::Pressed)));
+}
+
+#[test_case]
+fn test_us_and_uk_differ() {
+    let shift = Modifiers { left_shift: true, ..Modifiers::default() };
+    assert_eq!(keymap::US.character(KeyCode::Key2, shift), Some('@'));
+    assert_eq!(keymap::UK.character(KeyCode::Key2, shift), Some('"'));
+    assert_eq!(keymap::UK.character(KeyCode::Key3, shift), Some('£'));
+    assert_eq!(keymap::US.character(KeyCode::NonUsBackslash, Modifiers::default()), None);
+
+    let caps = Modifiers { caps_lock: true, ..Modifiers::default() };
+    assert_eq!(keymap::UK.character(KeyCode::Q, caps), Some('Q'));
+    assert_eq!(keymap::UK.character(KeyCode::Key1, caps), Some('1'));
+}
+
+#[test_case]
+fn test_shift_and_control() {
+    let mut keyboard = Keyboard::new(ScancodeSet::Set1);
+    let mut typed = |byte| keyboard.process(byte, &keymap::UK).and_then(|event| event.character);
+
+    assert_eq!(typed(0x1E), Some('a'));
+    assert_eq!(typed(0x2A), None);
+    assert_eq!(typed(0x1E), Some('A'));
+    assert_eq!(typed(0x04), Some('£'));
+    assert_eq!(typed(0xAA), None);
+    assert_eq!(typed(0x1D), None);
+    assert_eq!(typed(0x2E), Some('\u{3}'));
+    assert_eq!(typed(0x9D), None);
+    assert_eq!(typed(0x4F), Some('1'));
+}
+
+#[test_case]
+fn test_ring_buffer_wraps() {
+    let queue: RingBuffer<u8, 4> = RingBuffer::new();
+    for round in 0..3 {
+        for value in 0..4 {
+            assert_eq!(queue.push(round * 4 + value), Ok(()));
+        }
+        assert_eq!(queue.push(0xFF), Err(0xFF));
+        assert_eq!(queue.len(), 4);
+        for value in 0..4 {
+            assert_eq!(queue.pop(), Some(round * 4 + value));
+        }
+        assert_eq!(queue.pop(), None);
+    }
+}
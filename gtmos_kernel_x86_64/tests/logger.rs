@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+use gtmos_kernel::logger::{self, DMESG_LENGTH, MESSAGE_LENGTH};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+/// Logs warnings, debug records from the drivers except the PS/2 controller, and ignores `x=loud`
+/// as it is not a level.
+const COMMAND_LINE: &str =
+    "quiet log=warn,gtmos_kernel::drivers=debug,gtmos_kernel::drivers::i8042=off,x=loud";
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    logger::initialise(COMMAND_LINE).expect("The logger was already initialised");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+/// Returns the messages of every record kept by the logger, from the oldest.
+fn messages() -> Vec<String> {
+    let mut messages = Vec::new();
+    logger::dmesg(|entry| messages.push(String::from(entry.message())));
+    messages
+}
+
+#[test_case]
+fn test_filters() {
+    log::warn!(target: "gtmos_kernel::memory", "a");
+    log::info!(target: "gtmos_kernel::memory", "b");
+    log::debug!(target: "gtmos_kernel::drivers::keyboard", "c");
+    log::error!(target: "gtmos_kernel::drivers::i8042", "d");
+    log::info!(target: "gtmos_kernel::drivers_extra", "e");
+    assert_eq!(log::max_level(), log::LevelFilter::Debug);
+
+    let messages = messages();
+    assert_eq!(messages[messages.len() - 2..], ["a", "c"]);
+}
+
+#[test_case]
+fn test_dmesg_keeps_the_newest() {
+    for i in 0..DMESG_LENGTH + 6 {
+        log::warn!("{}", i);
+    }
+    let messages = messages();
+    assert_eq!(messages.len(), DMESG_LENGTH);
+    assert_eq!(messages[0], "6");
+    assert_eq!(messages[DMESG_LENGTH - 1], alloc::format!("{}", DMESG_LENGTH + 5));
+}
+
+#[test_case]
+fn test_long_messages_end_on_a_character() {
+    // The two bytes of `é` do not fit after the `x`s, so it is left out.
+    log::warn!("{:x<2$}{}", "", "\u{e9}c", MESSAGE_LENGTH - 1);
+    let message = messages().pop().unwrap();
+    assert_eq!(message.len(), MESSAGE_LENGTH - 1);
+    assert!(message.bytes().all(|byte| byte == b'x'));
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::mouse::{
+    Mouse, PacketDecoder, PACKET_ALWAYS_ONE, PACKET_LEFT, PACKET_X_SIGN,
+};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_packets_move_and_clamp() {
+    let mut mouse = Mouse::new(false, 100, 50);
+
+    // Left button held, moved 10 right and 5 up.
+    let bytes = [PACKET_ALWAYS_ONE | PACKET_LEFT, 10, 5];
+    let events = bytes.iter().filter_map(|byte| mouse.process(*byte));
+    let event = events.last().unwrap();
+    assert_eq!((event.x, event.y), (60, 20));
+    assert!(event.buttons.left);
+
+    // Moved 255 left, which is past the edge of the screen.
+    let bytes = [PACKET_ALWAYS_ONE | PACKET_X_SIGN, 0x01, 0];
+    let events = bytes.iter().filter_map(|byte| mouse.process(*byte));
+    let event = events.last().unwrap();
+    assert_eq!((event.x, event.dx), (0, -255));
+
+    // A byte without the always one bit is skipped while waiting for a packet.
+    assert_eq!(mouse.process(0x00), None);
+}
+
+#[test_case]
+fn test_intellimouse_wheel() {
+    let mut decoder = PacketDecoder::new(true);
+    let packet = [PACKET_ALWAYS_ONE, 0, 0, 0x0F]
+        .iter()
+        .filter_map(|byte| decoder.decode(*byte))
+        .last()
+        .unwrap();
+    assert_eq!(packet.dz, -1);
+}
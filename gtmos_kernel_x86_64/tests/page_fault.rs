@@ -0,0 +1,94 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::memory::fault::{register_region, region_at, Region, RegionError, RegionKind};
+use gtmos_kernel::memory::paging::{PageFlags, PageSize, VirtualMemory};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::paging::PAGE_TABLE;
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+const ANONYMOUS_START: u64 = 0x_6666_0000_0000;
+const STACK_START: u64 = 0x_6666_1000_0000;
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+fn is_mapped(address: u64) -> bool {
+    PAGE_TABLE.lock().as_ref().unwrap().translate(address).is_some()
+}
+
+#[test_case]
+fn test_anonymous_region_is_backed_on_demand() {
+    register_region(Region {
+        start: ANONYMOUS_START,
+        end: ANONYMOUS_START + 16 * 4096,
+        kind: RegionKind::Anonymous,
+        flags: PageFlags::WRITABLE | PageFlags::NO_EXECUTE,
+        name: "test anonymous region",
+    })
+    .unwrap();
+
+    let address = ANONYMOUS_START + 3 * 4096 + 8;
+    assert!(!is_mapped(address));
+    unsafe {
+        assert_eq!((address as *const u64).read_volatile(), 0);
+        (address as *mut u64).write_volatile(42);
+        assert_eq!((address as *const u64).read_volatile(), 42);
+    }
+    assert!(is_mapped(address));
+    assert!(!is_mapped(ANONYMOUS_START));
+}
+
+#[test_case]
+fn test_stack_region_grows_down() {
+    let end = STACK_START + 8 * 4096;
+    register_region(Region {
+        start: STACK_START,
+        end,
+        kind: RegionKind::Stack,
+        flags: PageFlags::WRITABLE | PageFlags::NO_EXECUTE,
+        name: "test stack",
+    })
+    .unwrap();
+
+    // Walk down from the top of the stack, stopping above the guard page.
+    let mut address = end - 8;
+    while address >= STACK_START + 4096 {
+        unsafe { (address as *mut u64).write_volatile(address) };
+        address -= PageSize::Size4KiB.bytes();
+    }
+    assert!(is_mapped(STACK_START + 4096));
+    assert!(!is_mapped(STACK_START));
+}
+
+#[test_case]
+fn test_overlapping_regions_are_rejected() {
+    let region = Region {
+        start: ANONYMOUS_START + 4096,
+        end: ANONYMOUS_START + 2 * 4096,
+        kind: RegionKind::Guard,
+        flags: PageFlags::empty(),
+        name: "test overlap",
+    };
+    assert_eq!(register_region(region), Err(RegionError::Overlap));
+    assert_eq!(region_at(ANONYMOUS_START + 4096).unwrap().name, "test anonymous region");
+}
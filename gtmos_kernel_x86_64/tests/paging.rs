@@ -0,0 +1,114 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::memory::frame::allocate_frame;
+use gtmos_kernel::memory::paging::{MapError, PageFlags, PageSize, VirtualMemory};
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::paging::PAGE_TABLE;
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+/// An unused virtual address for 4 KiB test pages.
+const TEST_ADDRESS: u64 = 0x_5555_0000_0000;
+/// An unused virtual address for 2 MiB test pages. It must not share a page table with
+/// `TEST_ADDRESS`.
+const HUGE_TEST_ADDRESS: u64 = 0x_5555_4000_0000;
+/// An unused virtual address for 1 GiB test pages, in its own entry of the level 3 table.
+const GIANT_TEST_ADDRESS: u64 = 0x_5555_8000_0000;
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+#[test_case]
+fn test_map_translate_unmap() {
+    let mut page_table = PAGE_TABLE.lock();
+    let page_table = page_table.as_mut().unwrap();
+    let frame = allocate_frame().unwrap().start_address();
+    let flags = PageFlags::WRITABLE | PageFlags::NO_EXECUTE;
+
+    page_table.map(TEST_ADDRESS, frame, PageSize::Size4KiB, flags).unwrap();
+    unsafe { (TEST_ADDRESS as *mut u64).write_volatile(0xdead_beef) };
+    assert_eq!(unsafe { (TEST_ADDRESS as *const u64).read_volatile() }, 0xdead_beef);
+
+    let translation = page_table.translate(TEST_ADDRESS + 8).unwrap();
+    assert_eq!(translation.physical_address, frame + 8);
+    assert_eq!(translation.size, PageSize::Size4KiB);
+    assert_eq!(translation.flags, flags);
+
+    assert_eq!(
+        page_table.map(TEST_ADDRESS, frame, PageSize::Size4KiB, flags),
+        Err(MapError::AlreadyMapped)
+    );
+    assert_eq!(page_table.unmap(TEST_ADDRESS, PageSize::Size4KiB), Ok(frame));
+    assert_eq!(page_table.translate(TEST_ADDRESS), None);
+}
+
+#[test_case]
+fn test_protect() {
+    let mut page_table = PAGE_TABLE.lock();
+    let page_table = page_table.as_mut().unwrap();
+    let frame = allocate_frame().unwrap().start_address();
+
+    page_table.map(TEST_ADDRESS, frame, PageSize::Size4KiB, PageFlags::WRITABLE).unwrap();
+    page_table.protect(TEST_ADDRESS, PageSize::Size4KiB, PageFlags::CACHE_DISABLE).unwrap();
+    let flags = page_table.translate(TEST_ADDRESS).unwrap().flags;
+    assert!(flags.contains(PageFlags::CACHE_DISABLE));
+    assert!(!flags.contains(PageFlags::WRITABLE));
+    page_table.unmap(TEST_ADDRESS, PageSize::Size4KiB).unwrap();
+}
+
+#[test_case]
+fn test_huge_page() {
+    let mut page_table = PAGE_TABLE.lock();
+    let page_table = page_table.as_mut().unwrap();
+
+    // Map the first 2 MiB of physical memory, read-only, as a single huge page.
+    assert_eq!(
+        page_table.map(HUGE_TEST_ADDRESS + 4096, 0, PageSize::Size2MiB, PageFlags::empty()),
+        Err(MapError::NotAligned)
+    );
+    page_table.map(HUGE_TEST_ADDRESS, 0, PageSize::Size2MiB, PageFlags::empty()).unwrap();
+    let translation = page_table.translate(HUGE_TEST_ADDRESS + 0x1234).unwrap();
+    assert_eq!(translation.physical_address, 0x1234);
+    assert_eq!(translation.size, PageSize::Size2MiB);
+    assert_eq!(page_table.unmap(HUGE_TEST_ADDRESS, PageSize::Size2MiB), Ok(0));
+}
+
+#[test_case]
+fn test_giant_page_translates() {
+    let mut page_table = PAGE_TABLE.lock();
+    let page_table = page_table.as_mut().unwrap();
+
+    // The page is only looked up, never touched, so the CPU does not need 1 GiB page support.
+    page_table.map(GIANT_TEST_ADDRESS, 0, PageSize::Size1GiB, PageFlags::empty()).unwrap();
+    let translation = page_table.translate(GIANT_TEST_ADDRESS + 0x20_1234).unwrap();
+    assert_eq!(translation.physical_address, 0x20_1234);
+    assert_eq!(translation.size, PageSize::Size1GiB);
+    assert_eq!(page_table.unmap(GIANT_TEST_ADDRESS, PageSize::Size1GiB), Ok(0));
+}
+
+#[test_case]
+fn test_sub_system_virtual_memory() {
+    // A second SubSystem is created without initialising it again, it shares the page tables.
+    let system = X86_64SubSystem {};
+    let heap_start = gtmos_kernel::memory::heap::HEAP_START as u64;
+    assert!(system.translate(heap_start).is_some());
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use gtmos_kernel::devices;
+use gtmos_kernel::drivers::serial;
+use gtmos_kernel::irq;
+use gtmos_kernel::memory::heap::HEAP_START;
+use gtmos_kernel::platform::{self, Platform, PlatformError, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+/// A vector which is not used by the SubSystem, it is raised with the `int` instruction.
+const TEST_VECTOR: u8 = 0xF1;
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+/// Returns whether the SubSystem finds the page of the heap.
+fn translate_heap() -> Option<bool> {
+    platform::with_sub_system(|sub_system| sub_system.virtual_memory().translate(HEAP_START as u64).is_some())
+}
+
+#[test_case]
+fn test_sub_system_is_kept() {
+    assert_eq!(translate_heap(), Some(true));
+    assert_eq!(translate_heap(), Some(true));
+    assert_eq!(set_platform(Platform::new(X86_64SubSystem {})), Err(PlatformError::AlreadySet));
+    assert_eq!(translate_heap(), Some(true));
+}
+
+#[test_case]
+fn test_print_from_interrupt() {
+    static PRINTED: AtomicBool = AtomicBool::new(false);
+    static USED_SUB_SYSTEM: AtomicBool = AtomicBool::new(false);
+    fn handler(_vector: u8) {
+        gtmos_kernel::serial_print!("[interrupt] ");
+        let message = b"[interrupt] ";
+        let written = devices::char_device(serial::DEVICE_NAME).and_then(|device| device.write(message));
+        PRINTED.store(written == Ok(message.len()), Ordering::Relaxed);
+        USED_SUB_SYSTEM.store(translate_heap() == Some(true), Ordering::Relaxed);
+    }
+
+    irq::register_vector(TEST_VECTOR, handler).unwrap();
+    unsafe { core::arch::asm!("int 0xF1") };
+    irq::unregister_vector(TEST_VECTOR);
+    assert!(PRINTED.load(Ordering::Relaxed));
+    assert!(USED_SUB_SYSTEM.load(Ordering::Relaxed));
+
+    // Printing keeps working after the interrupt.
+    let written = devices::char_device(serial::DEVICE_NAME).and_then(|device| device.write(b"[after] "));
+    assert_eq!(written, Ok(8));
+    assert_eq!(translate_heap(), Some(true));
+}
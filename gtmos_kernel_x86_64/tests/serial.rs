@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use gtmos_kernel::devices;
+use gtmos_kernel::drivers::serial;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::apic;
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+use x86_64::instructions::port::Port;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+/// The modem control register of COM1.
+const MODEM_CONTROL: u16 = 0x3F8 + 4;
+/// Set in the modem control register to connect the transmitter of the UART to its receiver.
+const LOOPBACK: u8 = 1 << 4;
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    // COM1 is attached to IRQ 4 before the controller is chosen, as in the kernel.
+    let rsdp_address = boot_info.rsdp_addr.into_option();
+    unsafe { gtmos_kernel_x86_64::interrupts::initialise_controller(rsdp_address) };
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+/// Runs `f` with COM1 in loopback mode, where every byte written to it is received by it as well,
+/// and arrives through IRQ 4. Anything received before is dropped.
+fn with_loopback(f: impl FnOnce()) {
+    let mut buffer = [0; 16];
+    while serial::try_read(&mut buffer) > 0 {}
+
+    let mut modem_control: Port<u8> = Port::new(MODEM_CONTROL);
+    let saved = unsafe { modem_control.read() };
+    unsafe { modem_control.write(saved | LOOPBACK) };
+    f();
+    unsafe { modem_control.write(saved) };
+}
+
+/// Sends `data` through the serial device, which waits until COM1 can take each byte.
+fn write(data: &[u8]) {
+    let written = devices::char_device(serial::DEVICE_NAME).and_then(|device| device.write(data));
+    assert_eq!(written, Ok(data.len()));
+}
+
+#[test_case]
+fn test_receive_under_apic() {
+    // QEMU's pc and q35 machines both have a MADT.
+    assert!(apic::is_enabled());
+    let mut buffer = [0; 16];
+    let mut length = 0;
+    with_loopback(|| {
+        write(b"G");
+        // Wait for up to 100 timer ticks.
+        for _ in 0..100 {
+            length = serial::try_read(&mut buffer);
+            if length > 0 {
+                break;
+            }
+            x86_64::instructions::hlt();
+        }
+    });
+    assert_eq!(&buffer[..length], b"G");
+}
+
+#[test_case]
+fn test_read_line_edits() {
+    let mut buffer = [0; 4];
+    let mut length = 0;
+    with_loopback(|| {
+        write(b"abx\x08cde\r");
+        length = serial::read_line(&mut buffer);
+    });
+    // The backspace removes the `x`, and the `e` does not fit.
+    assert_eq!(&buffer[..length], b"abcd");
+}
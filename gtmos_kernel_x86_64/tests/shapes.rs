@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(gtmos_kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::cell::RefCell;
+use core::panic::PanicInfo;
+use gtmos_kernel::drivers::framebuffer::{FramebufferMemory, Pixel, PixelFormat};
+use gtmos_kernel::graphics::GraphicsAPI;
+use gtmos_kernel::graphics::shapes::isqrt;
+use gtmos_kernel::platform::{Platform, set_platform};
+use gtmos_kernel_x86_64::system::X86_64SubSystem;
+
+bootloader_api::entry_point!(kernel_main, config = &gtmos_kernel_x86_64::BOOTLOADER_CONFIG);
+
+fn kernel_main(boot_info: &'static mut bootloader_api::BootInfo) -> ! {
+    let platform = Platform::new(X86_64SubSystem::new());
+    unsafe { gtmos_kernel_x86_64::memory::initialise(boot_info) };
+    set_platform(platform).expect("The platform was already set");
+    test_main();
+
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    gtmos_kernel::test_panic_handler(info)
+}
+
+const WHITE: Pixel = Pixel { r: 0xFF, g: 0xFF, b: 0xFF };
+
+/// Calls `f` with a black 11 by 11 framebuffer, one byte per pixel.
+fn with_graphics_api(f: impl FnOnce(&mut GraphicsAPI)) {
+    let mut buffer = [0u8; 11 * 11];
+    let memory = FramebufferMemory {
+        buffer: &mut buffer,
+        width: 11,
+        height: 11,
+        stride: 11,
+        bytes_per_pixel: 1,
+        pixel_format: PixelFormat::U8,
+    };
+    f(&mut GraphicsAPI::new(RefCell::new(memory)));
+}
+
+fn is_white(graphics_api: &mut GraphicsAPI, x: usize, y: usize) -> bool {
+    graphics_api.get_pixel(x, y) == Some(WHITE)
+}
+
+#[test_case]
+fn test_line_end_point_and_circle() {
+    with_graphics_api(|graphics_api| {
+        graphics_api.draw_line(0, 0, 3, 0, WHITE);
+        assert!(is_white(graphics_api, 3, 0));
+        assert!(!is_white(graphics_api, 4, 0));
+
+        graphics_api.draw_filled_circle(4, 4, 3, WHITE);
+        assert!(is_white(graphics_api, 4, 1) && is_white(graphics_api, 7, 4));
+        assert!(is_white(graphics_api, 4, 4));
+        assert!(!is_white(graphics_api, 8, 4) && !is_white(graphics_api, 1, 1));
+    });
+
+    assert_eq!(isqrt(99), 9);
+    assert_eq!(isqrt(1 << 16), 256);
+}
+
+#[test_case]
+fn test_flat_ellipses() {
+    with_graphics_api(|graphics_api| {
+        graphics_api.draw_ellipse(5, 5, 5, 0, WHITE);
+        assert!((0..11).all(|x| is_white(graphics_api, x, 5)));
+        assert!(!is_white(graphics_api, 5, 4) && !is_white(graphics_api, 5, 6));
+    });
+    with_graphics_api(|graphics_api| {
+        graphics_api.draw_filled_ellipse(5, 5, 0, 3, WHITE);
+        assert!((2..9).all(|y| is_white(graphics_api, 5, y)));
+        assert!(!is_white(graphics_api, 5, 1) && !is_white(graphics_api, 4, 5));
+    });
+}